@@ -0,0 +1,455 @@
+//! Opt-in HTTP/JSON API on 127.0.0.1 for scripts and editor plugins.
+//! Every route except the OpenAPI description requires `Authorization: Bearer <token>`.
+
+mod routes;
+
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, TcpListener as StdTcpListener};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{map_err, AppResult};
+use crate::models::settings::ApiSettings;
+use crate::state::AppState;
+use parking_lot::Mutex;
+use tauri::Manager;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+const MAX_BODY_BYTES: usize = 1024 * 1024;
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A running server. Dropping it leaves the server running; call `stop`.
+pub struct ApiServer {
+    port: u16,
+    token: Arc<Mutex<String>>,
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl ApiServer {
+    fn stop(self) {
+        self.task.abort();
+    }
+}
+
+/// Bring the running server in line with `settings`: start, stop, move to a
+/// new port, or just swap the token.
+pub fn apply_settings(app: &tauri::AppHandle, state: &AppState, settings: &ApiSettings) -> AppResult<()> {
+    let mut slot = state.api_server.lock();
+
+    if settings.enabled && !settings.token.is_empty() {
+        if let Some(server) = slot.as_ref().filter(|s| s.port == settings.port) {
+            *server.token.lock() = settings.token.clone();
+            return Ok(());
+        }
+    }
+
+    if let Some(server) = slot.take() {
+        server.stop();
+    }
+
+    if !settings.enabled {
+        return Ok(());
+    }
+    if settings.token.is_empty() {
+        return Err("The HTTP API needs a token before it can be enabled".to_string());
+    }
+
+    *slot = Some(start(app.clone(), settings)?);
+    Ok(())
+}
+
+fn start(app: tauri::AppHandle, settings: &ApiSettings) -> AppResult<ApiServer> {
+    let listener = bind(settings.port)?;
+    let token = Arc::new(Mutex::new(settings.token.clone()));
+
+    let task_token = token.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                eprintln!("HTTP API failed to start: {}", e);
+                return;
+            }
+        };
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    let app = app.clone();
+                    let token = task_token.lock().clone();
+                    tauri::async_runtime::spawn(handle_connection(app, token, stream));
+                }
+                Err(e) => eprintln!("HTTP API accept failed: {}", e),
+            }
+        }
+    });
+
+    Ok(ApiServer {
+        port: settings.port,
+        token,
+        task,
+    })
+}
+
+/// Bind on loopback only. A just-stopped server may still hold the port for a
+/// moment, so "address in use" is retried briefly.
+fn bind(port: u16) -> AppResult<StdTcpListener> {
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+    let mut attempts = 0;
+    loop {
+        match StdTcpListener::bind(addr) {
+            Ok(listener) => {
+                listener.set_nonblocking(true).map_err(map_err)?;
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == ErrorKind::AddrInUse && attempts < 5 => {
+                attempts += 1;
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) => return Err(format!("Failed to start HTTP API on {}: {}", addr, e)),
+        }
+    }
+}
+
+pub(crate) struct Request {
+    pub method: String,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub authorization: Option<String>,
+    pub body: Vec<u8>,
+}
+
+pub(crate) struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+async fn handle_connection(app: tauri::AppHandle, token: String, stream: TcpStream) {
+    respond(stream, move |request| {
+        routes::route(&app.state::<AppState>(), &token, &request)
+    })
+    .await
+}
+
+/// Read one request, answer it with `handle` and close the connection.
+async fn respond<F>(mut stream: TcpStream, handle: F)
+where
+    F: FnOnce(Request) -> Response + Send + 'static,
+{
+    let response = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut stream)).await {
+        Ok(Ok(request)) => {
+            // Handlers do blocking file IO
+            tauri::async_runtime::spawn_blocking(move || handle(request))
+                .await
+                .unwrap_or_else(|e| routes::error(500, &e.to_string()))
+        }
+        Ok(Err(response)) => response,
+        Err(_) => routes::error(408, "Timed out reading request"),
+    };
+
+    let head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len()
+    );
+    let _ = stream.write_all(head.as_bytes()).await;
+    let _ = stream.write_all(response.body.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Request, Response> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 4096];
+
+    let (mut request, content_length, head_len) = loop {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| routes::error(400, &e.to_string()))?;
+        if read == 0 {
+            return Err(routes::error(400, "Connection closed mid-request"));
+        }
+        buf.extend_from_slice(&chunk[..read]);
+
+        let mut headers = [httparse::EMPTY_HEADER; 32];
+        let mut parsed = httparse::Request::new(&mut headers);
+        match parsed.parse(&buf) {
+            Ok(httparse::Status::Complete(head_len)) => {
+                let (request, content_length) = parse_head(&parsed)?;
+                break (request, content_length, head_len);
+            }
+            Ok(httparse::Status::Partial) if buf.len() < MAX_HEAD_BYTES => continue,
+            Ok(httparse::Status::Partial) => return Err(routes::error(431, "Request head too large")),
+            Err(e) => return Err(routes::error(400, &e.to_string())),
+        }
+    };
+
+    let mut body = buf.split_off(head_len);
+    while body.len() < content_length {
+        let read = stream
+            .read(&mut chunk)
+            .await
+            .map_err(|e| routes::error(400, &e.to_string()))?;
+        if read == 0 {
+            return Err(routes::error(400, "Connection closed mid-body"));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    request.body = body;
+
+    Ok(request)
+}
+
+/// The request without its body, plus the body's Content-Length.
+fn parse_head(parsed: &httparse::Request) -> Result<(Request, usize), Response> {
+    let method = parsed.method.unwrap_or("GET").to_string();
+    let target = parsed.path.unwrap_or("/");
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, query),
+        None => (target, ""),
+    };
+
+    let header = |name: &str| {
+        parsed
+            .headers
+            .iter()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+            .and_then(|h| std::str::from_utf8(h.value).ok())
+            .map(str::to_string)
+    };
+
+    let content_length = match header("content-length") {
+        Some(value) => value
+            .trim()
+            .parse::<usize>()
+            .map_err(|_| routes::error(400, "Invalid Content-Length"))?,
+        None => 0,
+    };
+    if content_length > MAX_BODY_BYTES {
+        return Err(routes::error(413, "Request body too large"));
+    }
+
+    let request = Request {
+        method,
+        path: path.to_string(),
+        query: url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect(),
+        authorization: header("authorization"),
+        body: Vec::new(),
+    };
+    Ok((request, content_length))
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        422 => "Unprocessable Entity",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::usage_log_service;
+    use crate::test_support::{self, TempLibrary};
+    use serde_json::{json, Value};
+    use std::io::{Read, Write};
+
+    const TOKEN: &str = "test-token";
+
+    /// A server on a free port answering from its own library, like the real
+    /// one but without a Tauri app.
+    struct TestServer {
+        port: u16,
+        state: Arc<AppState>,
+        _library: TempLibrary,
+    }
+
+    impl TestServer {
+        fn start(name: &str) -> Self {
+            let library = test_support::temp_paths(name);
+            let state = Arc::new(AppState::new(library.clone()));
+
+            let listener = StdTcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+            listener.set_nonblocking(true).unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server_state = state.clone();
+            tauri::async_runtime::spawn(async move {
+                let listener = TcpListener::from_std(listener).unwrap();
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let state = server_state.clone();
+                    tauri::async_runtime::spawn(respond(stream, move |request| {
+                        routes::route(&state, TOKEN, &request)
+                    }));
+                }
+            });
+            TestServer {
+                port,
+                state,
+                _library: library,
+            }
+        }
+
+        fn add_prompt(&self, name: &str, content: &str) -> String {
+            test_support::add_prompt(&self.state.paths, "", name, content)
+        }
+
+        /// Send a raw request and return the status and the parsed body.
+        fn send(&self, method: &str, path: &str, token: Option<&str>, body: &str) -> (u16, Value) {
+            let mut stream =
+                std::net::TcpStream::connect((Ipv4Addr::LOCALHOST, self.port)).unwrap();
+            let auth = token
+                .map(|t| format!("Authorization: Bearer {}\r\n", t))
+                .unwrap_or_default();
+            write!(
+                stream,
+                "{} {} HTTP/1.1\r\nHost: localhost\r\n{}Content-Length: {}\r\n\r\n{}",
+                method,
+                path,
+                auth,
+                body.len(),
+                body
+            )
+            .unwrap();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            let (head, body) = response.split_once("\r\n\r\n").unwrap();
+            let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+            (status, serde_json::from_str(body).unwrap())
+        }
+
+        fn get(&self, path: &str) -> (u16, Value) {
+            self.send("GET", path, Some(TOKEN), "")
+        }
+
+        fn post(&self, path: &str, body: &str) -> (u16, Value) {
+            self.send("POST", path, Some(TOKEN), body)
+        }
+    }
+
+    #[test]
+    fn only_the_description_is_served_without_the_token() {
+        let server = TestServer::start("auth");
+
+        assert_eq!(server.send("GET", "/v1/openapi.json", None, "").0, 200);
+        assert_eq!(server.send("GET", "/v1/prompts", None, "").0, 401);
+        assert_eq!(server.send("GET", "/v1/prompts", Some("wrong"), "").0, 401);
+        assert_eq!(server.get("/v1/prompts").0, 200);
+    }
+
+    #[test]
+    fn prompts_are_listed_searched_and_fetched() {
+        let server = TestServer::start("read");
+        let id = server.add_prompt("Summarize", "Summarize this");
+        server.add_prompt("Translate", "Translate this");
+
+        let (status, list) = server.get("/v1/prompts");
+        assert_eq!(status, 200);
+        assert_eq!(list.as_array().unwrap().len(), 2);
+
+        let (status, hits) = server.get("/v1/search?q=summ");
+        assert_eq!(status, 200);
+        assert_eq!(hits[0]["id"], id);
+
+        let (status, prompt) = server.get(&format!("/v1/prompts/{}", id));
+        assert_eq!(status, 200);
+        assert_eq!(prompt["content"], "Summarize this");
+
+        assert_eq!(server.get("/v1/prompts/missing").0, 404);
+        assert_eq!(server.get("/v1/nothing").0, 404);
+        assert_eq!(server.send("DELETE", "/v1/prompts", Some(TOKEN), "").0, 405);
+    }
+
+    #[test]
+    fn rendering_fills_variables_and_rejects_broken_templates() {
+        let server = TestServer::start("render");
+        let id = server.add_prompt("Greet", "Hello {{name}}{{#if formal}}, sir{{/if}}");
+        let broken = server.add_prompt("Broken", "{{#if open}}never closed");
+
+        let (status, rendered) = server.post(
+            &format!("/v1/prompts/{}/render", id),
+            r#"{"variables": {"name": "Ada", "formal": "yes"}}"#,
+        );
+        assert_eq!(status, 200);
+        assert_eq!(rendered["content"], "Hello Ada, sir");
+
+        let (status, rendered) = server.post(&format!("/v1/prompts/{}/render", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(rendered["missingVariables"], json!(["name"]));
+
+        let (status, error) = server.post(&format!("/v1/prompts/{}/render", broken), "{}");
+        assert_eq!(status, 422);
+        assert_eq!(error["templateErrors"][0]["line"], 1);
+
+        let (status, _) = server.post(&format!("/v1/prompts/{}/render", id), "{not json");
+        assert_eq!(status, 400);
+    }
+
+    #[test]
+    fn usage_is_recorded() {
+        let server = TestServer::start("usage");
+        let id = server.add_prompt("Summarize", "Summarize this");
+
+        let (status, body) = server.post(&format!("/v1/prompts/{}/usage", id), "");
+        assert_eq!(status, 200);
+        assert_eq!(body["recorded"], true);
+        assert_eq!(server.post("/v1/prompts/missing/usage", "").0, 404);
+
+        let (_, prompt) = server.get(&format!("/v1/prompts/{}", id));
+        assert_eq!(prompt["useCount"], 1);
+    }
+
+    #[test]
+    fn usage_is_logged_for_the_app_in_the_request() {
+        let server = TestServer::start("usage-app");
+        let id = server.add_prompt("Summarize", "Summarize this");
+        // Left over from the launcher; an API caller isn't typing in it
+        *server.state.last_target_app.lock() = Some("slack.exe".to_string());
+
+        let path = format!("/v1/prompts/{}/usage", id);
+        server.post(
+            &path,
+            r#"{"targetApp": "Code.exe", "deliveryMode": "paste"}"#,
+        );
+        server.post(&path, "");
+
+        let log = usage_log_service::read_log(&server.state.paths).unwrap();
+        let apps: Vec<Option<&str>> = log.iter().map(|e| e.target_app.as_deref()).collect();
+        assert_eq!(apps, [Some("code.exe"), None]);
+    }
+
+    #[test]
+    fn concurrent_writes_are_not_lost() {
+        let server = Arc::new(TestServer::start("concurrent"));
+        let id = server.add_prompt("Summarize", "Summarize this");
+
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let server = server.clone();
+                let path = format!("/v1/prompts/{}/usage", id);
+                std::thread::spawn(move || server.post(&path, "").0)
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), 200);
+        }
+
+        let (_, prompt) = server.get(&format!("/v1/prompts/{}", id));
+        assert_eq!(prompt["useCount"], 8);
+    }
+}
@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+use super::{Request, Response};
+use crate::commands::data;
+use crate::models::prompt::TemplateError;
+use crate::models::usage::DeliveryMode;
+use crate::services::{index_service, prompt_service};
+use crate::state::AppState;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+const OPENAPI: &str = include_str!("openapi.json");
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct RenderBody {
+    variables: HashMap<String, String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct UsageBody {
+    delivery_mode: Option<DeliveryMode>,
+    variables_filled: bool,
+    /// Application the prompt was used in, e.g. "code.exe"
+    target_app: Option<String>,
+}
+
+pub(crate) fn route(state: &AppState, token: &str, request: &Request) -> Response {
+    let segments: Vec<&str> = request
+        .path
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    let method = request.method.as_str();
+
+    if segments == ["v1", "openapi.json"] {
+        return match method {
+            "GET" => Response {
+                status: 200,
+                body: OPENAPI.to_string(),
+            },
+            _ => error(405, "Method not allowed"),
+        };
+    }
+
+    if !authorized(request, token) {
+        return error(401, "Missing or invalid bearer token");
+    }
+
+    match (method, segments.as_slice()) {
+        ("GET", ["v1", "prompts"]) => list_prompts(state, request),
+        ("GET", ["v1", "search"]) => search(state, request),
+        ("GET", ["v1", "prompts", id]) => get_prompt(state, id),
+        ("POST", ["v1", "prompts", id, "render"]) => render(state, id, request),
+        ("POST", ["v1", "prompts", id, "usage"]) => record_usage(state, id, request),
+        (_, ["v1", "prompts"]) | (_, ["v1", "search"]) | (_, ["v1", "prompts", ..]) => {
+            error(405, "Method not allowed")
+        }
+        _ => error(404, "Not found"),
+    }
+}
+
+/// `GET /v1/prompts[?folder=]`: metadata of every prompt, optionally in one folder.
+fn list_prompts(state: &AppState, request: &Request) -> Response {
+    let index = match index_service::load_index(&state.paths) {
+        Ok(index) => index,
+        Err(e) => return error(500, &e),
+    };
+    let prompts: Vec<_> = index
+        .prompts
+        .into_iter()
+        .filter(|p| match request.query.get("folder") {
+            Some(folder) => &p.folder == folder,
+            None => true,
+        })
+        .collect();
+    ok(&prompts)
+}
+
+/// `GET /v1/search?q=`: same ranking as the launcher.
+fn search(state: &AppState, request: &Request) -> Response {
+    let query = request.query.get("q").map(String::as_str).unwrap_or("");
+    match data::search_library(state, query) {
+        Ok(hits) => {
+            let results: Vec<_> = hits.into_iter().map(|hit| hit.meta).collect();
+            ok(&results)
+        }
+        Err(e) => error(500, &e),
+    }
+}
+
+/// `GET /v1/prompts/{id}`: metadata and content.
+fn get_prompt(state: &AppState, id: &str) -> Response {
+    let index = match index_service::load_index(&state.paths) {
+        Ok(index) => index,
+        Err(e) => return error(500, &e),
+    };
+    if !index.prompts.iter().any(|p| p.id == id) {
+        return not_found(id);
+    }
+    match prompt_service::load_prompt(&state.paths, &index, id) {
+        Ok(prompt) => ok(&prompt),
+        Err(e) => error(500, &e),
+    }
+}
+
+/// `POST /v1/prompts/{id}/render` with `{"variables": {...}}`. A broken
+/// template is a 422 listing its problems.
+fn render(state: &AppState, id: &str, request: &Request) -> Response {
+    let body: RenderBody = match parse_body(request) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let index = match index_service::load_index(&state.paths) {
+        Ok(index) => index,
+        Err(e) => return error(500, &e),
+    };
+    if !index.prompts.iter().any(|p| p.id == id) {
+        return not_found(id);
+    }
+    let prompt = match prompt_service::load_prompt(&state.paths, &index, id) {
+        Ok(prompt) => prompt,
+        Err(e) => return error(500, &e),
+    };
+
+    let problems =
+        prompt_service::validate_template(&state.paths, &index, Some(id), &prompt.content);
+    if !problems.is_empty() {
+        return template_error(&problems);
+    }
+    let expanded = match prompt_service::load_expanded(&state.paths, &index, id) {
+        Ok(expanded) => expanded,
+        Err(e) => return error(500, &e),
+    };
+    // An included prompt can still be broken
+    match prompt_service::render_expanded(&state.paths, &index, expanded, &body.variables) {
+        Ok(rendered) => ok(&rendered),
+        Err(e) => template_error(&[e]),
+    }
+}
+
+/// `POST /v1/prompts/{id}/usage`: count one use, as the launcher does.
+fn record_usage(state: &AppState, id: &str, request: &Request) -> Response {
+    let body: UsageBody = match parse_body(request) {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+    let exists = match index_service::load_index(&state.paths) {
+        Ok(index) => index.prompts.iter().any(|p| p.id == id),
+        Err(e) => return error(500, &e),
+    };
+    if !exists {
+        return not_found(id);
+    }
+    let delivery_mode = body.delivery_mode.unwrap_or(DeliveryMode::Copy);
+    // Not whatever window the launcher last captured: the caller knows best
+    let target_app = body
+        .target_app
+        .map(|app| app.trim().to_lowercase())
+        .filter(|app| !app.is_empty());
+    match data::record_prompt_usage(state, id, target_app, delivery_mode, body.variables_filled) {
+        Ok(()) => ok(&json!({ "recorded": true })),
+        Err(e) => error(500, &e),
+    }
+}
+
+/// An empty body counts as `{}`.
+fn parse_body<T: for<'de> Deserialize<'de> + Default>(request: &Request) -> Result<T, Response> {
+    if request.body.iter().all(u8::is_ascii_whitespace) {
+        return Ok(T::default());
+    }
+    serde_json::from_slice(&request.body)
+        .map_err(|e| error(400, &format!("Invalid JSON body: {}", e)))
+}
+
+fn authorized(request: &Request, token: &str) -> bool {
+    let provided = match request
+        .authorization
+        .as_deref()
+        .and_then(|value| value.strip_prefix("Bearer "))
+    {
+        Some(provided) => provided.trim(),
+        None => return false,
+    };
+    constant_time_eq(provided.as_bytes(), token.as_bytes())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn ok<T: Serialize>(value: &T) -> Response {
+    match serde_json::to_string(value) {
+        Ok(body) => Response { status: 200, body },
+        Err(e) => error(500, &e.to_string()),
+    }
+}
+
+fn template_error(problems: &[TemplateError]) -> Response {
+    let message = problems
+        .iter()
+        .map(TemplateError::to_string)
+        .collect::<Vec<String>>()
+        .join("; ");
+    Response {
+        status: 422,
+        body: json!({ "error": message, "templateErrors": problems }).to_string(),
+    }
+}
+
+fn not_found(id: &str) -> Response {
+    error(404, &format!("Prompt '{}' not found", id))
+}
+
+pub(crate) fn error(status: u16, message: &str) -> Response {
+    Response {
+        status,
+        body: json!({ "error": message }).to_string(),
+    }
+}
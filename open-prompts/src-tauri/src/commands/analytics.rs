@@ -0,0 +1,76 @@
+use crate::error::AppResult;
+use crate::models::usage::{PromptUsageStat, UsageBucket};
+use crate::services::{index_service, usage_log_service};
+use crate::state::AppState;
+
+#[tauri::command]
+pub fn get_daily_usage(
+    state: tauri::State<'_, AppState>,
+    days: Option<u32>,
+) -> AppResult<Vec<UsageBucket>> {
+    let entries = usage_log_service::read_log(&state.paths)?;
+    Ok(usage_log_service::daily_usage(
+        &entries,
+        days.unwrap_or(30),
+        chrono::Utc::now(),
+    ))
+}
+
+#[tauri::command]
+pub fn get_weekly_usage(
+    state: tauri::State<'_, AppState>,
+    weeks: Option<u32>,
+) -> AppResult<Vec<UsageBucket>> {
+    let entries = usage_log_service::read_log(&state.paths)?;
+    Ok(usage_log_service::weekly_usage(
+        &entries,
+        weeks.unwrap_or(12),
+        chrono::Utc::now(),
+    ))
+}
+
+#[tauri::command]
+pub fn get_top_prompts(
+    state: tauri::State<'_, AppState>,
+    limit: Option<usize>,
+    days: Option<u32>,
+) -> AppResult<Vec<PromptUsageStat>> {
+    let index = index_service::load_index(&state.paths)?;
+    let entries = usage_log_service::read_log(&state.paths)?;
+    Ok(usage_log_service::top_prompts(
+        &entries,
+        &index,
+        limit.unwrap_or(10),
+        days,
+        chrono::Utc::now(),
+    ))
+}
+
+#[tauri::command]
+pub fn get_never_used_prompts(state: tauri::State<'_, AppState>) -> AppResult<Vec<PromptUsageStat>> {
+    let index = index_service::load_index(&state.paths)?;
+    let entries = usage_log_service::read_log(&state.paths)?;
+    Ok(usage_log_service::never_used(&entries, &index))
+}
+
+#[tauri::command]
+pub fn get_stale_prompts(
+    state: tauri::State<'_, AppState>,
+    days: Option<u32>,
+) -> AppResult<Vec<PromptUsageStat>> {
+    let index = index_service::load_index(&state.paths)?;
+    let entries = usage_log_service::read_log(&state.paths)?;
+    Ok(usage_log_service::stale_prompts(
+        &entries,
+        &index,
+        days.unwrap_or(90),
+        chrono::Utc::now(),
+    ))
+}
+
+/// Compact the usage log now. Returns the number of entries kept.
+#[tauri::command]
+pub fn compact_usage_log(state: tauri::State<'_, AppState>) -> AppResult<usize> {
+    let index = index_service::load_index(&state.paths)?;
+    usage_log_service::compact(&state.paths, &index, chrono::Utc::now())
+}
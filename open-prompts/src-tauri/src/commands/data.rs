@@ -1,7 +1,22 @@
+use crate::commands::hotkey;
 use crate::error::AppResult;
-use crate::models::prompt::{Prompt, PromptIndex, PromptMetadata};
-use crate::services::{index_service, prompt_service, search_service};
+use crate::models::prompt::{
+    BulkReport, DuplicateGroup, FolderDeleteMode, FolderMeta, FolderNode, Prompt, PromptDependencies,
+    PromptExport, PromptIndex, PromptMetadata, RenderedPrompt, SavedSearch, TemplateError,
+};
+use crate::models::search::SearchHit;
+use crate::models::settings::SearchSettings;
+use crate::models::usage::{DeliveryMode, UsageLogEntry};
+use crate::services::search_service::SearchContext;
+use crate::services::storage::StoragePaths;
+use crate::services::{
+    bulk_service, duplicate_service, folder_service, frecency_service, index_service, prompt_service, search_service,
+    settings_service, usage_log_service,
+};
+#[cfg(feature = "semantic-search")]
+use crate::services::embedding_service;
 use crate::state::AppState;
+use std::collections::HashMap;
 
 #[tauri::command]
 pub fn get_index(state: tauri::State<'_, AppState>) -> AppResult<PromptIndex> {
@@ -25,27 +40,177 @@ pub fn save_prompt(
     state: tauri::State<'_, AppState>,
     prompt: Prompt,
 ) -> AppResult<PromptMetadata> {
-    let mut index = index_service::load_index(&state.paths)?;
-    let meta = prompt_service::save_prompt(&state.paths, &mut index, prompt)?;
-    index_service::save_index(&state.paths, &index)?;
+    let meta = index_service::update_index(&state.paths, |index| {
+        prompt_service::save_prompt(&state.paths, index, prompt)
+    })?;
+    embed_saved_prompts(&state.paths);
     Ok(meta)
 }
 
+/// Embed new and edited prompts in the background, so semantic search finds them.
+#[cfg(feature = "semantic-search")]
+pub fn embed_saved_prompts(paths: &StoragePaths) {
+    if settings_service::load_settings(paths).is_ok_and(|s| s.search.semantic) {
+        embedding_service::refresh_in_background(paths);
+    }
+}
+
+#[cfg(not(feature = "semantic-search"))]
+pub fn embed_saved_prompts(_paths: &StoragePaths) {}
+
 #[tauri::command]
-pub fn delete_prompt(state: tauri::State<'_, AppState>, id: String) -> AppResult<()> {
-    let mut index = index_service::load_index(&state.paths)?;
-    prompt_service::delete_prompt(&state.paths, &mut index, &id)?;
-    index_service::save_index(&state.paths, &index)?;
+pub fn delete_prompt(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> AppResult<()> {
+    index_service::update_index(&state.paths, |index| {
+        prompt_service::delete_prompt(&state.paths, index, &id)
+    })?;
+    hotkey::update_prompt_hotkeys(&app, &state, |_| false);
     Ok(())
 }
 
+/// Copy a prompt, into `target_folder` if given.
+#[tauri::command]
+pub fn duplicate_prompt(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    target_folder: Option<String>,
+) -> AppResult<PromptMetadata> {
+    let meta = index_service::update_index(&state.paths, |index| {
+        prompt_service::duplicate_prompt(&state.paths, index, &id, target_folder.as_deref())
+    })?;
+    embed_saved_prompts(&state.paths);
+    Ok(meta)
+}
+
+#[tauri::command]
+pub fn move_prompts(
+    state: tauri::State<'_, AppState>,
+    ids: Vec<String>,
+    folder: String,
+) -> AppResult<BulkReport> {
+    index_service::update_index(&state.paths, |index| {
+        bulk_service::move_prompts(&state.paths, index, &ids, &folder)
+    })
+}
+
+#[tauri::command]
+pub fn delete_prompts(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    ids: Vec<String>,
+) -> AppResult<BulkReport> {
+    let report = index_service::update_index(&state.paths, |index| {
+        bulk_service::delete_prompts(&state.paths, index, &ids)
+    })?;
+    if report.applied {
+        hotkey::update_prompt_hotkeys(&app, &state, |_| false);
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn tag_prompts(
+    state: tauri::State<'_, AppState>,
+    ids: Vec<String>,
+    tags: Vec<String>,
+) -> AppResult<BulkReport> {
+    index_service::update_index(&state.paths, |index| {
+        bulk_service::tag_prompts(index, &ids, &tags)
+    })
+}
+
+#[tauri::command]
+pub fn export_prompts(
+    state: tauri::State<'_, AppState>,
+    ids: Vec<String>,
+) -> AppResult<PromptExport> {
+    let index = index_service::load_index(&state.paths)?;
+    bulk_service::export_prompts(&state.paths, &index, &ids)
+}
+
+/// Groups of duplicate prompts; `threshold` is the similarity, from 0 to 1,
+/// from which prompts count as near duplicates.
+#[tauri::command]
+pub fn find_duplicates(
+    state: tauri::State<'_, AppState>,
+    threshold: Option<f64>,
+) -> AppResult<Vec<DuplicateGroup>> {
+    let index = index_service::load_index(&state.paths)?;
+    let threshold = threshold.unwrap_or(duplicate_service::DEFAULT_THRESHOLD);
+    duplicate_service::find_duplicates(&state.paths, &index, threshold)
+}
+
+/// Merge `remove_ids` into `keep_id`, moving their usage history along.
+#[tauri::command]
+pub fn merge_prompts(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    keep_id: String,
+    remove_ids: Vec<String>,
+) -> AppResult<BulkReport> {
+    let report = index_service::update_index(&state.paths, |index| {
+        // The log must be seeded before the merge changes `last_used`
+        frecency_service::seed_usage_log(&state.paths, index)?;
+        let report = bulk_service::merge_prompts(&state.paths, index, &keep_id, &remove_ids)?;
+        if report.applied {
+            usage_log_service::reassign(&state.paths, &remove_ids, &keep_id)?;
+        }
+        Ok(report)
+    })?;
+    if report.applied {
+        hotkey::update_prompt_hotkeys(&app, &state, |settings| {
+            settings_service::reassign_prompt_hotkeys(settings, &remove_ids, &keep_id)
+        });
+    }
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn pin_prompt(state: tauri::State<'_, AppState>, id: String) -> AppResult<PromptMetadata> {
+    set_pinned(&state, &id, true)
+}
+
+#[tauri::command]
+pub fn unpin_prompt(state: tauri::State<'_, AppState>, id: String) -> AppResult<PromptMetadata> {
+    set_pinned(&state, &id, false)
+}
+
+fn set_pinned(state: &AppState, id: &str, pinned: bool) -> AppResult<PromptMetadata> {
+    index_service::update_index(&state.paths, |index| {
+        prompt_service::set_pinned(index, id, pinned)
+    })
+}
+
+/// Set the manual order of the listed prompts; prompts not listed keep their place.
+#[tauri::command]
+pub fn reorder_prompts(
+    state: tauri::State<'_, AppState>,
+    ids: Vec<String>,
+) -> AppResult<Vec<PromptMetadata>> {
+    index_service::update_index(&state.paths, |index| {
+        prompt_service::reorder_prompts(index, &ids)?;
+        Ok(index.prompts.clone())
+    })
+}
+
+/// Folder hierarchy with prompt counts, rooted at the top level.
+#[tauri::command]
+pub fn get_folder_tree(state: tauri::State<'_, AppState>) -> AppResult<FolderNode> {
+    let index = index_service::load_index(&state.paths)?;
+    Ok(folder_service::build_tree(&index))
+}
+
 #[tauri::command]
 pub fn add_folder(state: tauri::State<'_, AppState>, name: String) -> AppResult<Vec<String>> {
-    let mut index = index_service::load_index(&state.paths)?;
-    prompt_service::create_folder(&state.paths, &name)?;
-    index_service::add_folder(&mut index, name)?;
-    index_service::save_index(&state.paths, &index)?;
-    Ok(index.folders)
+    let name = folder_service::normalize_path(&name)?;
+    index_service::update_index(&state.paths, |index| {
+        prompt_service::create_folder(&state.paths, &name)?;
+        index_service::add_folder(index, name)?;
+        Ok(index.folders.clone())
+    })
 }
 
 #[tauri::command]
@@ -54,33 +219,272 @@ pub fn rename_folder(
     old: String,
     new: String,
 ) -> AppResult<Vec<String>> {
-    let mut index = index_service::load_index(&state.paths)?;
-    prompt_service::rename_folder(&state.paths, &mut index, &old, &new)?;
-    index_service::save_index(&state.paths, &index)?;
-    Ok(index.folders)
+    index_service::update_index(&state.paths, |index| {
+        prompt_service::rename_folder(&state.paths, index, &old, &new)?;
+        Ok(index.folders.clone())
+    })
 }
 
+/// Set a folder's icon, color and description. Empty values clear them.
 #[tauri::command]
-pub fn delete_folder(state: tauri::State<'_, AppState>, name: String) -> AppResult<Vec<String>> {
-    let mut index = index_service::load_index(&state.paths)?;
-    prompt_service::delete_folder(&state.paths, &mut index, &name)?;
-    index_service::save_index(&state.paths, &index)?;
-    Ok(index.folders)
+pub fn update_folder_meta(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    icon: Option<String>,
+    color: Option<String>,
+    description: Option<String>,
+) -> AppResult<FolderMeta> {
+    index_service::update_index(&state.paths, |index| {
+        folder_service::update_meta(
+            &state.paths,
+            index,
+            &name,
+            icon,
+            color,
+            description.unwrap_or_default(),
+        )
+    })
+}
+
+/// Order the subfolders of `parent` ("" for the top level) as listed in `order`.
+#[tauri::command]
+pub fn reorder_folders(
+    state: tauri::State<'_, AppState>,
+    parent: String,
+    order: Vec<String>,
+) -> AppResult<FolderNode> {
+    index_service::update_index(&state.paths, |index| {
+        folder_service::reorder(&state.paths, index, &parent, &order)?;
+        Ok(folder_service::build_tree(index))
+    })
+}
+
+/// Move a folder and its contents into `new_parent` ("" for the top level).
+#[tauri::command]
+pub fn move_folder(
+    state: tauri::State<'_, AppState>,
+    name: String,
+    new_parent: String,
+) -> AppResult<Vec<String>> {
+    index_service::update_index(&state.paths, |index| {
+        prompt_service::move_folder(&state.paths, index, &name, &new_parent)?;
+        Ok(index.folders.clone())
+    })
+}
+
+/// Delete a folder. Its contents move to the top level unless `mode` says otherwise.
+#[tauri::command]
+pub fn delete_folder(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    name: String,
+    mode: Option<FolderDeleteMode>,
+) -> AppResult<Vec<String>> {
+    let mode = mode.unwrap_or_default();
+    let folders = index_service::update_index(&state.paths, |index| {
+        prompt_service::delete_folder(&state.paths, index, &name, mode)?;
+        Ok(index.folders.clone())
+    })?;
+    if mode == FolderDeleteMode::DeleteContents {
+        hotkey::update_prompt_hotkeys(&app, &state, |_| false);
+    }
+    Ok(folders)
+}
+
+#[tauri::command]
+pub fn render_prompt(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    variables: HashMap<String, String>,
+) -> AppResult<RenderedPrompt> {
+    let index = index_service::load_index(&state.paths)?;
+    prompt_service::render_prompt(&state.paths, &index, &id, &variables)
+}
+
+/// The text to paste or copy for `id` from the launcher, where no variables are filled in.
+#[tauri::command]
+pub fn get_paste_content(state: tauri::State<'_, AppState>, id: String) -> AppResult<String> {
+    let index = index_service::load_index(&state.paths)?;
+    prompt_service::paste_content(&state.paths, &index, &id, &HashMap::new())
+}
+
+/// Check template syntax and includes in `content`, e.g. before saving.
+/// `id` is the prompt being edited, if it has been saved before.
+#[tauri::command]
+pub fn validate_prompt_template(
+    state: tauri::State<'_, AppState>,
+    id: Option<String>,
+    content: String,
+) -> AppResult<Vec<TemplateError>> {
+    let index = index_service::load_index(&state.paths)?;
+    Ok(prompt_service::validate_template(
+        &state.paths,
+        &index,
+        id.as_deref().filter(|id| !id.is_empty()),
+        &content,
+    ))
+}
+
+/// Which prompts this one includes and which include it.
+#[tauri::command]
+pub fn get_prompt_dependencies(
+    state: tauri::State<'_, AppState>,
+    id: String,
+) -> AppResult<PromptDependencies> {
+    let index = index_service::load_index(&state.paths)?;
+    prompt_service::prompt_dependencies(&state.paths, &index, &id)
 }
 
 #[tauri::command]
 pub fn search_prompts(
     state: tauri::State<'_, AppState>,
     query: String,
-) -> AppResult<Vec<PromptMetadata>> {
+) -> AppResult<Vec<SearchHit>> {
+    search_library(&state, &query)
+}
+
+/// Rank the whole library for `query`. Shared by the `search_prompts` command and the HTTP API.
+pub fn search_library(state: &AppState, query: &str) -> AppResult<Vec<SearchHit>> {
     let index = index_service::load_index(&state.paths)?;
-    Ok(search_service::search_prompts(&index.prompts, &query))
+    with_search_context(state, &index, query, |ctx| {
+        search_service::search_prompts(&index.prompts, query, ctx)
+    })
 }
 
+/// Save a search, replacing the one with the same name if there is one.
 #[tauri::command]
-pub fn record_usage(state: tauri::State<'_, AppState>, id: String) -> AppResult<()> {
-    let mut index = index_service::load_index(&state.paths)?;
-    prompt_service::record_usage(&mut index, &id)?;
-    index_service::save_index(&state.paths, &index)?;
-    Ok(())
+pub fn create_saved_search(
+    state: tauri::State<'_, AppState>,
+    search: SavedSearch,
+) -> AppResult<SavedSearch> {
+    index_service::update_index(&state.paths, |index| {
+        search_service::create_saved_search(index, search)
+    })
+}
+
+#[tauri::command]
+pub fn list_saved_searches(state: tauri::State<'_, AppState>) -> AppResult<Vec<SavedSearch>> {
+    let index = index_service::load_index(&state.paths)?;
+    Ok(index.saved_searches)
+}
+
+#[tauri::command]
+pub fn run_saved_search(
+    state: tauri::State<'_, AppState>,
+    name: String,
+) -> AppResult<Vec<SearchHit>> {
+    let index = index_service::load_index(&state.paths)?;
+    let search = search_service::find_saved_search(&index, &name)?;
+    with_search_context(&state, &index, &search.query, |ctx| {
+        search_service::run_saved_search(&index.prompts, search, ctx)
+    })
+}
+
+#[tauri::command]
+pub fn delete_saved_search(state: tauri::State<'_, AppState>, name: String) -> AppResult<()> {
+    index_service::update_index(&state.paths, |index| {
+        search_service::delete_saved_search(index, &name)
+    })
+}
+
+/// Load the usage and settings that ranking `query` needs and hand them to `f`.
+/// Prompt bodies are only read if the query uses them, and only those that
+/// changed since the last search.
+fn with_search_context<T>(
+    state: &AppState,
+    index: &PromptIndex,
+    query: &str,
+    f: impl FnOnce(&SearchContext) -> T,
+) -> AppResult<T> {
+    let settings = settings_service::load_settings(&state.paths)?;
+    let usage = frecency_service::load_usage(
+        &state.paths,
+        index,
+        settings.search.max_events_per_prompt,
+    )?;
+    let target_app = state.last_target_app.lock().clone();
+    let query = search_service::parse_query(query);
+    let mut body_cache = state.body_cache.lock();
+    let bodies = query
+        .uses_bodies()
+        .then(|| body_cache.refresh(&state.paths, index));
+    let similarities = semantic_similarities(state, index, &settings.search, &query.text);
+    let ctx = SearchContext {
+        usage: &usage,
+        settings: &settings.search,
+        target_app: target_app.as_deref(),
+        now: chrono::Utc::now(),
+        bodies,
+        similarities: similarities.as_ref(),
+    };
+    Ok(f(&ctx))
+}
+
+/// Similarity of each prompt to the query text, if semantic search is built in
+/// and turned on. Search falls back to keywords alone until the model is loaded
+/// or if it can't be.
+#[cfg(feature = "semantic-search")]
+fn semantic_similarities(
+    state: &AppState,
+    index: &PromptIndex,
+    settings: &SearchSettings,
+    text: &str,
+) -> Option<HashMap<String, f32>> {
+    if !settings.semantic || text.is_empty() {
+        return None;
+    }
+    embedding_service::similarities(&state.paths, index, text)
+}
+
+#[cfg(not(feature = "semantic-search"))]
+fn semantic_similarities(
+    _state: &AppState,
+    _index: &PromptIndex,
+    _settings: &SearchSettings,
+    _text: &str,
+) -> Option<HashMap<String, f32>> {
+    None
+}
+
+#[tauri::command]
+pub fn record_usage(
+    state: tauri::State<'_, AppState>,
+    id: String,
+    delivery_mode: Option<DeliveryMode>,
+    variables_filled: Option<bool>,
+) -> AppResult<()> {
+    let target_app = state.last_target_app.lock().clone();
+    record_prompt_usage(
+        &state,
+        &id,
+        target_app,
+        delivery_mode.unwrap_or(DeliveryMode::Paste),
+        variables_filled.unwrap_or(false),
+    )
+}
+
+/// Update use counters and the usage log for one use of a prompt in
+/// `target_app`, the application it was delivered to if known.
+/// Shared by the `record_usage` command, hotkey-driven pastes, links and the HTTP API.
+pub fn record_prompt_usage(
+    state: &AppState,
+    id: &str,
+    target_app: Option<String>,
+    delivery_mode: DeliveryMode,
+    variables_filled: bool,
+) -> AppResult<()> {
+    index_service::update_index(&state.paths, |index| {
+        // Seed before touching last_used so this use isn't counted twice
+        frecency_service::seed_usage_log(&state.paths, index)?;
+        prompt_service::record_usage(index, id)?;
+
+        let entry = UsageLogEntry {
+            prompt_id: id.to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+            target_app,
+            delivery_mode,
+            variables_filled,
+        };
+        usage_log_service::append(&state.paths, index, &entry)
+    })
 }
@@ -1,47 +1,104 @@
+use std::collections::HashMap;
 use std::sync::{Mutex, OnceLock};
 use std::time::{Duration, Instant};
 
 use crate::error::AppResult;
+use crate::models::hotkey::{
+    HotkeyAction, HotkeyBinding, HotkeyFailure, HotkeyFallback, HotkeyReport,
+};
+use crate::models::settings::AppSettings;
+use crate::models::usage::DeliveryMode;
+use crate::services::{index_service, prompt_service, settings_service};
 use crate::state::AppState;
-use tauri::Manager;
-use tauri_plugin_global_shortcut::{GlobalShortcutExt, ShortcutState};
+use tauri::{Emitter, Manager};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
 
 #[cfg(target_os = "windows")]
 use crate::platform::windows as win32;
 
+const DEFAULT_LAUNCHER_HOTKEY: &str = "CommandOrControl+8";
+
 #[tauri::command]
 pub fn get_current_hotkey(state: tauri::State<'_, AppState>) -> AppResult<String> {
-    Ok(state.current_hotkey.lock().clone())
+    Ok(launcher_accelerator(&state.hotkeys.lock())
+        .unwrap_or_else(|| DEFAULT_LAUNCHER_HOTKEY.to_string()))
+}
+
+#[tauri::command]
+pub fn get_hotkey_bindings(state: tauri::State<'_, AppState>) -> AppResult<Vec<HotkeyBinding>> {
+    Ok(state.hotkeys.lock().clone())
+}
+
+#[tauri::command]
+pub fn get_hotkey_fallback(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<HotkeyFallback>> {
+    Ok(state.hotkey_fallback.lock().clone())
 }
 
+/// Change only the launcher hotkey, keeping every other binding.
+/// The hotkey is saved to settings only once it has registered successfully.
 #[tauri::command]
 pub fn set_hotkey(
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     hotkey: Option<String>,
 ) -> AppResult<String> {
-    let current = state.current_hotkey.lock().clone();
+    let new_hotkey = hotkey.unwrap_or_else(|| DEFAULT_LAUNCHER_HOTKEY.to_string());
+    validate_accelerator(&new_hotkey)?;
 
-    // Unregister current
-    if let Err(e) = app.global_shortcut().unregister(current.as_str()) {
-        eprintln!("Failed to unregister hotkey '{}': {}", current, e);
+    let previous = state.hotkeys.lock().clone();
+    let report = apply_bindings(&app, &state, with_launcher(&previous, &new_hotkey));
+    if report.rolled_back {
+        return Err(describe_failures(&report));
     }
 
-    let new_hotkey = hotkey.unwrap_or_else(|| "CommandOrControl+8".to_string());
-
-    // Register new
-    register_hotkey(&app, &state, &new_hotkey)?;
+    let settings = match persist_launcher_hotkey(&state, &new_hotkey) {
+        Ok(settings) => settings,
+        Err(e) => {
+            // Keep the registered hotkey in sync with what is on disk
+            apply_bindings(&app, &state, previous);
+            return Err(e);
+        }
+    };
 
-    *state.current_hotkey.lock() = new_hotkey.clone();
+    *state.hotkey_fallback.lock() = None;
+    let _ = app.emit("settings-changed", &settings);
     Ok(new_hotkey)
 }
 
+/// Replace the whole hotkey set and save it to settings. If any binding fails
+/// to register, the previous set is restored and the failures are reported.
 #[tauri::command]
-pub fn pause_hotkey(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> AppResult<()> {
-    let current = state.current_hotkey.lock().clone();
-    if let Err(e) = app.global_shortcut().unregister(current.as_str()) {
-        eprintln!("Failed to pause hotkey: {}", e);
+pub fn set_hotkey_bindings(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+    bindings: Vec<HotkeyBinding>,
+) -> AppResult<HotkeyReport> {
+    let previous = state.hotkeys.lock().clone();
+    let report = apply_bindings(&app, &state, bindings.clone());
+    if report.rolled_back {
+        return Ok(report);
     }
+
+    let settings = match persist_bindings(&state, &bindings) {
+        Ok(settings) => settings,
+        Err(e) => {
+            // Keep the registered hotkeys in sync with what is on disk
+            apply_bindings(&app, &state, previous);
+            return Err(e);
+        }
+    };
+
+    *state.hotkey_fallback.lock() = None;
+    let _ = app.emit("settings-changed", &settings);
+    Ok(report)
+}
+
+#[tauri::command]
+pub fn pause_hotkey(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> AppResult<()> {
+    let bindings = state.hotkeys.lock().clone();
+    unregister_bindings(&app, &bindings);
     Ok(())
 }
 
@@ -49,38 +106,336 @@ pub fn pause_hotkey(app: tauri::AppHandle, state: tauri::State<'_, AppState>) ->
 pub fn resume_hotkey(
     app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
+) -> AppResult<HotkeyReport> {
+    let bindings = state.hotkeys.lock().clone();
+    let (registered, failures) = register_bindings(&app, &bindings);
+    Ok(HotkeyReport {
+        registered,
+        failures,
+        rolled_back: false,
+    })
+}
+
+/// Swap the registered set for `bindings`, all or nothing.
+/// Called from setup AND from the set_hotkey/set_hotkey_bindings commands.
+pub fn apply_bindings(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    bindings: Vec<HotkeyBinding>,
+) -> HotkeyReport {
+    let conflicts = find_conflicts(&bindings);
+    if !conflicts.is_empty() {
+        return HotkeyReport {
+            registered: Vec::new(),
+            failures: conflicts,
+            rolled_back: true,
+        };
+    }
+
+    let previous = state.hotkeys.lock().clone();
+    unregister_bindings(app, &previous);
+
+    let (registered, failures) = register_bindings(app, &bindings);
+    if failures.is_empty() {
+        *state.hotkeys.lock() = bindings;
+        return HotkeyReport {
+            registered,
+            failures,
+            rolled_back: false,
+        };
+    }
+
+    // Roll back: drop whatever did register and restore the previous set
+    unregister_bindings(app, &registered);
+    let (_, restore_failures) = register_bindings(app, &previous);
+    for failure in &restore_failures {
+        eprintln!(
+            "Failed to restore hotkey '{}' during rollback: {}",
+            failure.binding.accelerator, failure.error
+        );
+    }
+
+    HotkeyReport {
+        registered: Vec::new(),
+        failures,
+        rolled_back: true,
+    }
+}
+
+/// Register the hotkeys from settings at startup. If the launcher hotkey fails,
+/// fall back to the last known-good one, then the default, and notify the UI.
+pub fn register_from_settings(app: &tauri::AppHandle, state: &AppState, settings: &AppSettings) {
+    let bindings = settings_service::hotkey_bindings(settings);
+    let report = apply_bindings(app, state, bindings.clone());
+    if !report.rolled_back {
+        if settings.general.last_known_good_hotkey.as_deref() != Some(&settings.general.hotkey) {
+            if let Err(e) = persist_launcher_hotkey(state, &settings.general.hotkey) {
+                eprintln!("Warning: Failed to record last known-good hotkey: {}", e);
+            }
+        }
+        return;
+    }
+
+    for failure in &report.failures {
+        eprintln!("Warning: {}", failure.error);
+    }
+
+    // Keep whatever does register rather than running with no hotkeys at all
+    let usable: Vec<HotkeyBinding> = bindings
+        .into_iter()
+        .filter(|b| !report.failures.iter().any(|f| &f.binding == b))
+        .collect();
+
+    let launcher_failure = report
+        .failures
+        .iter()
+        .find(|f| f.binding.action == HotkeyAction::ToggleLauncher);
+    let launcher_failure = match launcher_failure {
+        Some(failure) => failure,
+        None => {
+            apply_bindings(app, state, usable);
+            return;
+        }
+    };
+
+    let candidates = [
+        settings.general.last_known_good_hotkey.clone(),
+        Some(DEFAULT_LAUNCHER_HOTKEY.to_string()),
+    ];
+    let mut active = None;
+    for candidate in candidates
+        .into_iter()
+        .flatten()
+        .filter(|c| c != &launcher_failure.binding.accelerator)
+    {
+        if !apply_bindings(app, state, with_launcher(&usable, &candidate)).rolled_back {
+            active = Some(candidate);
+            break;
+        }
+    }
+    if active.is_none() {
+        apply_bindings(app, state, usable);
+    }
+
+    let fallback = HotkeyFallback {
+        requested: launcher_failure.binding.accelerator.clone(),
+        active,
+        error: launcher_failure.error.clone(),
+    };
+    let _ = app.emit("hotkey-fallback", &fallback);
+    *state.hotkey_fallback.lock() = Some(fallback);
+}
+
+/// After prompts were merged or deleted: change prompt hotkeys with `edit`,
+/// unbind those of prompts the library no longer has and register the rest.
+/// Failures are only logged, since the prompts have changed by then.
+pub fn update_prompt_hotkeys(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    edit: impl FnOnce(&mut AppSettings) -> bool,
+) {
+    if let Err(e) = try_update_prompt_hotkeys(app, state, edit) {
+        eprintln!("Failed to update prompt hotkeys: {}", e);
+    }
+}
+
+fn try_update_prompt_hotkeys(
+    app: &tauri::AppHandle,
+    state: &AppState,
+    edit: impl FnOnce(&mut AppSettings) -> bool,
 ) -> AppResult<()> {
-    let current = state.current_hotkey.lock().clone();
-    register_hotkey(&app, &state, &current)
+    let index = index_service::load_index(&state.paths)?;
+    let mut settings = settings_service::load_settings(&state.paths)?;
+    let edited = edit(&mut settings);
+    let dropped = settings_service::drop_missing_prompt_hotkeys(&mut settings, &index);
+    if !edited && !dropped {
+        return Ok(());
+    }
+    settings_service::save_settings(&state.paths, &settings)?;
+
+    // The other hotkeys stay as registered, which may be a fallback launcher hotkey
+    let is_prompt = |b: &HotkeyBinding| matches!(b.action, HotkeyAction::PastePrompt { .. });
+    let mut bindings: Vec<HotkeyBinding> = state
+        .hotkeys
+        .lock()
+        .iter()
+        .filter(|b| !is_prompt(b))
+        .cloned()
+        .collect();
+    bindings.extend(
+        settings_service::hotkey_bindings(&settings)
+            .into_iter()
+            .filter(is_prompt),
+    );
+    let report = apply_bindings(app, state, bindings);
+    if report.rolled_back {
+        eprintln!("{}", describe_failures(&report));
+    }
+    let _ = app.emit("settings-changed", &settings);
+    Ok(())
+}
+
+/// Check accelerator syntax without registering anything.
+pub fn validate_accelerator(accelerator: &str) -> AppResult<()> {
+    accelerator
+        .parse::<Shortcut>()
+        .map(|_| ())
+        .map_err(|e| format!("Invalid hotkey '{}': {}", accelerator, e))
+}
+
+/// `bindings` with the launcher hotkey replaced by `accelerator`.
+fn with_launcher(bindings: &[HotkeyBinding], accelerator: &str) -> Vec<HotkeyBinding> {
+    let mut result = vec![HotkeyBinding {
+        accelerator: accelerator.to_string(),
+        action: HotkeyAction::ToggleLauncher,
+    }];
+    result.extend(
+        bindings
+            .iter()
+            .filter(|b| b.action != HotkeyAction::ToggleLauncher)
+            .cloned(),
+    );
+    result
 }
 
-/// Register the global hotkey with the launcher show/toggle handler.
-/// Called from setup hook AND from set_hotkey/resume_hotkey commands.
-pub fn register_hotkey(app: &tauri::AppHandle, _state: &AppState, hotkey: &str) -> AppResult<()> {
+fn persist_launcher_hotkey(state: &AppState, accelerator: &str) -> AppResult<AppSettings> {
+    let mut settings = settings_service::load_settings(&state.paths)?;
+    settings.general.hotkey = accelerator.to_string();
+    settings.general.last_known_good_hotkey = Some(accelerator.to_string());
+    settings_service::save_settings(&state.paths, &settings)?;
+    Ok(settings)
+}
+
+fn persist_bindings(state: &AppState, bindings: &[HotkeyBinding]) -> AppResult<AppSettings> {
+    let mut settings = settings_service::load_settings(&state.paths)?;
+    settings_service::set_hotkey_bindings(&mut settings, bindings);
+    if let Some(accelerator) = launcher_accelerator(bindings) {
+        settings.general.last_known_good_hotkey = Some(accelerator);
+    }
+    settings_service::save_settings(&state.paths, &settings)?;
+    Ok(settings)
+}
+
+/// Unparsable accelerators and accelerators bound more than once.
+fn find_conflicts(bindings: &[HotkeyBinding]) -> Vec<HotkeyFailure> {
+    let mut failures = Vec::new();
+    let mut seen: HashMap<u32, &HotkeyBinding> = HashMap::new();
+
+    for binding in bindings {
+        let shortcut = match binding.accelerator.parse::<Shortcut>() {
+            Ok(shortcut) => shortcut,
+            Err(_) => {
+                failures.push(HotkeyFailure {
+                    binding: binding.clone(),
+                    error: validate_accelerator(&binding.accelerator).unwrap_err(),
+                });
+                continue;
+            }
+        };
+
+        if let Some(existing) = seen.get(&shortcut.id()) {
+            failures.push(HotkeyFailure {
+                binding: binding.clone(),
+                error: format!(
+                    "Hotkey '{}' is already bound to {:?}",
+                    binding.accelerator, existing.action
+                ),
+            });
+            continue;
+        }
+        seen.insert(shortcut.id(), binding);
+    }
+
+    failures
+}
+
+fn register_bindings(
+    app: &tauri::AppHandle,
+    bindings: &[HotkeyBinding],
+) -> (Vec<HotkeyBinding>, Vec<HotkeyFailure>) {
+    let mut registered = Vec::new();
+    let mut failures = Vec::new();
+
+    for binding in bindings {
+        match register_binding(app, binding) {
+            Ok(()) => registered.push(binding.clone()),
+            Err(error) => failures.push(HotkeyFailure {
+                binding: binding.clone(),
+                error,
+            }),
+        }
+    }
+
+    (registered, failures)
+}
+
+fn unregister_bindings(app: &tauri::AppHandle, bindings: &[HotkeyBinding]) {
+    for binding in bindings {
+        if let Err(e) = app.global_shortcut().unregister(binding.accelerator.as_str()) {
+            eprintln!("Failed to unregister hotkey '{}': {}", binding.accelerator, e);
+        }
+    }
+}
+
+/// Register one global hotkey with the handler for its action.
+fn register_binding(app: &tauri::AppHandle, binding: &HotkeyBinding) -> AppResult<()> {
     let app_handle = app.clone();
+    let action = binding.action.clone();
 
     app.global_shortcut()
-        .on_shortcut(hotkey, move |_app, _shortcut, _event| {
+        .on_shortcut(binding.accelerator.as_str(), move |_app, _shortcut, _event| {
             // The event fires on both press and release — only act on press
             if _event.state == ShortcutState::Pressed {
-                handle_hotkey_press(&app_handle);
+                handle_hotkey_press(&app_handle, &action);
             }
         })
-        .map_err(|e| format!("Failed to register hotkey '{}': {}", hotkey, e))?;
+        .map_err(|e| format!("Failed to register hotkey '{}': {}", binding.accelerator, e))?;
 
     Ok(())
 }
 
-fn handle_hotkey_press(app: &tauri::AppHandle) {
-    static LAST_TRIGGER: OnceLock<Mutex<Instant>> = OnceLock::new();
-    let lock = LAST_TRIGGER.get_or_init(|| Mutex::new(Instant::now() - Duration::from_secs(1)));
+fn launcher_accelerator(bindings: &[HotkeyBinding]) -> Option<String> {
+    bindings
+        .iter()
+        .find(|b| b.action == HotkeyAction::ToggleLauncher)
+        .map(|b| b.accelerator.clone())
+}
+
+pub fn describe_failures(report: &HotkeyReport) -> String {
+    report
+        .failures
+        .iter()
+        .map(|f| f.error.clone())
+        .collect::<Vec<String>>()
+        .join("; ")
+}
+
+fn handle_hotkey_press(app: &tauri::AppHandle, action: &HotkeyAction) {
+    // Debounced per action, so pressing two different hotkeys in quick
+    // succession runs both
+    static LAST_TRIGGER: OnceLock<Mutex<HashMap<HotkeyAction, Instant>>> = OnceLock::new();
+    let lock = LAST_TRIGGER.get_or_init(|| Mutex::new(HashMap::new()));
     if let Ok(mut last) = lock.lock() {
-        if last.elapsed() < Duration::from_millis(200) {
+        if last
+            .get(action)
+            .is_some_and(|at| at.elapsed() < Duration::from_millis(200))
+        {
             return;
         }
-        *last = Instant::now();
+        last.insert(action.clone(), Instant::now());
+    }
+
+    match action {
+        HotkeyAction::ToggleLauncher => toggle_launcher(app),
+        HotkeyAction::QuickAdd => spawn_quick_add(app),
+        HotkeyAction::PasteLastUsed => spawn_direct_paste(app, None),
+        HotkeyAction::PastePrompt { prompt_id } => {
+            spawn_direct_paste(app, Some(prompt_id.clone()))
+        }
     }
+}
 
+fn toggle_launcher(app: &tauri::AppHandle) {
     let launcher = match app.get_webview_window("launcher") {
         Some(w) => w,
         None => return,
@@ -100,19 +455,69 @@ fn handle_hotkey_press(app: &tauri::AppHandle) {
 
     // Capture the currently focused window (filter our own process)
     #[cfg(target_os = "windows")]
-    {
-        let own_pid = win32::get_current_process_id();
-        if let Some(hwnd) = win32::capture_foreground_hwnd(own_pid) {
-            *state.last_external_hwnd.lock() = Some(hwnd);
-
-            // Position launcher on the correct monitor
-            if let Some((x, y)) = win32::get_launcher_position(hwnd, 650, 400) {
-                let _ = launcher.set_position(tauri::PhysicalPosition::new(x, y));
-            }
+    if let Some(hwnd) = capture_target(&state) {
+        // Position launcher on the correct monitor
+        if let Some((x, y)) = win32::get_launcher_position(hwnd, 650, 400) {
+            let _ = launcher.set_position(tauri::PhysicalPosition::new(x, y));
         }
     }
+    #[cfg(not(target_os = "windows"))]
+    let _ = state;
 
     // Show and focus the launcher
     let _ = launcher.show();
     let _ = launcher.set_focus();
 }
+
+/// Remember the foreground window and its application as the paste target.
+#[cfg(target_os = "windows")]
+pub fn capture_target(state: &AppState) -> Option<isize> {
+    let own_pid = win32::get_current_process_id();
+    let hwnd = win32::capture_foreground_hwnd(own_pid)?;
+    *state.last_external_hwnd.lock() = Some(hwnd);
+    *state.last_target_app.lock() = win32::get_window_process_name(hwnd);
+    Some(hwnd)
+}
+
+fn spawn_quick_add(app: &tauri::AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::commands::quick_add::capture_selection(&app).await {
+            eprintln!("Quick-add capture failed: {}", e);
+        }
+    });
+}
+
+/// Paste a prompt straight into the focused window. `None` pastes the most recently used prompt.
+fn spawn_direct_paste(app: &tauri::AppHandle, prompt_id: Option<String>) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = paste_prompt_directly(&app, prompt_id).await {
+            eprintln!("Hotkey paste failed: {}", e);
+        }
+    });
+}
+
+async fn paste_prompt_directly(app: &tauri::AppHandle, prompt_id: Option<String>) -> AppResult<()> {
+    let state = app.state::<AppState>();
+
+    #[cfg(target_os = "windows")]
+    capture_target(&state);
+    let target_app = state.last_target_app.lock().clone();
+
+    let index = index_service::load_index(&state.paths)?;
+    let id = match prompt_id {
+        Some(id) => id,
+        None => index
+            .prompts
+            .iter()
+            .filter(|p| p.last_used.is_some())
+            .max_by(|a, b| a.last_used.cmp(&b.last_used))
+            .map(|p| p.id.clone())
+            .ok_or("No prompt has been used yet")?,
+    };
+
+    let content = prompt_service::paste_content(&state.paths, &index, &id, &HashMap::new())?;
+    crate::commands::windows::paste_into_foreground(app, &content).await?;
+    crate::commands::data::record_prompt_usage(&state, &id, target_app, DeliveryMode::Paste, false)
+}
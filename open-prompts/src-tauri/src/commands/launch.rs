@@ -0,0 +1,159 @@
+use std::path::Path;
+
+use crate::commands::{data, windows};
+use crate::error::AppResult;
+use crate::models::launch::{DeepLink, PendingDeepLink};
+use crate::models::prompt::PromptMetadata;
+use crate::models::usage::DeliveryMode;
+use crate::services::{index_service, prompt_service};
+use crate::state::AppState;
+use tauri::{Emitter, Manager};
+
+const CONFIRM_LABEL: &str = "confirm";
+
+/// Take the query a `--search` launch left for the launcher, if any.
+#[tauri::command]
+pub fn take_pending_search(state: tauri::State<'_, AppState>) -> AppResult<Option<String>> {
+    Ok(state.pending_search.lock().take())
+}
+
+/// Take the prompt id a link asked the editor to open, if any.
+#[tauri::command]
+pub fn take_pending_open_prompt(state: tauri::State<'_, AppState>) -> AppResult<Option<String>> {
+    Ok(state.pending_open_prompt.lock().take())
+}
+
+/// The link the confirmation window is asking about, if any.
+#[tauri::command]
+pub fn get_pending_deep_link(
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<PendingDeepLink>> {
+    Ok(state.pending_deep_link.lock().clone())
+}
+
+/// Carry out the pending link. Returns the imported prompt for import links.
+#[tauri::command]
+pub async fn confirm_deep_link(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<Option<PromptMetadata>> {
+    let pending = state
+        .pending_deep_link
+        .lock()
+        .take()
+        .ok_or("No link is waiting for confirmation")?;
+    close_confirm_window(&app);
+
+    match pending.link {
+        DeepLink::Paste { id, variables } => {
+            let index = index_service::load_index(&state.paths)?;
+            let content = prompt_service::paste_content(&state.paths, &index, &id, &variables)?;
+            // Pasted into the window captured with the link
+            let target_app = state.last_target_app.lock().clone();
+            windows::paste_and_dismiss(app.clone(), state.clone(), content).await?;
+            let filled = !variables.is_empty();
+            data::record_prompt_usage(&state, &id, target_app, DeliveryMode::Paste, filled)?;
+            Ok(None)
+        }
+        DeepLink::Import { path } => {
+            let meta = index_service::update_index(&state.paths, |index| {
+                prompt_service::import_file(&state.paths, index, Path::new(&path))
+            })?;
+            data::embed_saved_prompts(&state.paths);
+            crate::open_prompt_in_editor(&app, &state, meta.id.clone());
+            Ok(Some(meta))
+        }
+        // Open links never wait for confirmation
+        DeepLink::Open { .. } => Ok(None),
+    }
+}
+
+/// Drop the pending link without acting on it.
+#[tauri::command]
+pub fn cancel_deep_link(app: tauri::AppHandle, state: tauri::State<'_, AppState>) -> AppResult<()> {
+    *state.pending_deep_link.lock() = None;
+    close_confirm_window(&app);
+    Ok(())
+}
+
+/// Park a paste or import link and ask the user about it. A newer link
+/// replaces one that was never answered.
+pub fn request_confirmation(app: &tauri::AppHandle, link: DeepLink) -> AppResult<()> {
+    let state = app.state::<AppState>();
+
+    // Paste into whatever had focus when the link was clicked, not into our own window
+    #[cfg(target_os = "windows")]
+    if matches!(link, DeepLink::Paste { .. }) {
+        crate::commands::hotkey::capture_target(&state);
+    }
+
+    let summary = describe(&state, &link)?;
+    let pending = PendingDeepLink { link, summary };
+    *state.pending_deep_link.lock() = Some(pending.clone());
+
+    open_confirm_window(app)?;
+    // An already open confirmation window won't ask for the pending link again
+    let _ = app.emit_to(CONFIRM_LABEL, "deep-link-pending", pending);
+    Ok(())
+}
+
+fn describe(state: &AppState, link: &DeepLink) -> AppResult<String> {
+    match link {
+        DeepLink::Open { id } => Ok(format!("Open prompt {}", id)),
+        DeepLink::Paste { id, variables } => {
+            let index = index_service::load_index(&state.paths)?;
+            let name = index
+                .prompts
+                .iter()
+                .find(|p| &p.id == id)
+                .map(|p| p.name.clone())
+                .ok_or_else(|| format!("Prompt '{}' not found", id))?;
+            let target = state
+                .last_target_app
+                .lock()
+                .clone()
+                .unwrap_or_else(|| "the focused window".to_string());
+            let mut summary = format!("Paste \"{}\" into {}", name, target);
+            if !variables.is_empty() {
+                let mut names: Vec<&str> = variables.keys().map(String::as_str).collect();
+                names.sort_unstable();
+                summary.push_str(&format!(", filling in {}", names.join(", ")));
+            }
+            Ok(summary)
+        }
+        DeepLink::Import { path } => Ok(format!("Import {} as a new prompt", path)),
+    }
+}
+
+fn open_confirm_window(app: &tauri::AppHandle) -> AppResult<()> {
+    if let Some(window) = app.get_webview_window(CONFIRM_LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let window = tauri::WebviewWindowBuilder::new(
+        app,
+        CONFIRM_LABEL,
+        tauri::WebviewUrl::App("index.html".into()),
+    )
+    .title("Open Link")
+    .inner_size(440.0, 180.0)
+    .resizable(false)
+    .decorations(true)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .center()
+    .visible(true)
+    .build()
+    .map_err(|e| e.to_string())?;
+
+    let _ = window.set_focus();
+    Ok(())
+}
+
+fn close_confirm_window(app: &tauri::AppHandle) {
+    if let Some(window) = app.get_webview_window(CONFIRM_LABEL) {
+        let _ = window.close();
+    }
+}
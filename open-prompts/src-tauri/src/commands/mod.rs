@@ -1,4 +1,7 @@
+pub mod analytics;
 pub mod data;
 pub mod hotkey;
+pub mod launch;
+pub mod quick_add;
 pub mod settings;
 pub mod windows;
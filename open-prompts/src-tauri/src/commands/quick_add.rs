@@ -0,0 +1,166 @@
+use std::time::Duration;
+
+use crate::commands::data;
+use crate::error::AppResult;
+use crate::models::prompt::{Prompt, PromptMetadata};
+use crate::services::{folder_service, index_service, prompt_service, settings_service};
+use crate::state::AppState;
+use tauri::{Emitter, Manager};
+use tauri_plugin_clipboard_manager::ClipboardExt;
+
+#[cfg(target_os = "windows")]
+use crate::platform::windows as win32;
+
+const QUICK_ADD_LABEL: &str = "quick-add";
+
+/// Take the text captured by the last quick-add hotkey press, if any.
+#[tauri::command]
+pub fn get_quick_add_capture(state: tauri::State<'_, AppState>) -> AppResult<Option<String>> {
+    Ok(state.quick_add_capture.lock().take())
+}
+
+/// Save captured text as a new prompt. The name defaults to the first line of
+/// the content and the folder to `GeneralSettings::quick_add_folder`.
+#[tauri::command]
+pub fn quick_add_prompt(
+    state: tauri::State<'_, AppState>,
+    content: String,
+    name: Option<String>,
+    folder: Option<String>,
+) -> AppResult<PromptMetadata> {
+    if content.trim().is_empty() {
+        return Err("Nothing to save: captured text is empty".to_string());
+    }
+
+    let settings = settings_service::load_settings(&state.paths)?;
+    let name = name
+        .filter(|n| !n.trim().is_empty())
+        .unwrap_or_else(|| prompt_service::derive_name(&content));
+    let folder =
+        folder_service::normalize_path(&folder.unwrap_or(settings.general.quick_add_folder))?;
+
+    let prompt = Prompt {
+        meta: PromptMetadata {
+            id: String::new(), // save_prompt will generate UUID
+            name,
+            folder,
+            description: String::new(),
+            filename: String::new(),
+            use_count: 0,
+            last_used: None,
+            created: String::new(),
+            updated: String::new(),
+            icon: None,
+            color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
+        },
+        content,
+    };
+
+    let meta = index_service::update_index(&state.paths, |index| {
+        prompt_service::save_prompt(&state.paths, index, prompt)
+    })?;
+    data::embed_saved_prompts(&state.paths);
+    Ok(meta)
+}
+
+/// Quick-add hotkey: copy the selection of the focused window, then open the
+/// capture window prefilled with it.
+pub async fn capture_selection(app: &tauri::AppHandle) -> AppResult<()> {
+    let state = app.state::<AppState>();
+
+    #[cfg(target_os = "windows")]
+    crate::commands::hotkey::capture_target(&state);
+
+    let text = copy_selection(app).await.unwrap_or_default();
+    *state.quick_add_capture.lock() = Some(text.clone());
+
+    open_quick_add_window(app)?;
+    // An already open capture window won't call get_quick_add_capture again
+    let _ = app.emit_to(QUICK_ADD_LABEL, "quick-add-captured", text);
+    Ok(())
+}
+
+/// Synthesize Ctrl+C in the focused window and read the result, restoring
+/// the previous clipboard text afterwards. Nothing is copied while the
+/// clipboard holds anything but text, since that couldn't be restored.
+async fn copy_selection(app: &tauri::AppHandle) -> Option<String> {
+    let previous = app.clipboard().read_text().ok();
+    if previous.is_none() && clipboard_has_non_text(app) {
+        eprintln!("Clipboard holds non-text content; quick-add opens without the selection");
+        return None;
+    }
+
+    // Clear first so an unchanged clipboard can't be mistaken for a selection
+    let _ = app.clipboard().clear();
+
+    #[cfg(target_os = "windows")]
+    {
+        // The hotkey's own modifiers would turn Ctrl+C into something else
+        win32::wait_for_modifier_release(500);
+        if !win32::send_ctrl_c() {
+            eprintln!("SendInput for Ctrl+C may have been blocked (UIPI or key state issue)");
+        }
+    }
+
+    let mut captured = None;
+    for _ in 0..15 {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        if let Ok(text) = app.clipboard().read_text() {
+            if !text.is_empty() {
+                captured = Some(text);
+                break;
+            }
+        }
+    }
+
+    let restored = match previous {
+        Some(text) => app.clipboard().write_text(text),
+        None => app.clipboard().clear(),
+    };
+    if let Err(e) = restored {
+        eprintln!("Failed to restore clipboard after quick-add capture: {}", e);
+    }
+
+    captured
+}
+
+#[cfg(target_os = "windows")]
+fn clipboard_has_non_text(_app: &tauri::AppHandle) -> bool {
+    win32::clipboard_has_non_text()
+}
+
+#[cfg(not(target_os = "windows"))]
+fn clipboard_has_non_text(app: &tauri::AppHandle) -> bool {
+    app.clipboard().read_image().is_ok()
+}
+
+fn open_quick_add_window(app: &tauri::AppHandle) -> AppResult<()> {
+    if let Some(window) = app.get_webview_window(QUICK_ADD_LABEL) {
+        let _ = window.show();
+        let _ = window.set_focus();
+        return Ok(());
+    }
+
+    let window = tauri::WebviewWindowBuilder::new(
+        app,
+        QUICK_ADD_LABEL,
+        tauri::WebviewUrl::App("index.html".into()),
+    )
+    .title("Quick Add Prompt")
+    .inner_size(520.0, 360.0)
+    .resizable(false)
+    .decorations(true)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .center()
+    .visible(true)
+    .build()
+    .map_err(|e| e.to_string())?;
+
+    let _ = window.set_focus();
+    Ok(())
+}
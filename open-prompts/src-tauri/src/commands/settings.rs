@@ -1,18 +1,100 @@
+use crate::api;
+use crate::commands::hotkey;
 use crate::error::AppResult;
 use crate::models::settings::AppSettings;
 use crate::services::settings_service;
 use crate::state::AppState;
+use tauri::{Emitter, Manager};
+use tauri_plugin_autostart::ManagerExt;
 
 #[tauri::command]
 pub fn get_settings(state: tauri::State<'_, AppState>) -> AppResult<AppSettings> {
     settings_service::load_settings(&state.paths)
 }
 
+/// Save settings and apply whatever changed to the running app: hotkeys, the
+/// HTTP API, autostart and the editor's always-on-top. Nothing is saved if a
+/// hotkey, API or autostart change fails, and those changes are undone if
+/// saving fails. Every window gets a `settings-changed` event.
 #[tauri::command]
 pub fn save_settings(
+    app: tauri::AppHandle,
     state: tauri::State<'_, AppState>,
     settings: AppSettings,
 ) -> AppResult<AppSettings> {
+    let mut settings = settings;
+    settings_service::ensure_api_token(&mut settings);
+    let previous = settings_service::load_settings(&state.paths)?;
+    let changes = settings_service::diff_settings(&previous, &settings);
+
+    let previous_hotkeys = state.hotkeys.lock().clone();
+    if changes.hotkeys {
+        let report =
+            hotkey::apply_bindings(&app, &state, settings_service::hotkey_bindings(&settings));
+        if report.rolled_back {
+            return Err(hotkey::describe_failures(&report));
+        }
+        settings.general.last_known_good_hotkey = Some(settings.general.hotkey.clone());
+        *state.hotkey_fallback.lock() = None;
+    }
+
+    let applied = if changes.api {
+        api::apply_settings(&app, &state, &settings.api)
+    } else {
+        Ok(())
+    }
+    .and_then(|_| apply_auto_launch(&app, changes.auto_launch))
+    .and_then(|_| settings_service::save_settings(&state.paths, &settings));
+    if let Err(e) = applied {
+        if changes.hotkeys {
+            hotkey::apply_bindings(&app, &state, previous_hotkeys);
+        }
+        if changes.api {
+            if let Err(e) = api::apply_settings(&app, &state, &previous.api) {
+                eprintln!("Failed to restore HTTP API: {}", e);
+            }
+        }
+        // Autostart may have been switched before the write failed
+        if let Some(enabled) = changes.auto_launch {
+            if let Err(e) = apply_auto_launch(&app, Some(!enabled)) {
+                eprintln!("Failed to restore launch at login: {}", e);
+            }
+        }
+        return Err(e);
+    }
+
+    if let Some(on_top) = changes.editor_always_on_top {
+        if let Some(editor) = app.get_webview_window("editor") {
+            if let Err(e) = editor.set_always_on_top(on_top) {
+                eprintln!("Failed to update editor always-on-top: {}", e);
+            }
+        }
+    }
+
+    let _ = app.emit("settings-changed", &settings);
+    Ok(settings)
+}
+
+/// Replace the HTTP API token; the old one stops working immediately.
+#[tauri::command]
+pub fn regenerate_api_token(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, AppState>,
+) -> AppResult<AppSettings> {
+    let mut settings = settings_service::load_settings(&state.paths)?;
+    settings.api.token = settings_service::generate_api_token();
+    api::apply_settings(&app, &state, &settings.api)?;
     settings_service::save_settings(&state.paths, &settings)?;
+
+    let _ = app.emit("settings-changed", &settings);
     Ok(settings)
 }
+
+fn apply_auto_launch(app: &tauri::AppHandle, enabled: Option<bool>) -> AppResult<()> {
+    let result = match enabled {
+        Some(true) => app.autolaunch().enable(),
+        Some(false) => app.autolaunch().disable(),
+        None => return Ok(()),
+    };
+    result.map_err(|e| format!("Failed to update launch at login: {}", e))
+}
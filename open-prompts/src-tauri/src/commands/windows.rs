@@ -57,6 +57,26 @@ pub async fn paste_and_dismiss(
     Ok(())
 }
 
+/// Paste text into whichever window currently has focus, without showing any UI.
+/// Used by prompt hotkeys, where the target app never lost focus.
+pub async fn paste_into_foreground(app: &tauri::AppHandle, text: &str) -> AppResult<()> {
+    app.clipboard().write_text(text).map_err(|e| e.to_string())?;
+    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+    // The hotkey's own modifiers are likely still held down
+    #[cfg(target_os = "windows")]
+    win32::wait_for_modifier_release(500);
+
+    #[cfg(target_os = "windows")]
+    {
+        if !win32::send_ctrl_v() {
+            eprintln!("SendInput for Ctrl+V may have been blocked (UIPI or key state issue)");
+        }
+    }
+
+    Ok(())
+}
+
 /// Dismiss launcher without pasting
 #[tauri::command]
 pub async fn dismiss_window(app: tauri::AppHandle) -> AppResult<()> {
@@ -1,20 +1,41 @@
+mod api;
 mod commands;
 mod error;
+mod mcp;
 mod models;
 mod platform;
 mod services;
+mod single_instance;
 mod state;
+#[cfg(test)]
+mod test_support;
 
+use models::launch::{DeepLink, LaunchArgs};
+use services::launch_service;
 use services::storage::{self, StoragePaths};
+use single_instance::Instance;
 use tauri::menu::{Menu, MenuItem};
 use tauri::tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent};
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 use tauri_plugin_autostart::MacosLauncher;
 use state::AppState;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     let paths = resolve_storage_paths().expect("Failed to resolve storage paths");
+    storage::ensure_storage_dirs(&paths).expect("Failed to create storage directories");
+
+    // Hand our arguments to an already running instance instead of starting a second one
+    let launch_args = launch_service::parse_args(std::env::args().skip(1));
+    let instance_listener = match single_instance::acquire(&paths, &launch_args) {
+        Ok(Instance::Primary(listener)) => Some(listener),
+        Ok(Instance::Forwarded) => return,
+        Err(e) => {
+            eprintln!("Warning: single-instance check failed, starting anyway: {}", e);
+            None
+        }
+    };
+
     let app_state = AppState::new(paths.clone());
 
     tauri::Builder::default()
@@ -30,17 +51,56 @@ pub fn run() {
             // Data commands
             commands::data::get_index,
             commands::data::get_folders,
+            commands::data::get_folder_tree,
             commands::data::get_prompt,
             commands::data::save_prompt,
             commands::data::delete_prompt,
+            commands::data::duplicate_prompt,
+            commands::data::move_prompts,
+            commands::data::delete_prompts,
+            commands::data::tag_prompts,
+            commands::data::export_prompts,
+            commands::data::find_duplicates,
+            commands::data::merge_prompts,
+            commands::data::pin_prompt,
+            commands::data::unpin_prompt,
+            commands::data::reorder_prompts,
             commands::data::add_folder,
             commands::data::rename_folder,
+            commands::data::move_folder,
+            commands::data::update_folder_meta,
+            commands::data::reorder_folders,
             commands::data::delete_folder,
+            commands::data::render_prompt,
+            commands::data::get_paste_content,
+            commands::data::get_prompt_dependencies,
+            commands::data::validate_prompt_template,
             commands::data::search_prompts,
+            commands::data::create_saved_search,
+            commands::data::list_saved_searches,
+            commands::data::run_saved_search,
+            commands::data::delete_saved_search,
             commands::data::record_usage,
+            // Analytics commands
+            commands::analytics::get_daily_usage,
+            commands::analytics::get_weekly_usage,
+            commands::analytics::get_top_prompts,
+            commands::analytics::get_never_used_prompts,
+            commands::analytics::get_stale_prompts,
+            commands::analytics::compact_usage_log,
+            // Quick-add commands
+            commands::quick_add::get_quick_add_capture,
+            commands::quick_add::quick_add_prompt,
             // Settings commands
             commands::settings::get_settings,
             commands::settings::save_settings,
+            commands::settings::regenerate_api_token,
+            // Launch commands
+            commands::launch::take_pending_search,
+            commands::launch::take_pending_open_prompt,
+            commands::launch::get_pending_deep_link,
+            commands::launch::confirm_deep_link,
+            commands::launch::cancel_deep_link,
             // Window commands
             commands::windows::paste_and_dismiss,
             commands::windows::dismiss_window,
@@ -51,10 +111,13 @@ pub fn run() {
             // Hotkey commands
             commands::hotkey::get_current_hotkey,
             commands::hotkey::set_hotkey,
+            commands::hotkey::get_hotkey_bindings,
+            commands::hotkey::get_hotkey_fallback,
+            commands::hotkey::set_hotkey_bindings,
             commands::hotkey::pause_hotkey,
             commands::hotkey::resume_hotkey,
         ])
-        .setup(|app| {
+        .setup(move |app| {
             // Use the paths from the managed state
             let paths =
                 resolve_storage_paths().expect("Failed to resolve storage paths in setup");
@@ -63,18 +126,37 @@ pub fn run() {
             storage::ensure_storage_dirs(&paths).expect("Failed to create storage directories");
 
             // Load index and seed if needed
-            let mut index = services::index_service::load_index(&paths)
-                .expect("Failed to load prompt index");
-
-            if let Err(e) = services::seed_service::seed_if_needed(&paths, &mut index) {
+            let seeded = services::index_service::update_index(&paths, |index| {
+                services::seed_service::seed_if_needed(&paths, index)
+            });
+            if let Err(e) = seeded {
                 eprintln!("Warning: Failed to seed sample prompts: {}", e);
             }
 
-            // Register global hotkey
+            // Let the OS hand openprompts:// links to us
+            #[cfg(target_os = "windows")]
+            match std::env::current_exe() {
+                Ok(exe) => {
+                    if let Err(e) =
+                        platform::windows::register_url_scheme(launch_service::URL_SCHEME, &exe)
+                    {
+                        eprintln!("Warning: Failed to register link handler: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Warning: Failed to register link handler: {}", e),
+            }
+
+            // Register global hotkeys
             let state = app.state::<AppState>();
-            let hotkey = state.current_hotkey.lock().clone();
-            if let Err(e) = commands::hotkey::register_hotkey(&app.handle(), &state, &hotkey) {
-                eprintln!("Warning: Failed to register hotkey '{}': {}", hotkey, e);
+            let settings = services::settings_service::load_settings(&paths).unwrap_or_else(|e| {
+                eprintln!("Warning: Failed to load settings, using defaults: {}", e);
+                models::settings::AppSettings::default()
+            });
+            commands::hotkey::register_from_settings(app.handle(), &state, &settings);
+
+            // Start the local HTTP API if enabled
+            if let Err(e) = api::apply_settings(app.handle(), &state, &settings.api) {
+                eprintln!("Warning: {}", e);
             }
 
             // Build tray menu
@@ -123,12 +205,71 @@ pub fn run() {
 
             let _tray = tray_builder.build(app)?;
 
+            // Serve launches forwarded by later instances, then act on our own arguments
+            if let Some(listener) = instance_listener {
+                let handle = app.handle().clone();
+                single_instance::listen(listener, move |args| handle_launch_args(&handle, args));
+            }
+            if !launch_args.is_empty() {
+                handle_launch_args(app.handle(), launch_args);
+            }
+
             Ok(())
         })
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
 
+/// Serve the prompt library over MCP on stdin/stdout instead of starting the app.
+pub fn run_mcp() -> Result<(), String> {
+    let paths = resolve_storage_paths()?;
+    storage::ensure_storage_dirs(&paths)?;
+    mcp::serve(&paths)
+}
+
+/// Show the window a launch asked for. A bare second launch opens the editor.
+fn handle_launch_args(app: &tauri::AppHandle, args: LaunchArgs) {
+    let state = app.state::<AppState>();
+
+    if let Some(url) = &args.url {
+        match launch_service::parse_deep_link(url) {
+            Ok(DeepLink::Open { id }) => open_prompt_in_editor(app, &state, id),
+            // Links can come from any web page, so pasting and importing wait for a yes
+            Ok(link) => {
+                if let Err(e) = commands::launch::request_confirmation(app, link) {
+                    eprintln!("Ignoring link {}: {}", url, e);
+                }
+            }
+            Err(e) => eprintln!("Ignoring link {}: {}", url, e),
+        }
+    }
+
+    if args.open_editor || args.is_empty() {
+        show_editor_window(app);
+    }
+
+    if let Some(query) = args.search {
+        *state.pending_search.lock() = Some(query);
+        // Re-showing a visible launcher wouldn't fire the focus event that picks the query up
+        if let Some(launcher) = app.get_webview_window("launcher") {
+            if launcher.is_visible().unwrap_or(false) {
+                let _ = launcher.hide();
+            }
+        }
+        show_launcher_window(app);
+    }
+}
+
+/// An open editor is told directly; a new one picks the id up once it has loaded.
+fn open_prompt_in_editor(app: &tauri::AppHandle, state: &AppState, id: String) {
+    if app.get_webview_window("editor").is_some() {
+        let _ = app.emit_to("editor", "open-prompt", id);
+    } else {
+        *state.pending_open_prompt.lock() = Some(id);
+    }
+    show_editor_window(app);
+}
+
 fn show_launcher_window(app: &tauri::AppHandle) {
     if let Some(launcher) = app.get_webview_window("launcher") {
         let _ = launcher.show();
@@ -179,15 +320,5 @@ fn show_editor_window(app: &tauri::AppHandle) {
 
 fn resolve_storage_paths() -> Result<StoragePaths, String> {
     let home = dirs::home_dir().ok_or("Could not determine home directory")?;
-    let root = home.join(".openprompt");
-    let prompts_dir = root.join("prompts");
-    let index_path = root.join("index.json");
-    let settings_path = root.join("settings.json");
-
-    Ok(StoragePaths {
-        root,
-        prompts_dir,
-        index_path,
-        settings_path,
-    })
+    Ok(StoragePaths::in_dir(home.join(".openprompt")))
 }
@@ -2,5 +2,13 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    if std::env::args().any(|arg| arg == "--mcp") {
+        if let Err(e) = open_prompts_lib::run_mcp() {
+            eprintln!("MCP server stopped: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     open_prompts_lib::run()
 }
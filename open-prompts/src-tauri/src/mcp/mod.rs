@@ -0,0 +1,638 @@
+//! Model Context Protocol server over stdio, started with `open-prompts --mcp`.
+//! Every prompt is an MCP prompt whose arguments are its `{{variables}}`;
+//! searching and saving are exposed as tools.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use crate::commands::data;
+use crate::error::{map_err, AppResult};
+use crate::models::prompt::{Prompt, PromptIndex, PromptMetadata};
+use crate::services::search_service::SearchContext;
+use crate::services::storage::StoragePaths;
+use crate::services::{
+    folder_service, frecency_service, index_service, prompt_service, search_service,
+    settings_service, template_service,
+};
+use serde_json::{json, Value};
+
+/// Newest first; the first one is offered when the client asks for something else.
+const PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    fn internal(message: String) -> Self {
+        Self::new(INTERNAL_ERROR, message)
+    }
+}
+
+/// Serve newline-delimited JSON-RPC on stdin/stdout until stdin closes.
+pub fn serve(paths: &StoragePaths) -> AppResult<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout().lock();
+
+    for line in stdin.lock().lines() {
+        let line = line.map_err(map_err)?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Some(response) = handle_message(paths, &line) {
+            writeln!(stdout, "{}", response).map_err(map_err)?;
+            stdout.flush().map_err(map_err)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Handle one JSON-RPC message. Notifications and client responses get no reply.
+pub fn handle_message(paths: &StoragePaths, line: &str) -> Option<Value> {
+    let message: Value = match serde_json::from_str(line) {
+        Ok(message) => message,
+        Err(e) => return Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+    };
+
+    let id = message.get("id").cloned();
+    let method = match message.get("method").and_then(Value::as_str) {
+        Some(method) => method,
+        None if message.get("result").is_some() || message.get("error").is_some() => return None,
+        None => {
+            return Some(error_response(
+                id.unwrap_or(Value::Null),
+                INVALID_REQUEST,
+                "Missing method",
+            ))
+        }
+    };
+    let id = id?;
+
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    Some(match dispatch(paths, method, &params) {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(e) => error_response(id, e.code, &e.message),
+    })
+}
+
+fn dispatch(paths: &StoragePaths, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "initialize" => Ok(initialize(params)),
+        "ping" => Ok(json!({})),
+        "prompts/list" => list_prompts(paths),
+        "prompts/get" => get_prompt(paths, params),
+        "tools/list" => Ok(json!({ "tools": tool_definitions() })),
+        "tools/call" => call_tool(paths, params),
+        _ => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("Method '{}' not found", method),
+        )),
+    }
+}
+
+fn initialize(params: &Value) -> Value {
+    let requested = params.get("protocolVersion").and_then(Value::as_str);
+    let version = requested
+        .filter(|v| PROTOCOL_VERSIONS.contains(v))
+        .unwrap_or(PROTOCOL_VERSIONS[0]);
+
+    json!({
+        "protocolVersion": version,
+        "capabilities": {
+            "prompts": { "listChanged": false },
+            "tools": { "listChanged": false }
+        },
+        "serverInfo": {
+            "name": "openprompts",
+            "version": env!("CARGO_PKG_VERSION")
+        }
+    })
+}
+
+fn list_prompts(paths: &StoragePaths) -> Result<Value, RpcError> {
+    let index = index_service::load_index(paths).map_err(RpcError::internal)?;
+
+    let mut prompts = Vec::with_capacity(index.prompts.len());
+    for meta in &index.prompts {
+        // Unreadable files are skipped rather than failing the whole list
+        let prompt = match prompt_service::load_expanded(paths, &index, &meta.id) {
+            Ok(prompt) => prompt,
+            Err(e) => {
+                eprintln!("Skipping prompt '{}': {}", meta.id, e);
+                continue;
+            }
+        };
+        // Block names are optional: a missing one counts as false or empty
+        let placeholders = template_service::extract_placeholders(&prompt.content);
+        let arguments: Vec<Value> = template_service::extract_variables(&prompt.content)
+            .into_iter()
+            .map(|name| {
+                let required = placeholders.contains(&name);
+                json!({ "name": name, "required": required })
+            })
+            .collect();
+
+        prompts.push(json!({
+            "name": prompt_service::qualified_name(meta),
+            "title": meta.name,
+            "description": describe(meta),
+            "arguments": arguments
+        }));
+    }
+
+    Ok(json!({ "prompts": prompts }))
+}
+
+fn get_prompt(paths: &StoragePaths, params: &Value) -> Result<Value, RpcError> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing prompt name"))?;
+    let arguments = string_map(params.get("arguments"))?;
+
+    let index = index_service::load_index(paths).map_err(RpcError::internal)?;
+    let meta = find_prompt(&index, name)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Prompt '{}' not found", name)))?;
+    let prompt =
+        prompt_service::load_expanded(paths, &index, &meta.id).map_err(RpcError::internal)?;
+
+    let rendered =
+        prompt_service::render_expanded(paths, &index, prompt, &arguments).map_err(|e| {
+            RpcError::new(
+                INVALID_PARAMS,
+                format!("Prompt '{}' has a template error: {}", name, e),
+            )
+        })?;
+    if !rendered.missing_variables.is_empty() {
+        return Err(RpcError::new(
+            INVALID_PARAMS,
+            format!(
+                "Missing arguments: {}",
+                rendered.missing_variables.join(", ")
+            ),
+        ));
+    }
+
+    Ok(json!({
+        "description": describe(meta),
+        "messages": [{
+            "role": "user",
+            "content": { "type": "text", "text": rendered.content }
+        }]
+    }))
+}
+
+fn tool_definitions() -> Value {
+    json!([
+        {
+            "name": "search_prompts",
+            "description": "Search the prompt library. An empty query lists the most used prompts.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string" },
+                    "limit": { "type": "integer", "minimum": 1, "default": DEFAULT_SEARCH_LIMIT }
+                },
+                "required": ["query"]
+            }
+        },
+        {
+            "name": "save_prompt",
+            "description": "Create a prompt, or update one when `id` is given. Use {{name}} for variables.",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "string" },
+                    "name": { "type": "string" },
+                    "content": { "type": "string" },
+                    "folder": { "type": "string" },
+                    "description": { "type": "string" }
+                },
+                "required": ["name", "content"]
+            }
+        }
+    ])
+}
+
+/// Tool failures are reported in the result with `isError`, as MCP expects,
+/// so the model can see them. Unknown tools are protocol errors.
+fn call_tool(paths: &StoragePaths, params: &Value) -> Result<Value, RpcError> {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "Missing tool name"))?;
+    let arguments = params.get("arguments").cloned().unwrap_or(json!({}));
+
+    let outcome = match name {
+        "search_prompts" => search_tool(paths, &arguments),
+        "save_prompt" => save_tool(paths, &arguments),
+        _ => {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Tool '{}' not found", name),
+            ))
+        }
+    };
+
+    Ok(match outcome {
+        Ok(value) => json!({
+            "content": [{ "type": "text", "text": value.to_string() }],
+            "structuredContent": value,
+            "isError": false
+        }),
+        Err(e) => json!({
+            "content": [{ "type": "text", "text": e }],
+            "isError": true
+        }),
+    })
+}
+
+fn search_tool(paths: &StoragePaths, arguments: &Value) -> AppResult<Value> {
+    let query = arguments
+        .get("query")
+        .and_then(Value::as_str)
+        .unwrap_or("");
+    let limit = arguments
+        .get("limit")
+        .and_then(Value::as_u64)
+        .map(|l| l.max(1) as usize)
+        .unwrap_or(DEFAULT_SEARCH_LIMIT);
+
+    let index = index_service::load_index(paths)?;
+    let settings = settings_service::load_settings(paths)?;
+    let usage =
+        frecency_service::load_usage(paths, &index, settings.search.max_events_per_prompt)?;
+    let bodies = search_service::parse_query(query)
+        .in_body
+        .then(|| prompt_service::load_bodies(paths, &index));
+    let ctx = SearchContext {
+        usage: &usage,
+        settings: &settings.search,
+        target_app: None,
+        now: chrono::Utc::now(),
+        bodies: bodies.as_ref(),
+        similarities: None,
+    };
+
+    let results: Vec<Value> = search_service::search_prompts(&index.prompts, query, &ctx)
+        .iter()
+        .take(limit)
+        .map(|hit| {
+            let meta = &hit.meta;
+            json!({
+                "id": meta.id,
+                "name": prompt_service::qualified_name(meta),
+                "title": meta.name,
+                "folder": meta.folder,
+                "description": meta.description
+            })
+        })
+        .collect();
+
+    Ok(json!({ "results": results }))
+}
+
+fn save_tool(paths: &StoragePaths, arguments: &Value) -> AppResult<Value> {
+    let field = |key: &str| arguments.get(key).and_then(Value::as_str);
+    let name = field("name")
+        .filter(|n| !n.trim().is_empty())
+        .ok_or("`name` is required")?;
+    let content = field("content").ok_or("`content` is required")?;
+    let folder = field("folder")
+        .map(folder_service::normalize_path)
+        .transpose()?;
+
+    // The app may be running and changing the library at the same time
+    let saved = index_service::update_index(paths, |index| {
+        let existing = match field("id").filter(|id| !id.is_empty()) {
+            Some(id) => Some(prompt_service::load_prompt(paths, index, id)?.meta),
+            None => None,
+        };
+
+        let mut meta = existing.unwrap_or_else(|| PromptMetadata {
+            id: String::new(), // save_prompt will generate UUID
+            name: String::new(),
+            folder: String::new(),
+            description: String::new(),
+            filename: String::new(),
+            use_count: 0,
+            last_used: None,
+            created: String::new(),
+            updated: String::new(),
+            icon: None,
+            color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
+        });
+        meta.name = name.to_string();
+        if let Some(folder) = folder {
+            meta.folder = folder;
+        }
+        if let Some(description) = field("description") {
+            meta.description = description.to_string();
+        }
+
+        prompt_service::save_prompt(
+            paths,
+            index,
+            Prompt {
+                meta,
+                content: content.to_string(),
+            },
+        )
+    })?;
+    data::embed_saved_prompts(paths);
+
+    Ok(json!({ "id": saved.id, "name": prompt_service::qualified_name(&saved) }))
+}
+
+/// Accept either the MCP name or the prompt id.
+fn find_prompt<'a>(index: &'a PromptIndex, name: &str) -> Option<&'a PromptMetadata> {
+    index
+        .prompts
+        .iter()
+        .find(|p| prompt_service::qualified_name(p) == name)
+        .or_else(|| index.prompts.iter().find(|p| p.id == name))
+}
+
+fn describe(meta: &PromptMetadata) -> String {
+    if meta.description.trim().is_empty() {
+        meta.name.clone()
+    } else {
+        meta.description.clone()
+    }
+}
+
+/// MCP prompt arguments are string to string.
+fn string_map(value: Option<&Value>) -> Result<HashMap<String, String>, RpcError> {
+    let object = match value {
+        None | Some(Value::Null) => return Ok(HashMap::new()),
+        Some(Value::Object(object)) => object,
+        Some(_) => return Err(RpcError::new(INVALID_PARAMS, "`arguments` must be an object")),
+    };
+
+    object
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => Ok((key.clone(), s.clone())),
+            _ => Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("Argument '{}' must be a string", key),
+            )),
+        })
+        .collect()
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{add_prompt, temp_paths};
+    use std::fs;
+
+    /// Send `request` and return the reply, which must answer its id.
+    fn call(paths: &StoragePaths, request: Value) -> Value {
+        let reply = handle_message(paths, &request.to_string()).expect("a reply");
+        assert_eq!(reply["jsonrpc"], "2.0");
+        assert_eq!(reply["id"], request["id"]);
+        reply
+    }
+
+    fn call_tool(paths: &StoragePaths, id: u64, name: &str, arguments: Value) -> Value {
+        call(
+            paths,
+            json!({
+                "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": { "name": name, "arguments": arguments }
+            }),
+        )["result"]
+            .clone()
+    }
+
+    #[test]
+    fn a_client_session_saves_lists_gets_and_searches_prompts() {
+        let paths = temp_paths("session");
+
+        let reply = call(
+            &paths,
+            json!({
+                "jsonrpc": "2.0", "id": 1, "method": "initialize",
+                "params": { "protocolVersion": "2025-03-26", "capabilities": {} }
+            }),
+        );
+        assert_eq!(reply["result"]["protocolVersion"], "2025-03-26");
+        assert_eq!(reply["result"]["serverInfo"]["name"], "openprompts");
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle_message(&paths, &initialized.to_string()).is_none());
+
+        let tools = call(
+            &paths,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+        );
+        let names: Vec<&str> = tools["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["search_prompts", "save_prompt"]);
+
+        let saved = call_tool(
+            &paths,
+            3,
+            "save_prompt",
+            json!({
+                "name": "Review",
+                "folder": "/code\\rust/",
+                "content": "Review this {{language}} code"
+            }),
+        );
+        assert_eq!(saved["isError"], false);
+        assert_eq!(saved["structuredContent"]["name"], "code/rust/Review");
+
+        let list = call(
+            &paths,
+            json!({ "jsonrpc": "2.0", "id": 4, "method": "prompts/list" }),
+        );
+        let prompt = &list["result"]["prompts"][0];
+        assert_eq!(prompt["name"], "code/rust/Review");
+        assert_eq!(
+            prompt["arguments"],
+            json!([{ "name": "language", "required": true }])
+        );
+
+        let got = call(
+            &paths,
+            json!({
+                "jsonrpc": "2.0", "id": 5, "method": "prompts/get",
+                "params": { "name": "code/rust/Review", "arguments": { "language": "Rust" } }
+            }),
+        );
+        assert_eq!(
+            got["result"]["messages"][0]["content"]["text"],
+            "Review this Rust code"
+        );
+
+        let missing = call(
+            &paths,
+            json!({
+                "jsonrpc": "2.0", "id": 6, "method": "prompts/get",
+                "params": { "name": "code/rust/Review" }
+            }),
+        );
+        assert_eq!(missing["error"]["code"], INVALID_PARAMS);
+
+        let found = call_tool(&paths, 7, "search_prompts", json!({ "query": "review" }));
+        assert_eq!(found["structuredContent"]["results"][0]["title"], "Review");
+    }
+
+    #[test]
+    fn saving_updates_by_id_and_keeps_prompts_inside_the_library() {
+        let paths = temp_paths("save");
+        let saved = call_tool(
+            &paths,
+            1,
+            "save_prompt",
+            json!({ "name": "Draft", "content": "v1" }),
+        );
+        let id = saved["structuredContent"]["id"]
+            .as_str()
+            .unwrap()
+            .to_string();
+
+        let updated = call_tool(
+            &paths,
+            2,
+            "save_prompt",
+            json!({ "id": id, "name": "Final", "content": "v2", "description": "Done" }),
+        );
+        assert_eq!(updated["structuredContent"]["id"], id.as_str());
+        let index = index_service::load_index(&paths).unwrap();
+        assert_eq!(index.prompts.len(), 1);
+        assert_eq!(index.prompts[0].name, "Final");
+        assert_eq!(index.prompts[0].description, "Done");
+
+        let escape = call_tool(
+            &paths,
+            3,
+            "save_prompt",
+            json!({ "name": "Evil", "folder": "../../outside", "content": "x" }),
+        );
+        assert_eq!(escape["isError"], true);
+        assert!(!paths.root.parent().unwrap().join("outside").exists());
+        assert_eq!(index_service::load_index(&paths).unwrap().prompts.len(), 1);
+
+        let nameless = call_tool(&paths, 4, "save_prompt", json!({ "content": "x" }));
+        assert_eq!(nameless["isError"], true);
+    }
+
+    #[test]
+    fn only_placeholders_are_required_and_broken_templates_are_invalid() {
+        let paths = temp_paths("arguments");
+        add_prompt(
+            &paths,
+            "",
+            "Letter",
+            "{{#if formal}}Dear {{name}}{{else}}Hi{{/if}}\n{{#each points}}- {{this}}\n{{/each}}",
+        );
+        // Written on disk, as saving would refuse it
+        fs::write(
+            paths.prompts_dir.join("Broken.md"),
+            "Fine\n{{#if x}}never closed",
+        )
+        .unwrap();
+
+        let list = call(
+            &paths,
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "prompts/list" }),
+        );
+        let letter = list["result"]["prompts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["name"] == "Letter")
+            .unwrap()
+            .clone();
+        assert_eq!(
+            letter["arguments"],
+            json!([
+                { "name": "formal", "required": false },
+                { "name": "name", "required": true },
+                { "name": "points", "required": false }
+            ])
+        );
+
+        let broken = call(
+            &paths,
+            json!({
+                "jsonrpc": "2.0", "id": 2, "method": "prompts/get",
+                "params": { "name": "Broken" }
+            }),
+        );
+        assert_eq!(broken["error"]["code"], INVALID_PARAMS);
+        let message = broken["error"]["message"].as_str().unwrap();
+        assert!(message.contains("Line 2, column 1"), "{}", message);
+    }
+
+    #[test]
+    fn protocol_errors_are_reported() {
+        let paths = temp_paths("errors");
+
+        let parse = handle_message(&paths, "{not json").unwrap();
+        assert_eq!(parse["error"]["code"], PARSE_ERROR);
+        assert_eq!(parse["id"], Value::Null);
+
+        let no_method = call(&paths, json!({ "jsonrpc": "2.0", "id": 1 }));
+        assert_eq!(no_method["error"]["code"], INVALID_REQUEST);
+
+        let unknown = call(
+            &paths,
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "nope" }),
+        );
+        assert_eq!(unknown["error"]["code"], METHOD_NOT_FOUND);
+
+        let tool = call(
+            &paths,
+            json!({
+                "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+                "params": { "name": "nope" }
+            }),
+        );
+        assert_eq!(tool["error"]["code"], INVALID_PARAMS);
+
+        let ping = call(
+            &paths,
+            json!({ "jsonrpc": "2.0", "id": "a", "method": "ping" }),
+        );
+        assert_eq!(ping["result"], json!({}));
+
+        // Replies from the client need no answer
+        let reply = json!({ "jsonrpc": "2.0", "id": 9, "result": {} });
+        assert!(handle_message(&paths, &reply.to_string()).is_none());
+    }
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// What a global hotkey does when pressed.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum HotkeyAction {
+    ToggleLauncher,
+    QuickAdd,
+    PasteLastUsed,
+    /// Paste a specific prompt into the focused window without showing any UI
+    PastePrompt {
+        #[serde(rename = "promptId")]
+        prompt_id: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyBinding {
+    pub accelerator: String,
+    pub action: HotkeyAction,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyFailure {
+    pub binding: HotkeyBinding,
+    pub error: String,
+}
+
+/// Outcome of (re)registering a set of hotkeys. When `rolled_back` is true,
+/// none of the requested bindings are active and the previous set was restored.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyReport {
+    pub registered: Vec<HotkeyBinding>,
+    pub failures: Vec<HotkeyFailure>,
+    pub rolled_back: bool,
+}
+
+/// Sent as the `hotkey-fallback` event when the configured launcher hotkey
+/// could not be registered. `active` is the hotkey in use instead, if any.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct HotkeyFallback {
+    pub requested: String,
+    pub active: Option<String>,
+    pub error: String,
+}
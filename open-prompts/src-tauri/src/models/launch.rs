@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// What a launch asked for. A second launch sends this to the running instance.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchArgs {
+    /// `--open-editor`
+    pub open_editor: bool,
+    /// `--search <query>`: open the launcher with this query
+    pub search: Option<String>,
+    /// An `openprompts://` link
+    pub url: Option<String>,
+}
+
+impl LaunchArgs {
+    pub fn is_empty(&self) -> bool {
+        !self.open_editor && self.search.is_none() && self.url.is_none()
+    }
+}
+
+/// A validated `openprompts://` link.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum DeepLink {
+    /// `open/<id>`: show the prompt in the editor
+    Open { id: String },
+    /// `paste/<id>?var=value`: render and paste into the focused window
+    Paste {
+        id: String,
+        variables: HashMap<String, String>,
+    },
+    /// `import?url=file:///...`: add a Markdown or text file as a new prompt
+    Import { path: String },
+}
+
+/// A link waiting for the user to confirm it in the confirmation window.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingDeepLink {
+    pub link: DeepLink,
+    /// What will happen, in words, e.g. `Paste "Code Review" into Notepad`
+    pub summary: String,
+}
@@ -1,2 +1,6 @@
+pub mod hotkey;
+pub mod launch;
 pub mod prompt;
+pub mod search;
 pub mod settings;
+pub mod usage;
@@ -1,6 +1,11 @@
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
 
+/// Bump when the index layout changes and add a matching step to
+/// `index_service::MIGRATIONS`. Files without a version are version 0.
+pub const CURRENT_INDEX_VERSION: u32 = 1;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PromptMetadata {
@@ -15,6 +20,18 @@ pub struct PromptMetadata {
     pub updated: String,
     pub icon: Option<String>,
     pub color: Option<String>,
+    /// Pinned prompts lead the launcher's list
+    #[serde(default)]
+    pub pinned: bool,
+    /// Manual position set by `reorder_prompts`; ordered prompts come before the rest
+    #[serde(default)]
+    pub sort_index: Option<u32>,
+    /// Free-form labels, stored without a leading `#`
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Fields this release doesn't know about, kept so saving doesn't drop them.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -25,30 +42,208 @@ pub struct Prompt {
     pub content: String,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct FolderMeta {
+    /// Folder path. Left out of the folder's sidecar file, where the directory says it.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub name: String,
     pub icon: Option<String>,
     pub color: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    /// Position among its sibling folders; folders without one come last, by name
+    #[serde(default)]
+    pub sort_index: Option<u32>,
+}
+
+/// A folder and everything below it, as shown in the editor's folder tree.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderNode {
+    /// Last path segment; empty for the root
+    pub name: String,
+    /// Full path such as `coding/rust`; empty for the root
+    pub path: String,
+    /// Prompts directly in this folder
+    pub prompt_count: usize,
+    /// Prompts in this folder and every folder below it
+    pub total_prompt_count: usize,
+    pub meta: Option<FolderMeta>,
+    pub children: Vec<FolderNode>,
+}
+
+/// What happens to the prompts and subfolders of a deleted folder.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FolderDeleteMode {
+    /// Move them to the top level, keeping the subfolders
+    #[default]
+    MoveToRoot,
+    /// Move them up into the deleted folder's parent, keeping the subfolders
+    MoveToParent,
+    /// Delete every prompt in the folder and its subfolders
+    DeleteContents,
+}
+
+/// A named query that lists its results like a folder, such as
+/// "tagged #review in Engineering/" or "never used".
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedSearch {
+    pub name: String,
+    /// Text matched like the launcher's search box; empty matches everything
+    #[serde(default)]
+    pub query: String,
+    /// Prompts must carry every one of these tags
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Only prompts in this folder or below it; empty for the whole library
+    #[serde(default)]
+    pub folder_prefix: String,
+    #[serde(default)]
+    pub usage: UsageFilter,
+    #[serde(default)]
+    pub sort: SavedSearchSort,
+}
+
+/// Which prompts a saved search keeps, by when they were last used.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", tag = "kind")]
+pub enum UsageFilter {
+    #[default]
+    Any,
+    Never,
+    /// Used in the last `days` days
+    #[serde(rename_all = "camelCase")]
+    WithinDays { days: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SavedSearchSort {
+    /// The launcher's order: best match, pinned and frecent first
+    #[default]
+    Relevance,
+    RecentlyUsed,
+    MostUsed,
+    RecentlyUpdated,
+    Name,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PromptIndex {
+    #[serde(default)]
+    pub version: u32,
     pub prompts: Vec<PromptMetadata>,
     pub folders: Vec<String>,
     pub folder_meta: Option<HashMap<String, FolderMeta>>,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
     pub seeded: bool,
+    /// Top-level fields this release doesn't know about.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
 }
 
 impl Default for PromptIndex {
     fn default() -> Self {
         Self {
+            version: CURRENT_INDEX_VERSION,
             prompts: Vec::new(),
             folders: Vec::new(),
             folder_meta: None,
+            saved_searches: Vec::new(),
             seeded: false,
+            extra: Map::new(),
         }
     }
 }
+
+/// A prompt body with its `{{variables}}` filled in.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderedPrompt {
+    pub id: String,
+    pub content: String,
+    /// Variables that had no value and were left as `{{name}}`
+    pub missing_variables: Vec<String>,
+}
+
+/// A problem in a prompt's template, located in its `.md` file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TemplateError {
+    pub message: String,
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+}
+
+impl std::fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let TemplateError {
+            message,
+            line,
+            column,
+        } = self;
+        write!(f, "Line {}, column {}: {}", line, column, message)
+    }
+}
+
+/// The outcome of one prompt in a bulk operation.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkItemResult {
+    pub id: String,
+    /// Why this prompt couldn't be handled; `None` if it could
+    pub error: Option<String>,
+}
+
+/// Result of a bulk operation. It is applied to every prompt or to none:
+/// if any item has an error, nothing was changed.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkReport {
+    pub applied: bool,
+    pub items: Vec<BulkItemResult>,
+}
+
+/// Prompts with their content, as written by `export_prompts`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptExport {
+    #[serde(flatten)]
+    pub report: BulkReport,
+    pub exported_at: String,
+    /// Empty unless the report was applied
+    pub prompts: Vec<Prompt>,
+}
+
+/// Prompts with the same or nearly the same content.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    /// Every prompt has the same content once case, accents and spacing are ignored
+    pub exact: bool,
+    /// Lowest estimated similarity, from 0 to 1, of two prompts that put
+    /// the group together; 1 for exact duplicates
+    pub similarity: f64,
+    /// Most used first, the likeliest one to keep
+    pub prompts: Vec<PromptMetadata>,
+}
+
+/// How a prompt is tied to others through `{{> folder/name}}` includes.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptDependencies {
+    pub id: String,
+    /// Prompts this one includes directly
+    pub includes: Vec<PromptMetadata>,
+    /// Include targets that match no prompt
+    pub unresolved: Vec<String>,
+    /// Prompts that include this one, directly or through other includes
+    pub included_by: Vec<PromptMetadata>,
+}
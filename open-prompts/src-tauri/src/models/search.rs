@@ -0,0 +1,41 @@
+use crate::models::prompt::PromptMetadata;
+use serde::{Deserialize, Serialize};
+
+/// A prompt found by a search, with what matched so the UI can highlight it.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub meta: PromptMetadata,
+    /// Ranking score; higher ranks first. Only comparable within one search.
+    pub score: f64,
+    /// Fields where the query matched; fields without a match are left out
+    pub matches: Vec<FieldMatch>,
+    /// A piece of the body around its best match, or its start if the body
+    /// didn't match. `None` if the body wasn't searched.
+    pub snippet: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldMatch {
+    pub field: SearchField,
+    /// Sorted and non-overlapping. For `body` they index into the snippet.
+    pub ranges: Vec<MatchRange>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchField {
+    Name,
+    Description,
+    Folder,
+    Body,
+}
+
+/// Matched characters `start..end`, counted in characters rather than bytes.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchRange {
+    pub start: usize,
+    pub end: usize,
+}
@@ -1,41 +1,146 @@
 use serde::{Deserialize, Serialize};
 
+/// Bump when the settings layout changes and add a matching step to
+/// `settings_service::MIGRATIONS`. Files without a version are version 0.
+pub const CURRENT_SETTINGS_VERSION: u32 = 1;
+
+// Every settings struct is `#[serde(default)]` so a field added in a newer
+// release falls back to its default instead of failing the whole file.
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct GeneralSettings {
     pub auto_launch: bool,
     pub hotkey: String,
     pub editor_always_on_top: bool,
     pub welcome_screen_dismissed: bool,
+    /// Last launcher hotkey that registered successfully, used as a fallback
+    pub last_known_good_hotkey: Option<String>,
+    /// Folder that quick-add captures are saved into ("" = root)
+    pub quick_add_folder: String,
+}
+
+impl Default for GeneralSettings {
+    fn default() -> Self {
+        Self {
+            auto_launch: false,
+            hotkey: "CommandOrControl+8".to_string(),
+            editor_always_on_top: true,
+            welcome_screen_dismissed: false,
+            last_known_good_hotkey: None,
+            quick_add_folder: String::new(),
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct AppearanceSettings {
     pub theme: String,
     pub accent_color: String,
 }
 
+impl Default for AppearanceSettings {
+    fn default() -> Self {
+        Self {
+            theme: "dark".to_string(),
+            accent_color: "avocado".to_string(),
+        }
+    }
+}
+
+/// A hotkey that pastes one prompt directly.
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
+pub struct PromptHotkey {
+    pub accelerator: String,
+    pub prompt_id: String,
+}
+
+/// Hotkeys besides the launcher one, which stays in `GeneralSettings::hotkey`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HotkeySettings {
+    pub quick_add: Option<String>,
+    pub paste_last_used: Option<String>,
+    pub prompts: Vec<PromptHotkey>,
+}
+
+/// Frecency ranking knobs. A weight of 0 disables that signal.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SearchSettings {
+    /// Days after which a use counts half as much
+    pub frecency_half_life_days: f64,
+    /// Weight of overall frecency
+    pub frecency_weight: f64,
+    /// Weight of frecency inside the current target app
+    pub context_weight: f64,
+    /// Latest uses per prompt that count toward frecency
+    pub max_events_per_prompt: usize,
+    /// Also rank by meaning, using a local embedding model. Needs a build
+    /// with the `semantic-search` feature.
+    pub semantic: bool,
+    /// Weight of a prompt's similarity to the query
+    pub semantic_weight: f64,
+    /// Cosine similarity below which a prompt isn't considered related
+    pub semantic_min_similarity: f64,
+}
+
+impl Default for SearchSettings {
+    fn default() -> Self {
+        Self {
+            frecency_half_life_days: 14.0,
+            frecency_weight: 3.0,
+            context_weight: 5.0,
+            max_events_per_prompt: 50,
+            semantic: false,
+            semantic_weight: 20.0,
+            semantic_min_similarity: 0.4,
+        }
+    }
+}
+
+/// Opt-in HTTP API for scripts and editor plugins. Always bound to 127.0.0.1.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    /// Bearer token every request must send; generated when the API is enabled
+    pub token: String,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: 47821,
+            token: String::new(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase", default)]
 pub struct AppSettings {
+    pub version: u32,
     pub general: GeneralSettings,
     pub appearance: AppearanceSettings,
+    pub search: SearchSettings,
+    pub hotkeys: HotkeySettings,
+    pub api: ApiSettings,
 }
 
 impl Default for AppSettings {
     fn default() -> Self {
         Self {
-            general: GeneralSettings {
-                auto_launch: false,
-                hotkey: "CommandOrControl+8".to_string(),
-                editor_always_on_top: true,
-                welcome_screen_dismissed: false,
-            },
-            appearance: AppearanceSettings {
-                theme: "dark".to_string(),
-                accent_color: "avocado".to_string(),
-            },
+            version: CURRENT_SETTINGS_VERSION,
+            general: GeneralSettings::default(),
+            appearance: AppearanceSettings::default(),
+            search: SearchSettings::default(),
+            hotkeys: HotkeySettings::default(),
+            api: ApiSettings::default(),
         }
     }
 }
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Clone, Debug)]
+pub struct UsageEvent {
+    pub timestamp: String,
+    /// Executable name of the target window, e.g. "code.exe"
+    pub app: Option<String>,
+}
+
+/// Recent usage events per prompt id, built from `usage.log.jsonl`.
+#[derive(Clone, Debug, Default)]
+pub struct UsageStore {
+    pub events: HashMap<String, Vec<UsageEvent>>,
+}
+
+/// How a prompt reached the target application.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum DeliveryMode {
+    Paste,
+    Copy,
+}
+
+/// One line of the append-only `usage.log.jsonl`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageLogEntry {
+    pub prompt_id: String,
+    pub timestamp: String,
+    pub target_app: Option<String>,
+    pub delivery_mode: DeliveryMode,
+    pub variables_filled: bool,
+}
+
+/// Number of uses in one period; `period` is `YYYY-MM-DD` or ISO week `YYYY-Www`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageBucket {
+    pub period: String,
+    pub count: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PromptUsageStat {
+    pub id: String,
+    pub name: String,
+    pub folder: String,
+    pub count: u64,
+    pub last_used: Option<String>,
+}
@@ -1,25 +1,33 @@
 #![cfg(target_os = "windows")]
 
 use std::mem::size_of;
+use std::path::Path;
 use std::thread;
 use std::time::{Duration, Instant};
 
-use windows::Win32::Foundation::{BOOL, HWND};
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{CloseHandle, BOOL, HWND};
 use windows::Win32::Graphics::Gdi::{
 	GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST,
 };
+use windows::Win32::System::DataExchange::{CountClipboardFormats, IsClipboardFormatAvailable};
+use windows::Win32::System::Registry::{RegSetKeyValueW, HKEY_CURRENT_USER, REG_SZ};
 use windows::Win32::System::Threading::{
-	AttachThreadInput, GetCurrentProcessId, GetCurrentThreadId,
+	AttachThreadInput, GetCurrentProcessId, GetCurrentThreadId, OpenProcess,
+	QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION,
 };
 use windows::Win32::UI::Input::KeyboardAndMouse::{
 	GetAsyncKeyState, SendInput, VIRTUAL_KEY, INPUT, INPUT_0, INPUT_KEYBOARD,
-	KEYBDINPUT, KEYEVENTF_KEYUP, VK_CONTROL, VK_MENU, VK_V,
+	KEYBDINPUT, KEYEVENTF_KEYUP, VK_C, VK_CONTROL, VK_MENU, VK_SHIFT, VK_V,
 };
 use windows::Win32::UI::WindowsAndMessaging::{
 	GetForegroundWindow, GetWindowThreadProcessId, IsIconic, IsWindow, SetForegroundWindow,
 	ShowWindow, SW_RESTORE,
 };
 
+/// Standard clipboard format for UTF-16 text.
+const CF_UNICODETEXT: u32 = 13;
+
 fn hwnd_from_isize(raw: isize) -> HWND {
 	HWND(raw as *mut std::ffi::c_void)
 }
@@ -53,6 +61,43 @@ pub fn capture_foreground_hwnd(own_pid: u32) -> Option<isize> {
 	Some(hwnd_to_isize(foreground))
 }
 
+/// Lowercase executable name (e.g. "code.exe") of the process owning the window.
+pub fn get_window_process_name(raw: isize) -> Option<String> {
+	let hwnd = hwnd_from_isize(raw);
+	let mut pid = 0u32;
+	// SAFETY: hwnd is a window handle from the OS; pid points to valid writable memory.
+	unsafe {
+		GetWindowThreadProcessId(hwnd, Some(&mut pid));
+	}
+	if pid == 0 {
+		return None;
+	}
+
+	// SAFETY: Limited query access is requested for a pid obtained from the OS.
+	let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, BOOL(0), pid) }.ok()?;
+
+	let mut buffer = [0u16; 1024];
+	let mut len = buffer.len() as u32;
+	// SAFETY: buffer is writable for `len` UTF-16 units; len is updated to the written length.
+	let query = unsafe {
+		QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len)
+	};
+	// SAFETY: process is a handle opened above and not used afterwards.
+	unsafe {
+		let _ = CloseHandle(process);
+	}
+
+	if let Err(e) = query {
+		eprintln!("get_window_process_name: QueryFullProcessImageNameW failed for pid={pid}: {e}");
+		return None;
+	}
+
+	let image_path = String::from_utf16_lossy(&buffer[..len as usize]);
+	Path::new(&image_path)
+		.file_name()
+		.map(|name| name.to_string_lossy().to_lowercase())
+}
+
 pub fn is_valid_window(hwnd: isize) -> bool {
 	let hwnd = hwnd_from_isize(hwnd);
 	// SAFETY: IsWindow accepts any HWND value and reports validity.
@@ -135,11 +180,14 @@ pub fn wait_for_modifier_release(timeout_ms: u64) {
 		let ctrl_state = unsafe { GetAsyncKeyState(VK_CONTROL.0 as i32) };
 		// SAFETY: GetAsyncKeyState is safe to call for virtual key constants.
 		let alt_state = unsafe { GetAsyncKeyState(VK_MENU.0 as i32) };
+		// SAFETY: GetAsyncKeyState is safe to call for virtual key constants.
+		let shift_state = unsafe { GetAsyncKeyState(VK_SHIFT.0 as i32) };
 
 		let ctrl_down = (ctrl_state as u16 & 0x8000) != 0;
 		let alt_down = (alt_state as u16 & 0x8000) != 0;
+		let shift_down = (shift_state as u16 & 0x8000) != 0;
 
-		if !ctrl_down && !alt_down {
+		if !ctrl_down && !alt_down && !shift_down {
 			return;
 		}
 
@@ -148,6 +196,23 @@ pub fn wait_for_modifier_release(timeout_ms: u64) {
 }
 
 pub fn send_ctrl_v() -> bool {
+	send_ctrl_chord(VK_V, "send_ctrl_v")
+}
+
+/// Copy the current selection of the focused window.
+pub fn send_ctrl_c() -> bool {
+	send_ctrl_chord(VK_C, "send_ctrl_c")
+}
+
+/// Whether the clipboard holds something other than text, such as an image or files.
+pub fn clipboard_has_non_text() -> bool {
+	// SAFETY: CountClipboardFormats has no preconditions and doesn't need the clipboard open.
+	let formats = unsafe { CountClipboardFormats() };
+	// SAFETY: IsClipboardFormatAvailable only queries a format id and doesn't need the clipboard open.
+	formats > 0 && unsafe { IsClipboardFormatAvailable(CF_UNICODETEXT) }.is_err()
+}
+
+fn send_ctrl_chord(key: VIRTUAL_KEY, label: &str) -> bool {
 	let inputs = [
 		INPUT {
 			r#type: INPUT_KEYBOARD,
@@ -165,7 +230,7 @@ pub fn send_ctrl_v() -> bool {
 			r#type: INPUT_KEYBOARD,
 			Anonymous: INPUT_0 {
 				ki: KEYBDINPUT {
-					wVk: VIRTUAL_KEY(VK_V.0),
+					wVk: key,
 					wScan: 0,
 					dwFlags: Default::default(),
 					time: 0,
@@ -177,7 +242,7 @@ pub fn send_ctrl_v() -> bool {
 			r#type: INPUT_KEYBOARD,
 			Anonymous: INPUT_0 {
 				ki: KEYBDINPUT {
-					wVk: VIRTUAL_KEY(VK_V.0),
+					wVk: key,
 					wScan: 0,
 					dwFlags: KEYEVENTF_KEYUP,
 					time: 0,
@@ -203,7 +268,7 @@ pub fn send_ctrl_v() -> bool {
 	let sent = unsafe { SendInput(&inputs, size_of::<INPUT>() as i32) };
 	if sent != inputs.len() as u32 {
 		eprintln!(
-			"send_ctrl_v: SendInput sent {sent} of {} events (possible UIPI blocking)",
+			"{label}: SendInput sent {sent} of {} events (possible UIPI blocking)",
 			inputs.len()
 		);
 		return false;
@@ -247,3 +312,50 @@ pub fn get_launcher_position(
 
 	Some((x, y))
 }
+
+/// Register `scheme://` links for the current user so Windows starts `exe` with
+/// the link as its only argument. Rewritten on every start so a moved install keeps working.
+pub fn register_url_scheme(scheme: &str, exe: &Path) -> Result<(), String> {
+	let key = format!(r"Software\Classes\{}", scheme);
+	set_registry_string(&key, None, &format!("URL:{} link", scheme))?;
+	set_registry_string(&key, Some("URL Protocol"), "")?;
+	set_registry_string(
+		&format!(r"{}\shell\open\command", key),
+		None,
+		&format!("\"{}\" \"%1\"", exe.display()),
+	)
+}
+
+/// Write a REG_SZ value under HKEY_CURRENT_USER, creating the key if needed.
+/// `None` writes the key's default value.
+fn set_registry_string(subkey: &str, name: Option<&str>, value: &str) -> Result<(), String> {
+	let subkey_w = to_wide(subkey);
+	let name_w = name.map(to_wide);
+	let data = to_wide(value);
+	let name_ptr = name_w
+		.as_ref()
+		.map_or(PCWSTR::null(), |name| PCWSTR(name.as_ptr()));
+
+	// SAFETY: every pointer refers to a NUL-terminated UTF-16 buffer that outlives the
+	// call, and the byte count covers `data` including its terminator.
+	let status = unsafe {
+		RegSetKeyValueW(
+			HKEY_CURRENT_USER,
+			PCWSTR(subkey_w.as_ptr()),
+			name_ptr,
+			REG_SZ.0,
+			Some(data.as_ptr() as *const std::ffi::c_void),
+			(data.len() * size_of::<u16>()) as u32,
+		)
+	};
+
+	if status.is_ok() {
+		Ok(())
+	} else {
+		Err(format!("Failed to write registry key {}: error {}", subkey, status.0))
+	}
+}
+
+fn to_wide(value: &str) -> Vec<u16> {
+	value.encode_utf16().chain(std::iter::once(0)).collect()
+}
@@ -0,0 +1,461 @@
+//! Operations on several prompts at once. Every id is checked before anything
+//! is touched, and the operation is applied to all of them or to none.
+
+use crate::error::{map_err, AppResult};
+use crate::models::prompt::{BulkItemResult, BulkReport, PromptExport, PromptIndex};
+use crate::services::storage::StoragePaths;
+use crate::services::{folder_service, prompt_service};
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+/// Move prompts into `folder`, renaming files that would clash. If a file
+/// can't be moved, the files already moved are put back.
+pub fn move_prompts(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    ids: &[String],
+    folder: &str,
+) -> AppResult<BulkReport> {
+    let folder = folder_service::normalize_path(folder)?;
+    let report = check_ids(index, ids);
+    if !report.applied {
+        return Ok(report);
+    }
+
+    let mut prompts = index.prompts.clone();
+    let mut moved = Vec::new();
+    let now = chrono::Utc::now().to_rfc3339();
+    for prompt in prompts
+        .iter_mut()
+        .filter(|p| ids.contains(&p.id) && p.folder != folder)
+    {
+        let old_path = prompt_service::get_prompt_path(paths, &prompt.folder, &prompt.filename);
+        let filename = prompt_service::ensure_unique_filename(paths, &folder, &prompt.name);
+        let new_path = prompt_service::get_prompt_path(paths, &folder, &filename);
+
+        // A prompt whose file is already gone only moves in the index
+        let result = if old_path.exists() {
+            new_path
+                .parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .and_then(|_| fs::rename(&old_path, &new_path))
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result {
+            for (from, to) in moved.into_iter().rev() {
+                let _ = fs::rename(to, from);
+            }
+            return Ok(failed(ids, &[(prompt.id.clone(), map_err(e))]));
+        }
+
+        moved.push((old_path, new_path));
+        prompt.folder = folder.clone();
+        prompt.filename = filename;
+        prompt.updated = now.clone();
+    }
+
+    index.prompts = prompts;
+    if !folder.is_empty() && !index.folders.contains(&folder) {
+        index.folders.push(folder);
+    }
+    Ok(report)
+}
+
+/// Delete prompts. If a file can't be removed, e.g. because another program
+/// has it open, the files already removed are put back.
+pub fn delete_prompts(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    ids: &[String],
+) -> AppResult<BulkReport> {
+    let report = check_ids(index, ids);
+    if !report.applied {
+        return Ok(report);
+    }
+    if let Err(errors) = remove_files(paths, index, ids) {
+        return Ok(failed(ids, &errors));
+    }
+    index.prompts.retain(|p| !ids.contains(&p.id));
+    Ok(report)
+}
+
+/// Add `tags` to each prompt, keeping the tags it already has.
+pub fn tag_prompts(
+    index: &mut PromptIndex,
+    ids: &[String],
+    tags: &[String],
+) -> AppResult<BulkReport> {
+    let tags = prompt_service::normalize_tags(tags);
+    if tags.is_empty() {
+        return Err("No tags given".to_string());
+    }
+    let report = check_ids(index, ids);
+    if !report.applied {
+        return Ok(report);
+    }
+
+    let now = chrono::Utc::now().to_rfc3339();
+    for prompt in index.prompts.iter_mut().filter(|p| ids.contains(&p.id)) {
+        let combined: Vec<String> = prompt.tags.iter().chain(&tags).cloned().collect();
+        prompt.tags = prompt_service::normalize_tags(&combined);
+        prompt.updated = now.clone();
+    }
+    Ok(report)
+}
+
+/// Fold the prompts `remove_ids` into `keep_id`: their use counts are added to
+/// it, it keeps the latest `last_used`, and they are deleted. A prompt that a
+/// remaining prompt includes isn't removed, since the include would break.
+pub fn merge_prompts(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    keep_id: &str,
+    remove_ids: &[String],
+) -> AppResult<BulkReport> {
+    if !index.prompts.iter().any(|p| p.id == keep_id) {
+        return Err(format!("Prompt '{}' not found", keep_id));
+    }
+    if remove_ids.is_empty() {
+        return Err("No prompts to merge".to_string());
+    }
+
+    let mut report = check_ids(index, remove_ids);
+    for item in report.items.iter_mut().filter(|item| item.error.is_none()) {
+        if item.id == keep_id {
+            item.error = Some("The prompt to keep can't also be removed".to_string());
+            continue;
+        }
+        match prompt_service::prompt_dependencies(paths, index, &item.id) {
+            Ok(dependencies) => {
+                item.error = dependencies
+                    .included_by
+                    .iter()
+                    .find(|p| !remove_ids.contains(&p.id))
+                    .map(|p| format!("Prompt '{}' is included by '{}'", item.id, p.name));
+            }
+            Err(e) => item.error = Some(e),
+        }
+    }
+    report.applied = report.items.iter().all(|item| item.error.is_none());
+    if !report.applied {
+        return Ok(report);
+    }
+
+    if let Err(errors) = remove_files(paths, index, remove_ids) {
+        return Ok(failed(remove_ids, &errors));
+    }
+    let removed: Vec<(u64, Option<String>)> = index
+        .prompts
+        .iter()
+        .filter(|p| remove_ids.contains(&p.id))
+        .map(|p| (p.use_count, p.last_used.clone()))
+        .collect();
+    if let Some(keep) = index.prompts.iter_mut().find(|p| p.id == keep_id) {
+        for (use_count, last_used) in removed {
+            keep.use_count += use_count;
+            keep.last_used = keep.last_used.take().max(last_used);
+        }
+        keep.updated = chrono::Utc::now().to_rfc3339();
+    }
+    index.prompts.retain(|p| !remove_ids.contains(&p.id));
+    Ok(report)
+}
+
+/// Prompts with their content, in the order of `ids`. Nothing is exported
+/// if any of them can't be read.
+pub fn export_prompts(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    ids: &[String],
+) -> AppResult<PromptExport> {
+    let mut report = check_ids(index, ids);
+    let mut prompts = Vec::new();
+    if report.applied {
+        for item in &mut report.items {
+            match prompt_service::load_prompt(paths, index, &item.id) {
+                Ok(prompt) => prompts.push(prompt),
+                Err(e) => item.error = Some(e),
+            }
+        }
+        report.applied = report.items.iter().all(|item| item.error.is_none());
+        if !report.applied {
+            prompts.clear();
+        }
+    }
+
+    Ok(PromptExport {
+        report,
+        exported_at: chrono::Utc::now().to_rfc3339(),
+        prompts,
+    })
+}
+
+/// Delete the files of the prompts `ids`, all of them or none. Each is first
+/// moved aside, and if any can't be, the others are put back and the ids that
+/// failed are returned with the reason. Files already gone are skipped.
+fn remove_files(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    ids: &[String],
+) -> Result<(), Vec<(String, String)>> {
+    let aside_dir = paths
+        .root
+        .join(format!("deleting-{}", uuid::Uuid::new_v4()));
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut errors = Vec::new();
+    for (n, prompt) in index
+        .prompts
+        .iter()
+        .filter(|p| ids.contains(&p.id))
+        .enumerate()
+    {
+        let path = prompt_service::get_prompt_path(paths, &prompt.folder, &prompt.filename);
+        if !path.exists() {
+            continue;
+        }
+        let aside = aside_dir.join(n.to_string());
+        match fs::create_dir_all(&aside_dir).and_then(|_| fs::rename(&path, &aside)) {
+            Ok(()) => moved.push((path, aside)),
+            Err(e) => errors.push((prompt.id.clone(), map_err(e))),
+        }
+    }
+
+    if !errors.is_empty() {
+        for (from, to) in moved.into_iter().rev() {
+            let _ = fs::rename(to, from);
+        }
+        // Only removed once empty, so a file that couldn't be put back isn't lost
+        let _ = fs::remove_dir(&aside_dir);
+        return Err(errors);
+    }
+    if !moved.is_empty() {
+        if let Err(e) = fs::remove_dir_all(&aside_dir) {
+            eprintln!("Failed to remove deleted prompts in {:?}: {}", aside_dir, e);
+        }
+    }
+    Ok(())
+}
+
+/// One result per id: unknown and repeated ids are errors.
+fn check_ids(index: &PromptIndex, ids: &[String]) -> BulkReport {
+    let mut seen = HashSet::new();
+    let items: Vec<BulkItemResult> = ids
+        .iter()
+        .map(|id| {
+            let error = if !seen.insert(id.as_str()) {
+                Some(format!("Prompt '{}' is listed twice", id))
+            } else if !index.prompts.iter().any(|p| &p.id == id) {
+                Some(format!("Prompt '{}' not found", id))
+            } else {
+                None
+            };
+            BulkItemResult {
+                id: id.clone(),
+                error,
+            }
+        })
+        .collect();
+
+    BulkReport {
+        applied: items.iter().all(|item| item.error.is_none()),
+        items,
+    }
+}
+
+/// A report where each of `errors` failed with its reason, by id, and nothing was applied.
+fn failed(ids: &[String], errors: &[(String, String)]) -> BulkReport {
+    BulkReport {
+        applied: false,
+        items: ids
+            .iter()
+            .map(|item| BulkItemResult {
+                id: item.clone(),
+                error: errors
+                    .iter()
+                    .find(|(id, _)| id == item)
+                    .map(|(_, error)| error.clone()),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::index_service;
+    use crate::test_support::{add_prompt, temp_paths};
+
+    fn file_of(paths: &StoragePaths, index: &PromptIndex, id: &str) -> PathBuf {
+        let meta = index.prompts.iter().find(|p| p.id == id).unwrap();
+        prompt_service::get_prompt_path(paths, &meta.folder, &meta.filename)
+    }
+
+    #[test]
+    fn deleting_removes_every_file_and_entry() {
+        let paths = temp_paths("delete");
+        let a = add_prompt(&paths, "", "A", "A");
+        let b = add_prompt(&paths, "work", "B", "B");
+        let keep = add_prompt(&paths, "", "Keep", "Keep");
+        let mut index = index_service::load_index(&paths).unwrap();
+        let files = [file_of(&paths, &index, &a), file_of(&paths, &index, &b)];
+
+        let report = delete_prompts(&paths, &mut index, &[a, b]).unwrap();
+        assert!(report.applied);
+        assert_eq!(index.prompts.len(), 1);
+        assert_eq!(index.prompts[0].id, keep);
+        assert!(files.iter().all(|file| !file.exists()));
+        // Nothing is left over in the data folder
+        let leftovers = fs::read_dir(&paths.root)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("deleting-")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_file_that_cannot_be_removed_keeps_every_prompt() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let paths = temp_paths("delete-stuck");
+        let a = add_prompt(&paths, "", "A", "A");
+        let stuck = add_prompt(&paths, "locked", "Stuck", "Stuck");
+        let mut index = index_service::load_index(&paths).unwrap();
+        let files = [file_of(&paths, &index, &a), file_of(&paths, &index, &stuck)];
+
+        let locked = paths.prompts_dir.join("locked");
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+        let probe = locked.join("probe");
+        if fs::write(&probe, "").is_ok() {
+            // Permissions don't stop this user, e.g. root, so there's nothing to test
+            let _ = fs::remove_file(&probe);
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+            return;
+        }
+
+        let ids = [a.clone(), stuck.clone()];
+        let report = delete_prompts(&paths, &mut index, &ids).unwrap();
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert!(!report.applied);
+        let errors: Vec<(&str, bool)> = report
+            .items
+            .iter()
+            .map(|item| (item.id.as_str(), item.error.is_some()))
+            .collect();
+        assert_eq!(errors, [(a.as_str(), false), (stuck.as_str(), true)]);
+        assert_eq!(index.prompts.len(), 2);
+        assert!(files.iter().all(|file| file.exists()));
+    }
+
+    #[test]
+    fn unknown_or_repeated_ids_delete_nothing() {
+        let paths = temp_paths("delete-invalid");
+        let a = add_prompt(&paths, "", "A", "A");
+        let mut index = index_service::load_index(&paths).unwrap();
+
+        let ids = [a.clone(), "missing".to_string(), a.clone()];
+        let report = delete_prompts(&paths, &mut index, &ids).unwrap();
+        assert!(!report.applied);
+        let errors: Vec<Option<&str>> = report
+            .items
+            .iter()
+            .map(|item| item.error.as_deref())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                None,
+                Some("Prompt 'missing' not found"),
+                Some(format!("Prompt '{}' is listed twice", a).as_str())
+            ]
+        );
+        assert!(file_of(&paths, &index, &a).exists());
+    }
+
+    #[test]
+    fn merging_sums_use_and_keeps_the_latest_use() {
+        let paths = temp_paths("merge");
+        let keep = add_prompt(&paths, "", "Keep", "Keep");
+        let a = add_prompt(&paths, "", "A", "A");
+        let b = add_prompt(&paths, "work", "B", "B");
+        let other = add_prompt(&paths, "", "Other", "Other");
+        let mut index = index_service::load_index(&paths).unwrap();
+        let usage = [
+            (&keep, 3, Some("2026-03-01T00:00:00+00:00")),
+            (&a, 2, Some("2026-05-01T00:00:00+00:00")),
+            (&b, 4, None),
+            (&other, 7, Some("2026-09-01T00:00:00+00:00")),
+        ];
+        for (id, use_count, last_used) in usage {
+            let meta = index.prompts.iter_mut().find(|p| &p.id == id).unwrap();
+            meta.use_count = use_count;
+            meta.last_used = last_used.map(str::to_string);
+        }
+        let files = [file_of(&paths, &index, &a), file_of(&paths, &index, &b)];
+
+        let report = merge_prompts(&paths, &mut index, &keep, &[a, b]).unwrap();
+        assert!(report.applied);
+        assert_eq!(index.prompts.len(), 2);
+        let usage_of = |id: &str| {
+            let meta = index.prompts.iter().find(|p| p.id == id).unwrap();
+            (meta.use_count, meta.last_used.clone())
+        };
+        assert_eq!(
+            usage_of(&keep),
+            (9, Some("2026-05-01T00:00:00+00:00".to_string()))
+        );
+        assert_eq!(
+            usage_of(&other),
+            (7, Some("2026-09-01T00:00:00+00:00".to_string()))
+        );
+        assert!(files.iter().all(|file| !file.exists()));
+        assert!(file_of(&paths, &index, &keep).exists());
+    }
+
+    #[test]
+    fn merging_keeps_prompts_still_needed() {
+        let paths = temp_paths("merge-refused");
+        let keep = add_prompt(&paths, "", "Keep", "Keep");
+        let a = add_prompt(&paths, "", "A", "A");
+        let user = add_prompt(&paths, "", "User", "User");
+        let mut index = index_service::load_index(&paths).unwrap();
+        let included =
+            prompt_service::qualified_name(index.prompts.iter().find(|p| p.id == a).unwrap());
+        fs::write(
+            file_of(&paths, &index, &user),
+            format!("{{{{> {}}}}}", included),
+        )
+        .unwrap();
+
+        let report = merge_prompts(&paths, &mut index, &keep, &[a.clone(), keep.clone()]).unwrap();
+        assert!(!report.applied);
+        let errors: Vec<Option<&str>> = report
+            .items
+            .iter()
+            .map(|item| item.error.as_deref())
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                Some(format!("Prompt '{}' is included by 'User'", a).as_str()),
+                Some("The prompt to keep can't also be removed"),
+            ]
+        );
+        assert_eq!(index.prompts.len(), 3);
+        assert!(file_of(&paths, &index, &a).exists());
+
+        // Removing the prompt that includes it as well is fine
+        let report = merge_prompts(&paths, &mut index, &keep, &[a, user]).unwrap();
+        assert!(report.applied);
+        assert_eq!(index.prompts.len(), 1);
+    }
+}
@@ -0,0 +1,349 @@
+//! Finding prompts with the same or nearly the same content. Case, accents and
+//! spacing are ignored. Near duplicates are found by MinHash: each prompt gets
+//! a signature whose positions agree between two prompts about as often as
+//! their sets of word shingles overlap. Only prompts that share a band of their
+//! signatures are compared, so not every pair has to be. The lower the
+//! threshold, the shorter the bands, so that pairs just above it still share one.
+
+use crate::error::AppResult;
+use crate::models::prompt::{DuplicateGroup, PromptIndex, PromptMetadata};
+use crate::services::storage::StoragePaths;
+use crate::services::{fuzzy_service, prompt_service};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+
+/// Used when no threshold is given
+pub const DEFAULT_THRESHOLD: f64 = 0.8;
+/// Below this, even bands of one position would miss similar pairs
+pub const MIN_THRESHOLD: f64 = 0.1;
+/// Words per shingle
+const SHINGLE_WORDS: usize = 3;
+const SIGNATURE_LEN: usize = 64;
+/// How likely two prompts exactly at the threshold should be to share a band
+const CATCH_PROBABILITY: f64 = 0.99;
+
+type Signature = [u64; SIGNATURE_LEN];
+
+/// Groups of prompts whose content is the same, or at least `threshold`
+/// similar, from `MIN_THRESHOLD` to 1. Empty prompts and prompts whose file can't be read
+/// are left out. Largest groups come first.
+pub fn find_duplicates(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    threshold: f64,
+) -> AppResult<Vec<DuplicateGroup>> {
+    if !(MIN_THRESHOLD..=1.0).contains(&threshold) {
+        return Err(format!(
+            "Similarity threshold must be between {} and 1",
+            MIN_THRESHOLD
+        ));
+    }
+
+    let bodies = prompt_service::load_bodies(paths, index);
+    let prompts: Vec<(&PromptMetadata, String)> = index
+        .prompts
+        .iter()
+        .filter_map(|p| {
+            let text = normalize(bodies.get(&p.id)?);
+            (!text.is_empty()).then_some((p, text))
+        })
+        .collect();
+    let hashes: Vec<String> = prompts
+        .iter()
+        .map(|(_, text)| prompt_service::content_hash(text.as_bytes()))
+        .collect();
+
+    // Pairs of prompts that belong together, with their similarity
+    let mut links: Vec<(usize, usize, f64)> = Vec::new();
+
+    let mut first_with_hash: HashMap<&str, usize> = HashMap::new();
+    for (i, hash) in hashes.iter().enumerate() {
+        match first_with_hash.entry(hash) {
+            Entry::Occupied(first) => links.push((*first.get(), i, 1.0)),
+            Entry::Vacant(slot) => {
+                slot.insert(i);
+            }
+        }
+    }
+
+    let signatures: Vec<Option<Signature>> =
+        prompts.iter().map(|(_, text)| signature(text)).collect();
+    let band_rows = band_rows(threshold);
+    let mut buckets: HashMap<(usize, &[u64]), Vec<usize>> = HashMap::new();
+    for (i, signature) in signatures.iter().enumerate() {
+        if let Some(signature) = signature {
+            for (band, rows) in signature.chunks(band_rows).enumerate() {
+                buckets.entry((band, rows)).or_default().push(i);
+            }
+        }
+    }
+    let mut compared = HashSet::new();
+    for members in buckets.values() {
+        for (n, &a) in members.iter().enumerate() {
+            for &b in &members[n + 1..] {
+                if hashes[a] == hashes[b] || !compared.insert((a, b)) {
+                    continue;
+                }
+                if let (Some(sa), Some(sb)) = (&signatures[a], &signatures[b]) {
+                    let similarity = estimate_similarity(sa, sb);
+                    if similarity >= threshold {
+                        links.push((a, b, similarity));
+                    }
+                }
+            }
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..prompts.len()).collect();
+    for &(a, b, _) in &links {
+        let (root_a, root_b) = (find_root(&mut parent, a), find_root(&mut parent, b));
+        parent[root_a] = root_b;
+    }
+    let mut lowest: HashMap<usize, f64> = HashMap::new();
+    for &(a, _, similarity) in &links {
+        let entry = lowest.entry(find_root(&mut parent, a)).or_insert(1.0);
+        *entry = entry.min(similarity);
+    }
+    let mut members: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..prompts.len() {
+        members
+            .entry(find_root(&mut parent, i))
+            .or_default()
+            .push(i);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = members
+        .into_iter()
+        .filter(|(_, group)| group.len() > 1)
+        .map(|(root, group)| {
+            let exact = group.iter().all(|&i| hashes[i] == hashes[group[0]]);
+            let mut metas: Vec<PromptMetadata> =
+                group.iter().map(|&i| prompts[i].0.clone()).collect();
+            metas.sort_by(|a, b| {
+                b.use_count
+                    .cmp(&a.use_count)
+                    .then_with(|| b.last_used.cmp(&a.last_used))
+            });
+            DuplicateGroup {
+                exact,
+                similarity: if exact { 1.0 } else { lowest[&root] },
+                prompts: metas,
+            }
+        })
+        .collect();
+    groups.sort_by(|a, b| {
+        b.prompts
+            .len()
+            .cmp(&a.prompts.len())
+            .then_with(|| b.similarity.total_cmp(&a.similarity))
+            .then_with(|| a.prompts[0].name.cmp(&b.prompts[0].name))
+    });
+    Ok(groups)
+}
+
+/// Lowercase, without accents, with runs of whitespace made one space.
+fn normalize(text: &str) -> String {
+    fuzzy_service::fold(text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The smallest hash of any shingle under each of the signature's hash
+/// functions. `None` for text without words.
+fn signature(text: &str) -> Option<Signature> {
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut signature = [u64::MAX; SIGNATURE_LEN];
+    for shingle in words.windows(SHINGLE_WORDS.min(words.len())) {
+        let mut hasher = DefaultHasher::new();
+        shingle.hash(&mut hasher);
+        let base = hasher.finish();
+        for (i, slot) in signature.iter_mut().enumerate() {
+            *slot = (*slot).min(mix(base ^ mix(i as u64)));
+        }
+    }
+    Some(signature)
+}
+
+/// Share of signature positions that agree, which estimates the Jaccard
+/// similarity of the two prompts' shingles.
+fn estimate_similarity(a: &Signature, b: &Signature) -> f64 {
+    let same = a.iter().zip(b).filter(|(x, y)| x == y).count();
+    same as f64 / SIGNATURE_LEN as f64
+}
+
+/// Signature positions per band for `threshold`. Longer bands mean fewer
+/// dissimilar prompts compared, so this is the longest that still catches a
+/// pair at the threshold with `CATCH_PROBABILITY`.
+fn band_rows(threshold: f64) -> usize {
+    (1..=SIGNATURE_LEN)
+        .rev()
+        .find(|&rows| catch_probability(threshold, rows) >= CATCH_PROBABILITY)
+        .unwrap_or(1)
+}
+
+/// Chance that two prompts `similarity` similar agree on every position of
+/// at least one band. A shorter last band only adds to it.
+fn catch_probability(similarity: f64, rows: usize) -> f64 {
+    let bands = SIGNATURE_LEN / rows;
+    1.0 - (1.0 - similarity.powi(rows as i32)).powi(bands as i32)
+}
+
+/// SplitMix64's finalizer: scrambles `x` well enough that each seed mixed in
+/// acts as a separate hash function.
+fn mix(x: u64) -> u64 {
+    let x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+fn find_root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::index_service;
+    use crate::test_support::{add_prompt, temp_paths};
+
+    const REVIEW: &str = "Review the following code for bugs, unclear names and missing \
+        tests. Point out each problem with the line it is on, explain why it matters and \
+        suggest a fix. Keep the tone friendly and the answer short, and end with a summary \
+        of the most important changes to make first.";
+
+    /// Names of each group's prompts, sorted
+    fn names(groups: &[DuplicateGroup]) -> Vec<Vec<String>> {
+        groups
+            .iter()
+            .map(|group| {
+                let mut names: Vec<String> = group.prompts.iter().map(|p| p.name.clone()).collect();
+                names.sort();
+                names
+            })
+            .collect()
+    }
+
+    #[test]
+    fn normalizing_ignores_case_accents_and_spacing() {
+        assert_eq!(normalize("  Résumé\tTHE   text\n"), "resume the text");
+        assert_eq!(normalize(" \n "), "");
+    }
+
+    #[test]
+    fn signatures_estimate_shingle_overlap() {
+        assert!(signature("... !").is_none());
+        let review = signature(REVIEW).unwrap();
+        assert_eq!(review, signature(REVIEW).unwrap());
+        assert_eq!(estimate_similarity(&review, &review), 1.0);
+
+        let edited = signature(&REVIEW.replace("friendly", "neutral")).unwrap();
+        let similarity = estimate_similarity(&review, &edited);
+        assert!((0.6..1.0).contains(&similarity), "{}", similarity);
+
+        let other = signature("Translate this email into formal German please").unwrap();
+        assert!(estimate_similarity(&review, &other) < 0.1);
+        // Short texts are one shingle, not none
+        assert!(signature("hello").is_some());
+    }
+
+    #[test]
+    fn bands_get_shorter_as_the_threshold_drops() {
+        let rows: Vec<usize> = [1.0, DEFAULT_THRESHOLD, 0.5, MIN_THRESHOLD]
+            .into_iter()
+            .map(band_rows)
+            .collect();
+        assert_eq!(rows[0], SIGNATURE_LEN);
+        assert!(rows.windows(2).all(|pair| pair[0] > pair[1]), "{:?}", rows);
+        for threshold in [DEFAULT_THRESHOLD, 0.5, 0.3, MIN_THRESHOLD] {
+            let rows = band_rows(threshold);
+            assert!(catch_probability(threshold, rows) >= CATCH_PROBABILITY);
+        }
+    }
+
+    #[test]
+    fn thresholds_too_low_to_find_pairs_are_rejected() {
+        let paths = temp_paths("threshold");
+        let index = index_service::load_index(&paths).unwrap();
+        for threshold in [0.0, 0.05, 1.5, f64::NAN] {
+            assert!(find_duplicates(&paths, &index, threshold).is_err());
+        }
+        assert!(find_duplicates(&paths, &index, MIN_THRESHOLD).is_ok());
+    }
+
+    #[test]
+    fn prompts_are_grouped_by_content_and_similarity() {
+        let paths = temp_paths("groups");
+        add_prompt(&paths, "", "Review", REVIEW);
+        add_prompt(
+            &paths,
+            "",
+            "Review copy",
+            &REVIEW.to_uppercase().replace(' ', "  "),
+        );
+        add_prompt(
+            &paths,
+            "",
+            "Review edited",
+            &REVIEW.replace("friendly", "neutral"),
+        );
+        add_prompt(
+            &paths,
+            "",
+            "Translate",
+            "Translate this email into formal German",
+        );
+        add_prompt(
+            &paths,
+            "",
+            "Translate again",
+            "translate THIS email into formal german",
+        );
+        add_prompt(
+            &paths,
+            "",
+            "Summarize",
+            "Summarize the text below in three bullet points",
+        );
+        add_prompt(&paths, "", "Empty", " ");
+        add_prompt(&paths, "", "Also empty", "\n");
+        let index = index_service::load_index(&paths).unwrap();
+
+        let groups = find_duplicates(&paths, &index, 0.6).unwrap();
+        assert_eq!(
+            names(&groups),
+            [
+                vec!["Review", "Review copy", "Review edited"],
+                vec!["Translate", "Translate again"],
+            ]
+        );
+        assert!(!groups[0].exact);
+        assert!(groups[0].similarity >= 0.6 && groups[0].similarity < 1.0);
+        assert!(groups[1].exact);
+        assert_eq!(groups[1].similarity, 1.0);
+
+        // Only the exact copies are left once near duplicates don't count
+        let groups = find_duplicates(&paths, &index, 1.0).unwrap();
+        assert_eq!(
+            names(&groups),
+            [
+                vec!["Review", "Review copy"],
+                vec!["Translate", "Translate again"],
+            ]
+        );
+        assert!(groups.iter().all(|group| group.exact));
+    }
+}
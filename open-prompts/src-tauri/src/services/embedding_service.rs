@@ -0,0 +1,462 @@
+//! Embeddings for semantic search. Each prompt's name, description and body
+//! are embedded with a small model that runs locally on the CPU. Its files are
+//! read from `models/embedding` in the data folder and nothing is downloaded.
+//! Vectors are kept in `embeddings.json` by a hash of the embedded text, so only
+//! new and edited prompts are embedded again. That happens on a background
+//! thread, so searching never waits for it: a prompt that isn't embedded yet
+//! is ranked by its keywords alone.
+
+use crate::error::{map_err, AppResult};
+use crate::models::prompt::PromptIndex;
+use crate::services::storage::{atomic_write, stamp, FileStamp, StoragePaths};
+use crate::services::{index_service, prompt_service};
+use fastembed::{
+    InitOptionsUserDefined, Pooling, TextEmbedding, TokenizerFiles, UserDefinedEmbeddingModel,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+const MODEL_DIR: &str = "models/embedding";
+const MODEL_FILES: [&str; 5] = [
+    "model.onnx",
+    "tokenizer.json",
+    "config.json",
+    "special_tokens_map.json",
+    "tokenizer_config.json",
+];
+const STORE_FILE: &str = "embeddings.json";
+/// Prompts embedded per model run
+const BATCH_SIZE: usize = 32;
+/// Query vectors kept, so searching the same text again doesn't run the model
+const QUERY_CACHE_SIZE: usize = 64;
+
+/// What's known about the data folder in use. The lock is only held briefly;
+/// the model runs and files are read outside it.
+static STATE: Mutex<Option<Embeddings>> = parking_lot::const_mutex(None);
+
+struct Embeddings {
+    root: PathBuf,
+    model: ModelState,
+    /// Hash of each prompt's embedded text by prompt id, as of the last refresh
+    hashes: HashMap<String, String>,
+    store: Option<EmbeddingStore>,
+    /// `fingerprint` of the index the last refresh embedded
+    refreshed: Option<String>,
+    refreshing: bool,
+    /// The library changed while a refresh ran, so another one follows it
+    pending: bool,
+    queries: HashMap<String, Vec<f32>>,
+}
+
+#[derive(Clone)]
+enum ModelState {
+    NotLoaded,
+    /// Loaded once and kept, since loading takes much longer than embedding
+    Loaded(Arc<LoadedModel>),
+    /// Loading failed with the model files as stamped; tried again once they change
+    Failed(Vec<Option<FileStamp>>),
+}
+
+struct LoadedModel {
+    /// Hash of the model file; vectors from another model are thrown away
+    id: String,
+    embedding: TextEmbedding,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EmbeddingStore {
+    model: String,
+    /// Vectors by `prompt_service::content_hash` of the embedded text
+    vectors: HashMap<String, Vec<f32>>,
+}
+
+/// Embed new and edited prompts on a background thread and drop vectors no
+/// prompt uses any more. Asked for while a refresh runs, another one follows.
+pub fn refresh_in_background(paths: &StoragePaths) {
+    with_state(paths, |state| start_refresh(state, paths));
+}
+
+/// Cosine similarity of each embedded prompt to `query`, by prompt id. `None`
+/// while the model isn't loaded, or can't be. A library that changed since it
+/// was last embedded is embedded again in the background meanwhile.
+pub fn similarities(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    query: &str,
+) -> Option<HashMap<String, f32>> {
+    let fingerprint = fingerprint(index);
+    let (model, cached) = with_state(paths, |state| {
+        if let ModelState::Failed(stamps) = &state.model {
+            if *stamps != model_stamps(paths) {
+                state.model = ModelState::NotLoaded;
+                state.refreshed = None;
+            }
+        }
+        let stale = state.refreshed.as_ref() != Some(&fingerprint);
+        if stale && !state.refreshing && !matches!(state.model, ModelState::Failed(_)) {
+            start_refresh(state, paths);
+        }
+        match &state.model {
+            ModelState::Loaded(model) => Some((model.clone(), state.queries.get(query).cloned())),
+            _ => None,
+        }
+    })?;
+
+    let query_vector = match cached {
+        Some(vector) => vector,
+        None => {
+            let vector = match embed(&model, vec![query.to_string()]) {
+                Ok(mut vectors) => vectors.remove(0),
+                Err(e) => {
+                    eprintln!("Semantic search unavailable: {}", e);
+                    return None;
+                }
+            };
+            with_state(paths, |state| {
+                if state.queries.len() >= QUERY_CACHE_SIZE {
+                    state.queries.clear();
+                }
+                state.queries.insert(query.to_string(), vector.clone());
+            });
+            vector
+        }
+    };
+
+    with_state(paths, |state| {
+        let store = state
+            .store
+            .as_ref()
+            .filter(|store| store.model == model.id)?;
+        Some(
+            state
+                .hashes
+                .iter()
+                .filter_map(|(id, hash)| {
+                    let vector = store.vectors.get(hash)?;
+                    Some((id.clone(), cosine_similarity(&query_vector, vector)))
+                })
+                .collect(),
+        )
+    })
+}
+
+/// Run `f` on the state of the data folder in `paths`, starting over if
+/// another folder was used before.
+fn with_state<T>(paths: &StoragePaths, f: impl FnOnce(&mut Embeddings) -> T) -> T {
+    let mut guard = STATE.lock();
+    if guard.as_ref().is_none_or(|state| state.root != paths.root) {
+        *guard = Some(Embeddings {
+            root: paths.root.clone(),
+            model: ModelState::NotLoaded,
+            hashes: HashMap::new(),
+            store: None,
+            refreshed: None,
+            refreshing: false,
+            pending: false,
+            queries: HashMap::new(),
+        });
+    }
+    f(guard.as_mut().expect("state was just set"))
+}
+
+fn start_refresh(state: &mut Embeddings, paths: &StoragePaths) {
+    if state.refreshing {
+        state.pending = true;
+        return;
+    }
+    state.refreshing = true;
+    let paths = paths.clone();
+    thread::spawn(move || loop {
+        if let Err(e) = refresh(&paths) {
+            eprintln!("Failed to embed prompts: {}", e);
+        }
+        let again = with_state(&paths, |state| {
+            let again = std::mem::take(&mut state.pending);
+            state.refreshing = again;
+            again
+        });
+        if !again {
+            break;
+        }
+    });
+}
+
+/// Embed the prompts of the library whose text isn't embedded yet, save the
+/// store if it changed and hand it to searches.
+fn refresh(paths: &StoragePaths) -> AppResult<()> {
+    let model = match model(paths) {
+        Some(model) => model,
+        None => return Ok(()),
+    };
+    let index = index_service::load_index(paths)?;
+    let fingerprint = fingerprint(&index);
+    let bodies = prompt_service::load_bodies(paths, &index);
+    // (id, hash, text) of each prompt whose file could be read
+    let prompts: Vec<(String, String, String)> = index
+        .prompts
+        .iter()
+        .filter_map(|p| {
+            let text = embedding_text(&p.name, &p.description, bodies.get(&p.id)?);
+            Some((
+                p.id.clone(),
+                prompt_service::content_hash(text.as_bytes()),
+                text,
+            ))
+        })
+        .collect();
+    let mut store = load_store(paths, &model.id);
+    if update_store(&mut store, &prompts, |texts| embed(&model, texts))? {
+        save_store(paths, &store)?;
+    }
+
+    let hashes = prompts
+        .into_iter()
+        .map(|(id, hash, _)| (id, hash))
+        .collect();
+    with_state(paths, |state| {
+        state.hashes = hashes;
+        state.store = Some(store);
+        state.refreshed = Some(fingerprint);
+    });
+    Ok(())
+}
+
+/// Embed the texts of `prompts`, given as (id, hash, text), that `store` has
+/// no vector for and drop the vectors none of them uses. Prompts with the same
+/// text share a vector. Returns whether the store changed.
+fn update_store(
+    store: &mut EmbeddingStore,
+    prompts: &[(String, String, String)],
+    embed: impl FnOnce(Vec<String>) -> AppResult<Vec<Vec<f32>>>,
+) -> AppResult<bool> {
+    let missing: HashMap<&String, &String> = prompts
+        .iter()
+        .filter(|(_, hash, _)| !store.vectors.contains_key(hash))
+        .map(|(_, hash, text)| (hash, text))
+        .collect();
+    let used: HashSet<&String> = prompts.iter().map(|(_, hash, _)| hash).collect();
+    let changed = !missing.is_empty() || store.vectors.keys().any(|hash| !used.contains(hash));
+    store.vectors.retain(|hash, _| used.contains(hash));
+
+    if !missing.is_empty() {
+        let (missing_hashes, texts): (Vec<&String>, Vec<String>) = missing
+            .into_iter()
+            .map(|(hash, text)| (hash, text.clone()))
+            .unzip();
+        let vectors = embed(texts)?;
+        store
+            .vectors
+            .extend(missing_hashes.into_iter().cloned().zip(vectors));
+    }
+    Ok(changed)
+}
+
+/// Changes whenever a prompt is added, removed or edited, in the app or on disk.
+fn fingerprint(index: &PromptIndex) -> String {
+    let mut text = String::new();
+    for p in &index.prompts {
+        for field in [&p.id, &p.name, &p.description, &p.updated] {
+            text.push_str(field);
+            text.push('\0');
+        }
+    }
+    prompt_service::content_hash(text.as_bytes())
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
+
+fn embedding_text(name: &str, description: &str, body: &str) -> String {
+    format!("{}\n{}\n{}", name, description, body)
+}
+
+fn embed(model: &LoadedModel, texts: Vec<String>) -> AppResult<Vec<Vec<f32>>> {
+    model
+        .embedding
+        .embed(texts, Some(BATCH_SIZE))
+        .map_err(|e| format!("Could not compute embeddings: {}", e))
+}
+
+/// The model in the data folder, loading it on first use. A model that can't
+/// be loaded is only tried again once its files change, so the failure is
+/// logged once.
+fn model(paths: &StoragePaths) -> Option<Arc<LoadedModel>> {
+    let stamps = model_stamps(paths);
+    match with_state(paths, |state| state.model.clone()) {
+        ModelState::Loaded(model) => return Some(model),
+        ModelState::Failed(failed) if failed == stamps => return None,
+        _ => {}
+    }
+
+    let loaded = load_model(&paths.root.join(MODEL_DIR));
+    with_state(paths, |state| {
+        state.queries.clear();
+        state.model = match loaded {
+            Ok(model) => ModelState::Loaded(Arc::new(model)),
+            Err(e) => {
+                eprintln!("Semantic search unavailable: {}", e);
+                ModelState::Failed(stamps)
+            }
+        };
+        match &state.model {
+            ModelState::Loaded(model) => Some(model.clone()),
+            _ => None,
+        }
+    })
+}
+
+fn model_stamps(paths: &StoragePaths) -> Vec<Option<FileStamp>> {
+    let dir = paths.root.join(MODEL_DIR);
+    MODEL_FILES
+        .iter()
+        .map(|name| stamp(&dir.join(name)))
+        .collect()
+}
+
+fn load_model(dir: &Path) -> AppResult<LoadedModel> {
+    let read = |name: &str| {
+        fs::read(dir.join(name)).map_err(|e| {
+            format!(
+                "Semantic search needs an embedding model in {:?}; could not read {}: {}",
+                dir, name, e
+            )
+        })
+    };
+    let onnx_file = read("model.onnx")?;
+    let tokenizer_files = TokenizerFiles {
+        tokenizer_file: read("tokenizer.json")?,
+        config_file: read("config.json")?,
+        special_tokens_map_file: read("special_tokens_map.json")?,
+        tokenizer_config_file: read("tokenizer_config.json")?,
+    };
+
+    let id = format!(
+        "{}-{}",
+        onnx_file.len(),
+        prompt_service::content_hash(&onnx_file)
+    );
+    let model =
+        UserDefinedEmbeddingModel::new(onnx_file, tokenizer_files).with_pooling(Pooling::Mean);
+    let embedding = TextEmbedding::try_new_from_user_defined(model, InitOptionsUserDefined::new())
+        .map_err(|e| format!("Could not load the embedding model: {}", e))?;
+    Ok(LoadedModel { id, embedding })
+}
+
+/// The store for `model`. A missing or unreadable store, or one written with
+/// another model, starts over empty.
+fn load_store(paths: &StoragePaths, model: &str) -> EmbeddingStore {
+    let store = fs::read_to_string(paths.root.join(STORE_FILE))
+        .ok()
+        .and_then(|json| serde_json::from_str::<EmbeddingStore>(&json).ok())
+        .filter(|store| store.model == model);
+    store.unwrap_or_else(|| EmbeddingStore {
+        model: model.to_string(),
+        vectors: HashMap::new(),
+    })
+}
+
+fn save_store(paths: &StoragePaths, store: &EmbeddingStore) -> AppResult<()> {
+    let json = serde_json::to_string(store).map_err(map_err)?;
+    atomic_write(&paths.root.join(STORE_FILE), json.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{metadata, temp_paths};
+    use serde_json::json;
+
+    fn store(model: &str, hashes: &[&str]) -> EmbeddingStore {
+        EmbeddingStore {
+            model: model.to_string(),
+            vectors: hashes.iter().map(|h| (h.to_string(), vec![1.0])).collect(),
+        }
+    }
+
+    fn prompt(id: &str, text: &str) -> (String, String, String) {
+        let hash = prompt_service::content_hash(text.as_bytes());
+        (id.to_string(), hash, text.to_string())
+    }
+
+    #[test]
+    fn similarity_is_by_direction_and_zero_for_zero_vectors() {
+        assert!((cosine_similarity(&[1.0, 2.0], &[2.0, 4.0]) - 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]), 0.0);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 1.0]), 0.0);
+        assert_eq!(cosine_similarity(&[], &[]), 0.0);
+    }
+
+    #[test]
+    fn the_fingerprint_changes_with_any_prompt() {
+        let index = PromptIndex {
+            prompts: vec![metadata("a", json!({ "updated": "2026-01-01T00:00:00Z" }))],
+            ..PromptIndex::default()
+        };
+        let print = fingerprint(&index);
+        assert_eq!(print, fingerprint(&index.clone()));
+
+        let mut edited = index.clone();
+        edited.prompts[0].updated = "2026-01-02T00:00:00Z".to_string();
+        let mut renamed = index.clone();
+        renamed.prompts[0].name = "Renamed".to_string();
+        let mut added = index.clone();
+        added.prompts.push(metadata("b", json!({})));
+        for changed in [edited, renamed, added, PromptIndex::default()] {
+            assert_ne!(fingerprint(&changed), print);
+        }
+    }
+
+    #[test]
+    fn stores_of_another_model_start_over() {
+        let paths = temp_paths("embedding-store");
+        save_store(&paths, &store("old-model", &["hash"])).unwrap();
+
+        assert_eq!(load_store(&paths, "old-model").vectors.len(), 1);
+        let fresh = load_store(&paths, "new-model");
+        assert_eq!(fresh.model, "new-model");
+        assert!(fresh.vectors.is_empty());
+
+        fs::write(paths.root.join(STORE_FILE), "{not json").unwrap();
+        assert!(load_store(&paths, "old-model").vectors.is_empty());
+    }
+
+    #[test]
+    fn only_new_texts_are_embedded_and_unused_vectors_dropped() {
+        let kept = prompt("a", "Kept");
+        let shared = [prompt("b", "Same text"), prompt("c", "Same text")];
+        let mut store = store("model", &[&kept.1, "unused"]);
+        let prompts = [kept.clone(), shared[0].clone(), shared[1].clone()];
+
+        let mut embedded = Vec::new();
+        let embed = |texts: Vec<String>| {
+            embedded.extend(texts.iter().cloned());
+            Ok(texts.iter().map(|_| vec![0.5]).collect())
+        };
+        assert!(update_store(&mut store, &prompts, embed).unwrap());
+        assert_eq!(embedded, ["Same text"]);
+        let mut hashes: Vec<&String> = store.vectors.keys().collect();
+        hashes.sort();
+        let mut expected = vec![&kept.1, &shared[0].1];
+        expected.sort();
+        assert_eq!(hashes, expected);
+        assert_eq!(store.vectors[&kept.1], [1.0]);
+
+        // Nothing new and nothing unused
+        let nothing = |_: Vec<String>| -> AppResult<Vec<Vec<f32>>> { panic!("nothing to embed") };
+        assert!(!update_store(&mut store, &prompts, nothing).unwrap());
+    }
+}
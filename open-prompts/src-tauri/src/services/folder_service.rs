@@ -0,0 +1,479 @@
+//! Folder paths such as `coding/rust`, the tree they form, and folder metadata.
+//! Paths always use `/` and the root folder is the empty string. Each folder's
+//! metadata is kept in the index and in a sidecar file inside the folder.
+
+use crate::error::{map_err, AppResult};
+use crate::models::prompt::{FolderMeta, FolderNode, PromptIndex};
+use crate::services::storage::{atomic_write, StoragePaths};
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+/// Sidecar file holding a folder's metadata, inside the folder.
+pub const FOLDER_META_FILE: &str = ".folder.json";
+
+/// Clean up a folder path typed by the user: `\` becomes `/`, and empty
+/// segments and surrounding whitespace are dropped. `.` and `..` are refused.
+pub fn normalize_path(path: &str) -> AppResult<String> {
+    let path = path.replace('\\', "/");
+    let mut segments = Vec::new();
+    for segment in path.split('/').map(str::trim).filter(|s| !s.is_empty()) {
+        if segment == "." || segment == ".." {
+            return Err(format!("Invalid folder name '{}'", segment));
+        }
+        segments.push(segment);
+    }
+    Ok(segments.join("/"))
+}
+
+/// `coding/rust` -> `coding`; top-level folders have the root as parent.
+pub fn parent(path: &str) -> &str {
+    path.rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or("")
+}
+
+/// `coding/rust` -> `rust`.
+pub fn leaf(path: &str) -> &str {
+    path.rsplit_once('/').map(|(_, leaf)| leaf).unwrap_or(path)
+}
+
+pub fn join(parent: &str, name: &str) -> String {
+    if parent.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent, name)
+    }
+}
+
+/// Whether `path` is `ancestor` itself or somewhere below it.
+pub fn is_within(path: &str, ancestor: &str) -> bool {
+    ancestor.is_empty()
+        || path == ancestor
+        || (path.starts_with(ancestor) && path[ancestor.len()..].starts_with('/'))
+}
+
+/// `path` moved from below `old` to below `new`, or `None` if it isn't within `old`.
+pub fn reparent(path: &str, old: &str, new: &str) -> Option<String> {
+    if !is_within(path, old) {
+        return None;
+    }
+    let rest = path[old.len()..].trim_start_matches('/');
+    Some(if rest.is_empty() {
+        new.to_string()
+    } else {
+        join(new, rest)
+    })
+}
+
+/// Every folder the index knows of, including the parents of nested ones.
+pub fn all_folders(index: &PromptIndex) -> BTreeSet<String> {
+    let mut folders = BTreeSet::new();
+    let named = index
+        .folders
+        .iter()
+        .chain(index.prompts.iter().map(|p| &p.folder));
+    for folder in named {
+        let mut path = folder.as_str();
+        while !path.is_empty() && folders.insert(path.to_string()) {
+            path = parent(path);
+        }
+    }
+    folders
+}
+
+/// The whole folder hierarchy under a root node, with prompt counts and
+/// metadata. Children are ordered by `sort_index`, then by name.
+pub fn build_tree(index: &PromptIndex) -> FolderNode {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for prompt in &index.prompts {
+        *counts.entry(prompt.folder.as_str()).or_default() += 1;
+    }
+
+    let mut children: HashMap<String, Vec<String>> = HashMap::new();
+    for folder in all_folders(index) {
+        children
+            .entry(parent(&folder).to_string())
+            .or_default()
+            .push(folder);
+    }
+
+    build_node("", index, &children, &counts)
+}
+
+fn build_node(
+    path: &str,
+    index: &PromptIndex,
+    children: &HashMap<String, Vec<String>>,
+    counts: &HashMap<&str, usize>,
+) -> FolderNode {
+    let mut nodes: Vec<FolderNode> = children
+        .get(path)
+        .map(|paths| {
+            paths
+                .iter()
+                .map(|child| build_node(child, index, children, counts))
+                .collect()
+        })
+        .unwrap_or_default();
+    nodes.sort_by_key(|node| {
+        let sort_index = node.meta.as_ref().and_then(|m| m.sort_index);
+        (sort_index.unwrap_or(u32::MAX), node.name.to_lowercase())
+    });
+
+    let prompt_count = counts.get(path).copied().unwrap_or(0);
+    FolderNode {
+        name: leaf(path).to_string(),
+        path: path.to_string(),
+        prompt_count,
+        total_prompt_count: prompt_count
+            + nodes.iter().map(|n| n.total_prompt_count).sum::<usize>(),
+        meta: index
+            .folder_meta
+            .as_ref()
+            .and_then(|meta| meta.get(path))
+            .cloned(),
+        children: nodes,
+    }
+}
+
+/// Set a folder's icon, color and description, keeping its place in the order.
+pub fn update_meta(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    path: &str,
+    icon: Option<String>,
+    color: Option<String>,
+    description: String,
+) -> AppResult<FolderMeta> {
+    let path = existing_folder(index, path)?;
+    let meta = index
+        .folder_meta
+        .get_or_insert_with(HashMap::new)
+        .entry(path.clone())
+        .or_default();
+    meta.name = path.clone();
+    meta.icon = icon.filter(|i| !i.trim().is_empty());
+    meta.color = color.filter(|c| !c.trim().is_empty());
+    meta.description = description.trim().to_string();
+
+    let meta = meta.clone();
+    write_sidecar(paths, &path, &meta)?;
+    Ok(meta)
+}
+
+/// Put the subfolders of `parent` in the order of `order`. Subfolders not
+/// listed keep their current relative order after the listed ones.
+pub fn reorder(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    parent_path: &str,
+    order: &[String],
+) -> AppResult<()> {
+    let parent_path = normalize_path(parent_path)?;
+    let tree = build_tree(index);
+    let siblings: Vec<String> = find_node(&tree, &parent_path)
+        .ok_or_else(|| format!("Folder '{}' not found", parent_path))?
+        .children
+        .iter()
+        .map(|child| child.path.clone())
+        .collect();
+
+    let mut ordered: Vec<String> = Vec::with_capacity(siblings.len());
+    for path in order {
+        let path = normalize_path(path)?;
+        if !siblings.contains(&path) {
+            return Err(format!(
+                "'{}' is not a subfolder of '{}'",
+                path, parent_path
+            ));
+        }
+        if ordered.contains(&path) {
+            return Err(format!("'{}' is listed twice", path));
+        }
+        ordered.push(path);
+    }
+    for path in siblings {
+        if !ordered.contains(&path) {
+            ordered.push(path);
+        }
+    }
+
+    let all_meta = index.folder_meta.get_or_insert_with(HashMap::new);
+    for (position, path) in ordered.into_iter().enumerate() {
+        let meta = all_meta.entry(path.clone()).or_default();
+        meta.name = path.clone();
+        meta.sort_index = Some(position as u32);
+        write_sidecar(paths, &path, meta)?;
+    }
+    Ok(())
+}
+
+/// The node for `path` in the subtree under `node`.
+fn find_node<'a>(node: &'a FolderNode, path: &str) -> Option<&'a FolderNode> {
+    if node.path == path {
+        return Some(node);
+    }
+    node.children
+        .iter()
+        .filter(|child| is_within(path, &child.path))
+        .find_map(|child| find_node(child, path))
+}
+
+fn existing_folder(index: &PromptIndex, path: &str) -> AppResult<String> {
+    let path = normalize_path(path)?;
+    if path.is_empty() {
+        return Err("The root folder has no metadata".to_string());
+    }
+    if !all_folders(index).contains(&path) {
+        return Err(format!("Folder '{}' not found", path));
+    }
+    Ok(path)
+}
+
+/// Write a folder's metadata next to its prompts, so it survives the index
+/// being rebuilt and travels with the folder when it is moved.
+pub fn write_sidecar(paths: &StoragePaths, path: &str, meta: &FolderMeta) -> AppResult<()> {
+    let dir = paths.prompts_dir.join(path);
+    fs::create_dir_all(&dir).map_err(map_err)?;
+    let sidecar = FolderMeta {
+        name: String::new(),
+        ..meta.clone()
+    };
+    let data = serde_json::to_string_pretty(&sidecar).map_err(map_err)?;
+    atomic_write(&dir.join(FOLDER_META_FILE), data.as_bytes())
+}
+
+/// Metadata from every folder's sidecar file, keyed by folder path.
+/// Unreadable sidecars are skipped.
+pub fn read_sidecars(paths: &StoragePaths) -> AppResult<HashMap<String, FolderMeta>> {
+    let mut found = HashMap::new();
+    if paths.prompts_dir.is_dir() {
+        read_sidecars_in(&paths.prompts_dir, "", &mut found)?;
+    }
+    Ok(found)
+}
+
+fn read_sidecars_in(
+    dir: &Path,
+    path: &str,
+    found: &mut HashMap<String, FolderMeta>,
+) -> AppResult<()> {
+    for entry in fs::read_dir(dir).map_err(map_err)? {
+        let entry = entry.map_err(map_err)?;
+        if !entry.file_type().map_err(map_err)?.is_dir() {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().to_string();
+        let child = join(path, &name);
+
+        if let Some(meta) = read_sidecar_in(&entry.path(), &child) {
+            found.insert(child.clone(), meta);
+        }
+        read_sidecars_in(&entry.path(), &child, found)?;
+    }
+    Ok(())
+}
+
+/// Metadata from the sidecar file of the folder at `path`, if it has a readable one.
+pub fn read_sidecar(paths: &StoragePaths, path: &str) -> Option<FolderMeta> {
+    read_sidecar_in(&paths.prompts_dir.join(path), path)
+}
+
+fn read_sidecar_in(dir: &Path, path: &str) -> Option<FolderMeta> {
+    let sidecar = dir.join(FOLDER_META_FILE);
+    let data = fs::read_to_string(&sidecar).ok()?;
+    match serde_json::from_str::<FolderMeta>(&data) {
+        Ok(mut meta) => {
+            meta.name = path.to_string();
+            Some(meta)
+        }
+        Err(e) => {
+            eprintln!("Warning: ignoring {:?}: {}", sidecar, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::{index_service, prompt_service};
+    use crate::test_support::{add_prompt, metadata, temp_paths};
+
+    /// Paths of the subfolders of `path`, in tree order.
+    fn children(index: &PromptIndex, path: &str) -> Vec<String> {
+        let tree = build_tree(index);
+        find_node(&tree, path)
+            .unwrap()
+            .children
+            .iter()
+            .map(|child| child.path.clone())
+            .collect()
+    }
+
+    #[test]
+    fn paths_are_normalized() {
+        assert_eq!(normalize_path(" coding\\rust/ ").unwrap(), "coding/rust");
+        assert_eq!(normalize_path("a//b/ /c").unwrap(), "a/b/c");
+        assert_eq!(normalize_path("/").unwrap(), "");
+        assert!(normalize_path("a/../b").is_err());
+        assert!(normalize_path("./a").is_err());
+        assert_eq!(parent("a/b/c"), "a/b");
+        assert_eq!(parent("a"), "");
+        assert_eq!(leaf("a/b/c"), "c");
+        assert_eq!(join("", "a"), "a");
+        assert_eq!(join("a", "b"), "a/b");
+    }
+
+    #[test]
+    fn within_means_the_folder_or_below_it() {
+        assert!(is_within("a", "a"));
+        assert!(is_within("a/b", "a"));
+        assert!(is_within("a", ""));
+        assert!(!is_within("ab", "a"));
+        assert!(!is_within("a", "a/b"));
+        assert!(!is_within("b/a", "a"));
+
+        assert_eq!(reparent("a/b/c", "a/b", "x").as_deref(), Some("x/c"));
+        assert_eq!(reparent("a/b", "a/b", "x/y").as_deref(), Some("x/y"));
+        assert_eq!(reparent("ab/c", "a", "x"), None);
+        assert_eq!(reparent("a", "", "x").as_deref(), Some("x/a"));
+    }
+
+    #[test]
+    fn the_tree_counts_prompts_in_each_folder_and_below() {
+        let mut index = PromptIndex::default();
+        for (id, folder) in [
+            ("1", ""),
+            ("2", "a"),
+            ("3", "a/b"),
+            ("4", "a/b/c"),
+            ("5", "d"),
+        ] {
+            index
+                .prompts
+                .push(metadata(id, serde_json::json!({ "folder": folder })));
+        }
+        index.folders.push("e/f".to_string());
+
+        let tree = build_tree(&index);
+        assert_eq!(tree.path, "");
+        assert_eq!((tree.prompt_count, tree.total_prompt_count), (1, 5));
+        let names: Vec<&str> = tree.children.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["a", "d", "e"]);
+        let a = find_node(&tree, "a").unwrap();
+        assert_eq!((a.prompt_count, a.total_prompt_count), (1, 3));
+        let b = find_node(&tree, "a/b").unwrap();
+        assert_eq!((b.name.as_str(), b.total_prompt_count), ("b", 2));
+        // Empty folders are part of the tree, with their parents
+        let f = find_node(&tree, "e/f").unwrap();
+        assert_eq!((f.prompt_count, f.total_prompt_count), (0, 0));
+    }
+
+    #[test]
+    fn metadata_is_set_in_the_index_and_the_sidecar() {
+        let paths = temp_paths("folder-meta");
+        add_prompt(&paths, "coding/rust", "Borrow", "text");
+        let mut index = index_service::load_index(&paths).unwrap();
+
+        let meta = update_meta(
+            &paths,
+            &mut index,
+            "coding\\rust",
+            Some("🦀".to_string()),
+            Some(" ".to_string()),
+            "  Ownership help ".to_string(),
+        )
+        .unwrap();
+        assert_eq!(meta.name, "coding/rust");
+        assert_eq!(meta.icon.as_deref(), Some("🦀"));
+        assert_eq!(meta.color, None);
+        assert_eq!(meta.description, "Ownership help");
+        let stored = &index.folder_meta.as_ref().unwrap()["coding/rust"];
+        assert_eq!(stored.icon, meta.icon);
+
+        let sidecar = paths.prompts_dir.join("coding/rust").join(FOLDER_META_FILE);
+        let written = fs::read_to_string(sidecar).unwrap();
+        assert!(!written.contains("\"name\""), "{}", written);
+        let sidecars = read_sidecars(&paths).unwrap();
+        assert_eq!(sidecars.len(), 1);
+        assert_eq!(sidecars["coding/rust"].name, "coding/rust");
+        assert_eq!(sidecars["coding/rust"].description, "Ownership help");
+
+        let missing = update_meta(&paths, &mut index, "nowhere", None, None, String::new());
+        assert_eq!(missing.unwrap_err(), "Folder 'nowhere' not found");
+        assert!(update_meta(&paths, &mut index, "", None, None, String::new()).is_err());
+    }
+
+    #[test]
+    fn unreadable_sidecars_are_skipped() {
+        let paths = temp_paths("folder-sidecars");
+        let good = FolderMeta {
+            icon: Some("★".to_string()),
+            ..FolderMeta::default()
+        };
+        write_sidecar(&paths, "a/b", &good).unwrap();
+        fs::create_dir_all(paths.prompts_dir.join("c")).unwrap();
+        fs::write(paths.prompts_dir.join("c").join(FOLDER_META_FILE), "{ oops").unwrap();
+
+        let sidecars = read_sidecars(&paths).unwrap();
+        assert_eq!(sidecars.keys().collect::<Vec<_>>(), ["a/b"]);
+        assert_eq!(sidecars["a/b"].icon.as_deref(), Some("★"));
+        assert!(read_sidecar(&paths, "c").is_none());
+        assert!(read_sidecar(&paths, "a").is_none());
+    }
+
+    #[test]
+    fn reordering_puts_listed_folders_first_and_keeps_the_rest_in_order() {
+        let paths = temp_paths("folder-reorder");
+        for folder in ["a/x", "a/y", "a/z", "b"] {
+            add_prompt(&paths, folder, "Prompt", "text");
+        }
+        let mut index = index_service::load_index(&paths).unwrap();
+        assert_eq!(children(&index, "a"), ["a/x", "a/y", "a/z"]);
+
+        reorder(&paths, &mut index, "a", &["a/z".to_string()]).unwrap();
+        assert_eq!(children(&index, "a"), ["a/z", "a/x", "a/y"]);
+        reorder(&paths, &mut index, "a", &["a/y".to_string()]).unwrap();
+        assert_eq!(children(&index, "a"), ["a/y", "a/z", "a/x"]);
+        // The order is in the sidecars too
+        let sidecars = read_sidecars(&paths).unwrap();
+        assert_eq!(sidecars["a/x"].sort_index, Some(2));
+
+        let twice = ["a/x".to_string(), "a/x".to_string()];
+        assert_eq!(
+            reorder(&paths, &mut index, "a", &twice).unwrap_err(),
+            "'a/x' is listed twice"
+        );
+        assert_eq!(
+            reorder(&paths, &mut index, "a", &["b".to_string()]).unwrap_err(),
+            "'b' is not a subfolder of 'a'"
+        );
+        assert!(reorder(&paths, &mut index, "nowhere", &[]).is_err());
+        assert_eq!(children(&index, "a"), ["a/y", "a/z", "a/x"]);
+
+        // Top-level folders are reordered under the root
+        reorder(&paths, &mut index, "", &["b".to_string()]).unwrap();
+        assert_eq!(children(&index, ""), ["b", "a"]);
+    }
+
+    #[test]
+    fn sidecars_move_with_their_folder() {
+        let paths = temp_paths("folder-meta-move");
+        add_prompt(&paths, "a/b", "Prompt", "text");
+        add_prompt(&paths, "x", "Other", "text");
+        let mut index = index_service::load_index(&paths).unwrap();
+        let icon = Some("📁".to_string());
+        update_meta(&paths, &mut index, "a/b", icon.clone(), None, String::new()).unwrap();
+
+        prompt_service::move_folder(&paths, &mut index, "a", "x").unwrap();
+        let meta = index.folder_meta.as_ref().unwrap();
+        assert_eq!(meta["x/a/b"].icon, icon);
+        assert_eq!(meta["x/a/b"].name, "x/a/b");
+        assert!(!meta.contains_key("a/b"));
+        let sidecars = read_sidecars(&paths).unwrap();
+        assert_eq!(sidecars["x/a/b"].icon, icon);
+        assert!(!sidecars.contains_key("a/b"));
+    }
+}
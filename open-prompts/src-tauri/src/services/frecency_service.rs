@@ -0,0 +1,219 @@
+use crate::error::AppResult;
+use crate::models::prompt::PromptIndex;
+use crate::models::settings::SearchSettings;
+use crate::models::usage::{DeliveryMode, UsageEvent, UsageLogEntry, UsageStore};
+use crate::services::storage::{lock_library, StoragePaths};
+use crate::services::usage_log_service::{self, parse_timestamp};
+use chrono::{DateTime, Utc};
+
+/// The latest `max_events` uses of each prompt, read from the usage log.
+/// Takes the library lock while seeding the log, so don't call this from
+/// inside `index_service::update_index`.
+pub fn load_usage(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    max_events: usize,
+) -> AppResult<UsageStore> {
+    let lock = lock_library(paths)?;
+    seed_usage_log(paths, index)?;
+    drop(lock);
+    let entries = usage_log_service::read_log(paths)?;
+    Ok(usage_from_log(&entries, max_events))
+}
+
+/// Group log entries by prompt, oldest first, keeping the latest `max_events` of each.
+fn usage_from_log(entries: &[UsageLogEntry], max_events: usize) -> UsageStore {
+    let mut store = UsageStore::default();
+    for entry in entries {
+        store
+            .events
+            .entry(entry.prompt_id.clone())
+            .or_default()
+            .push(UsageEvent {
+                timestamp: entry.timestamp.clone(),
+                app: entry
+                    .target_app
+                    .as_deref()
+                    .filter(|app| !app.is_empty())
+                    .map(str::to_lowercase),
+            });
+    }
+
+    let max_events = max_events.max(1);
+    for events in store.events.values_mut() {
+        events.sort_by_key(|event| parse_timestamp(&event.timestamp));
+        if events.len() > max_events {
+            let excess = events.len() - max_events;
+            events.drain(..excess);
+        }
+    }
+    store
+}
+
+/// Start the usage log with one use of each prompt at its `last_used`, so a
+/// library from before the log keeps its order. Nothing happens once the log
+/// exists. Must run before a use updates `last_used`, or it counts twice.
+pub fn seed_usage_log(paths: &StoragePaths, index: &PromptIndex) -> AppResult<()> {
+    if paths.usage_log_path.exists() {
+        return Ok(());
+    }
+
+    let mut entries: Vec<UsageLogEntry> = index
+        .prompts
+        .iter()
+        .filter_map(|prompt| {
+            Some(UsageLogEntry {
+                prompt_id: prompt.id.clone(),
+                timestamp: prompt.last_used.clone()?,
+                target_app: None,
+                delivery_mode: DeliveryMode::Paste,
+                variables_filled: false,
+            })
+        })
+        .collect();
+    entries.sort_by_key(|entry| parse_timestamp(&entry.timestamp));
+    usage_log_service::write_log(paths, &entries)
+}
+
+/// Sum of each use decayed by its age: a use `half_life_days` old counts 0.5.
+/// With `app` set, only uses inside that application count.
+pub fn frecency(
+    store: &UsageStore,
+    id: &str,
+    app: Option<&str>,
+    settings: &SearchSettings,
+    now: DateTime<Utc>,
+) -> f64 {
+    let events = match store.events.get(id) {
+        Some(events) => events,
+        None => return 0.0,
+    };
+
+    let app = app.map(|a| a.to_lowercase());
+    events
+        .iter()
+        .filter(|event| match &app {
+            Some(app) => event.app.as_deref() == Some(app.as_str()),
+            None => true,
+        })
+        .filter_map(|event| parse_timestamp(&event.timestamp))
+        .map(|timestamp| decay(timestamp, now, settings.frecency_half_life_days))
+        .sum()
+}
+
+fn decay(timestamp: DateTime<Utc>, now: DateTime<Utc>, half_life_days: f64) -> f64 {
+    if half_life_days <= 0.0 {
+        return 1.0;
+    }
+    let age_days = (now - timestamp).num_seconds().max(0) as f64 / 86_400.0;
+    0.5_f64.powf(age_days / half_life_days)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{metadata, temp_paths};
+    use chrono::{Duration, TimeZone};
+    use serde_json::json;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    fn settings() -> SearchSettings {
+        SearchSettings {
+            frecency_half_life_days: 10.0,
+            ..SearchSettings::default()
+        }
+    }
+
+    fn entry(id: &str, app: Option<&str>, at: DateTime<Utc>) -> UsageLogEntry {
+        UsageLogEntry {
+            prompt_id: id.to_string(),
+            timestamp: at.to_rfc3339(),
+            target_app: app.map(str::to_string),
+            delivery_mode: DeliveryMode::Paste,
+            variables_filled: false,
+        }
+    }
+
+    #[test]
+    fn a_use_one_half_life_old_counts_half() {
+        let old = entry("a", None, now() - Duration::days(10));
+        let store = usage_from_log(std::slice::from_ref(&old), 50);
+        let score = frecency(&store, "a", None, &settings(), now());
+        assert!((score - 0.5).abs() < 1e-9);
+
+        let store = usage_from_log(&[old, entry("a", None, now())], 50);
+        let score = frecency(&store, "a", None, &settings(), now());
+        assert!((score - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn uses_in_the_future_count_fully() {
+        let store = usage_from_log(&[entry("a", None, now() + Duration::days(3))], 50);
+        assert_eq!(frecency(&store, "a", None, &settings(), now()), 1.0);
+    }
+
+    #[test]
+    fn app_frecency_only_counts_that_app() {
+        let store = usage_from_log(
+            &[
+                entry("a", Some("Code.exe"), now()),
+                entry("a", Some("slack.exe"), now()),
+                entry("a", None, now()),
+            ],
+            50,
+        );
+        let s = settings();
+
+        assert_eq!(frecency(&store, "a", Some("code.exe"), &s, now()), 1.0);
+        assert_eq!(frecency(&store, "a", Some("CODE.EXE"), &s, now()), 1.0);
+        assert_eq!(frecency(&store, "a", Some("word.exe"), &s, now()), 0.0);
+        assert_eq!(frecency(&store, "a", None, &s, now()), 3.0);
+        assert_eq!(frecency(&store, "b", None, &s, now()), 0.0);
+    }
+
+    #[test]
+    fn only_the_latest_events_count() {
+        // Out of order, as in a log merged from several places
+        let entries: Vec<UsageLogEntry> = [3, 0, 4, 1, 2]
+            .iter()
+            .map(|day| entry("a", None, now() + Duration::days(*day)))
+            .collect();
+        let store = usage_from_log(&entries, 3);
+        let events = &store.events["a"];
+        assert_eq!(events.len(), 3);
+        assert_eq!(
+            events[0].timestamp,
+            (now() + Duration::days(2)).to_rfc3339()
+        );
+        assert_eq!(
+            events[2].timestamp,
+            (now() + Duration::days(4)).to_rfc3339()
+        );
+    }
+
+    #[test]
+    fn the_index_seeds_the_log_once() {
+        let paths = temp_paths("bootstrap");
+        let used = now() - Duration::days(1);
+        let mut index = PromptIndex::default();
+        index
+            .prompts
+            .push(metadata("a", json!({ "lastUsed": used.to_rfc3339() })));
+        index.prompts.push(metadata("b", json!({})));
+
+        let store = load_usage(&paths, &index, 50).unwrap();
+        assert_eq!(store.events["a"].len(), 1);
+        assert!(!store.events.contains_key("b"));
+        assert!(paths.usage_log_path.exists());
+
+        // A later use lands only in the log; loading again doesn't reseed
+        let mut entries = usage_log_service::read_log(&paths).unwrap();
+        entries.push(entry("a", None, now()));
+        usage_log_service::write_log(&paths, &entries).unwrap();
+        let store = load_usage(&paths, &index, 50).unwrap();
+        assert_eq!(store.events["a"].len(), 2);
+    }
+}
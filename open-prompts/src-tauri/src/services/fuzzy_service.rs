@@ -0,0 +1,401 @@
+//! Fuzzy matching of a query against short text such as prompt names, in the
+//! style of fzf: every alignment of the query is considered and the best one
+//! wins. Matching ignores case and diacritics, and tolerates a typo or two in
+//! longer queries.
+
+use std::ops::Range;
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+const SCORE_MATCH: i32 = 16;
+const GAP_START: i32 = 3;
+const GAP_EXTENSION: i32 = 1;
+/// A match at the start of the text or right after a space or separator
+const BONUS_BOUNDARY: i32 = 8;
+/// A match on an uppercase letter after a lowercase one, or a digit after a letter
+const BONUS_CAMEL: i32 = 7;
+/// Smallest bonus for a match right after the previous one
+const BONUS_CONSECUTIVE: i32 = GAP_START + GAP_EXTENSION;
+/// The first query character counts its bonus this many times
+const FIRST_CHAR_MULTIPLIER: i32 = 2;
+/// Raw scores are divided by this to stay on the scale the ranking weights expect
+const SCORE_SCALE: f64 = 8.0;
+/// Only this many characters at the start of a longer text are matched
+const MAX_TEXT_CHARS: usize = 1024;
+/// Marks a cell no alignment reaches. Far enough from `i32::MIN` that gap
+/// penalties can be subtracted without overflowing.
+const UNREACHABLE: i32 = i32::MIN / 2;
+
+/// Where and how well a query matched.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch {
+    /// Higher is better; always positive
+    pub score: f64,
+    /// Character (not byte) indices of the matched text, ascending
+    pub positions: Vec<usize>,
+    /// Edits the match needed; 0 when every query character was found in order
+    pub typos: usize,
+}
+
+/// One folded character of the text, with the character it came from.
+struct Folded {
+    ch: char,
+    source: usize,
+    bonus: i32,
+}
+
+/// Lowercase `text` and strip its diacritics, so `Résumé` becomes `resume`
+/// and `Straße` becomes `strasse`.
+pub fn fold(text: &str) -> String {
+    text.chars().flat_map(fold_char).collect()
+}
+
+fn fold_char(c: char) -> impl Iterator<Item = char> {
+    // `ß` has no decomposition, but is written `ss` where it isn't available
+    let sharp_s = matches!(c, 'ß' | 'ẞ');
+    c.to_lowercase()
+        .nfd()
+        .filter(move |c| !sharp_s && !is_combining_mark(*c))
+        .chain(std::iter::repeat_n('s', if sharp_s { 2 } else { 0 }))
+}
+
+/// Match `query` against `text`. Returns `None` if the query's characters
+/// don't appear in order and it isn't within a few typos of any part of the text.
+pub fn fuzzy_match(text: &str, query: &str) -> Option<FuzzyMatch> {
+    let query: Vec<char> = query.chars().flat_map(fold_char).collect();
+    if query.is_empty() {
+        return Some(FuzzyMatch {
+            score: 0.0,
+            positions: Vec::new(),
+            typos: 0,
+        });
+    }
+    let text = fold_with_bonuses(text);
+
+    if let Some((score, positions)) = align(&text, &query) {
+        return Some(FuzzyMatch {
+            score: score as f64 / SCORE_SCALE,
+            positions,
+            typos: 0,
+        });
+    }
+
+    let (typos, positions) = align_with_typos(&text, &query, allowed_typos(query.len()))?;
+    // Each typo costs the matched character and one more, so a typo match
+    // stays below an exact match of the same query
+    let score = SCORE_MATCH * (query.len() - 2 * typos) as i32;
+    Some(FuzzyMatch {
+        score: score as f64 / SCORE_SCALE,
+        positions,
+        typos,
+    })
+}
+
+/// Every place `needle` appears in `text`, ignoring case and diacritics, as
+/// character ranges of `text`. Occurrences don't overlap.
+pub fn find_all(text: &str, needle: &str) -> Vec<Range<usize>> {
+    let needle: Vec<char> = needle.chars().flat_map(fold_char).collect();
+    if needle.is_empty() {
+        return Vec::new();
+    }
+    let folded: Vec<(char, usize)> = text
+        .chars()
+        .enumerate()
+        .flat_map(|(source, c)| fold_char(c).map(move |ch| (ch, source)))
+        .collect();
+
+    let mut found = Vec::new();
+    let mut i = 0;
+    while i + needle.len() <= folded.len() {
+        let window = &folded[i..i + needle.len()];
+        let range = window[0].1..window[window.len() - 1].1 + 1;
+        // A character folding to several can match again, from its second one
+        let overlaps = found
+            .last()
+            .is_some_and(|last: &Range<usize>| last.end > range.start);
+        if !overlaps && window.iter().map(|(ch, _)| *ch).eq(needle.iter().copied()) {
+            found.push(range);
+            i += needle.len();
+        } else {
+            i += 1;
+        }
+    }
+    found
+}
+
+/// One typo from four characters on, two from eight.
+fn allowed_typos(query_len: usize) -> usize {
+    match query_len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+/// The folded text, each character with the bonus for matching there. A
+/// character that folds to several gets the bonus on the first only.
+fn fold_with_bonuses(text: &str) -> Vec<Folded> {
+    let mut folded = Vec::new();
+    let mut prev: Option<char> = None;
+    for (source, c) in text.chars().take(MAX_TEXT_CHARS).enumerate() {
+        let bonus = match prev {
+            None => BONUS_BOUNDARY,
+            Some(p) if !p.is_alphanumeric() && c.is_alphanumeric() => BONUS_BOUNDARY,
+            Some(p) if p.is_lowercase() && c.is_uppercase() => BONUS_CAMEL,
+            Some(p) if p.is_alphabetic() && c.is_numeric() => BONUS_CAMEL,
+            Some(_) => 0,
+        };
+        for (i, ch) in fold_char(c).enumerate() {
+            folded.push(Folded {
+                ch,
+                source,
+                bonus: if i == 0 { bonus } else { 0 },
+            });
+        }
+        prev = Some(c);
+    }
+    folded
+}
+
+/// The best-scoring way to match every query character, in order, to a text
+/// character. `score[i][j]` is the best score of the first `i + 1` query
+/// characters with the last one matched at `j`; `from` remembers where the
+/// previous query character was matched, to recover the positions.
+fn align(text: &[Folded], query: &[char]) -> Option<(i32, Vec<usize>)> {
+    let (n, m) = (text.len(), query.len());
+    if m > n {
+        return None;
+    }
+    let mut score = vec![UNREACHABLE; m * n];
+    let mut from = vec![usize::MAX; m * n];
+
+    for (j, t) in text.iter().enumerate() {
+        if t.ch == query[0] {
+            score[j] = SCORE_MATCH + t.bonus * FIRST_CHAR_MULTIPLIER;
+        }
+    }
+
+    for i in 1..m {
+        let (prev_row, row) = (&score[(i - 1) * n..i * n], i * n);
+        // Best earlier match of the previous character at least one gap away,
+        // with the gap penalty up to `j` already taken off
+        let mut gapped = UNREACHABLE;
+        let mut gapped_from = usize::MAX;
+        let mut cells = Vec::with_capacity(n);
+
+        for (j, t) in text.iter().enumerate() {
+            if j >= 2 {
+                gapped -= GAP_EXTENSION;
+                let candidate = prev_row[j - 2] - GAP_START;
+                if candidate >= gapped {
+                    gapped = candidate;
+                    gapped_from = j - 2;
+                }
+            }
+
+            let mut best = (UNREACHABLE, usize::MAX);
+            if t.ch == query[i] {
+                if j >= 1 && prev_row[j - 1] > UNREACHABLE {
+                    let bonus = t.bonus.max(BONUS_CONSECUTIVE);
+                    best = (prev_row[j - 1] + SCORE_MATCH + bonus, j - 1);
+                }
+                if gapped > UNREACHABLE / 2 && gapped + SCORE_MATCH + t.bonus > best.0 {
+                    best = (gapped + SCORE_MATCH + t.bonus, gapped_from);
+                }
+            }
+            cells.push(best);
+        }
+
+        for (j, (value, previous)) in cells.into_iter().enumerate() {
+            score[row + j] = value;
+            from[row + j] = previous;
+        }
+    }
+
+    let last = (m - 1) * n;
+    let (end, best) = (0..n)
+        .map(|j| (j, score[last + j]))
+        .filter(|(_, s)| *s > UNREACHABLE / 2)
+        .max_by_key(|(j, s)| (*s, std::cmp::Reverse(*j)))?;
+
+    let mut positions = Vec::with_capacity(m);
+    let mut j = end;
+    for i in (0..m).rev() {
+        positions.push(text[j].source);
+        j = from[i * n + j];
+    }
+    positions.reverse();
+    positions.dedup();
+    Some((best, positions))
+}
+
+/// The fewest edits (Levenshtein distance) that turn `query` into some part of
+/// `text`, if there are at most `max_typos`, with the positions of the text
+/// characters that matched. Swapping two neighbouring characters counts as one edit.
+fn align_with_typos(
+    text: &[Folded],
+    query: &[char],
+    max_typos: usize,
+) -> Option<(usize, Vec<usize>)> {
+    if max_typos == 0 || text.is_empty() {
+        return None;
+    }
+    let (n, m) = (text.len(), query.len());
+    let width = n + 1;
+    // distance[i][j]: edits to match the first `i` query characters ending
+    // just before text position `j`. The match may start anywhere, so row 0 is free.
+    let mut distance = vec![0usize; (m + 1) * width];
+    for i in 1..=m {
+        distance[i * width] = i;
+        for j in 1..=n {
+            let substitution =
+                distance[(i - 1) * width + j - 1] + usize::from(query[i - 1] != text[j - 1].ch);
+            let skip_query = distance[(i - 1) * width + j] + 1;
+            let skip_text = distance[i * width + j - 1] + 1;
+            let mut best = substitution.min(skip_query).min(skip_text);
+            if is_swap(text, query, i, j) {
+                best = best.min(distance[(i - 2) * width + j - 2] + 1);
+            }
+            distance[i * width + j] = best;
+        }
+        // Later rows build on this one and the one before, so once both are
+        // over the limit everywhere no match is possible
+        let over = |row: usize| {
+            distance[row * width..(row + 1) * width]
+                .iter()
+                .all(|d| *d > max_typos)
+        };
+        if over(i) && over(i - 1) {
+            return None;
+        }
+    }
+
+    let (end, typos) = (1..=n)
+        .map(|j| (j, distance[m * width + j]))
+        .min_by_key(|(j, d)| (*d, *j))?;
+    if typos > max_typos {
+        return None;
+    }
+
+    let mut positions = Vec::new();
+    let (mut i, mut j) = (m, end);
+    while i > 0 {
+        let here = distance[i * width + j];
+        if j > 0 {
+            let same = query[i - 1] == text[j - 1].ch;
+            if here == distance[(i - 1) * width + j - 1] + usize::from(!same) {
+                if same {
+                    positions.push(text[j - 1].source);
+                }
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+            if is_swap(text, query, i, j) && here == distance[(i - 2) * width + j - 2] + 1 {
+                positions.push(text[j - 1].source);
+                positions.push(text[j - 2].source);
+                i -= 2;
+                j -= 2;
+                continue;
+            }
+            if here == distance[i * width + j - 1] + 1 {
+                j -= 1;
+                continue;
+            }
+        }
+        i -= 1;
+    }
+    positions.reverse();
+    positions.dedup();
+    Some((typos, positions))
+}
+
+/// Whether query characters `i - 2` and `i - 1` are text characters `j - 2`
+/// and `j - 1` the other way round.
+fn is_swap(text: &[Folded], query: &[char], i: usize, j: usize) -> bool {
+    i >= 2
+        && j >= 2
+        && query[i - 1] != query[i - 2]
+        && query[i - 1] == text[j - 2].ch
+        && query[i - 2] == text[j - 1].ch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(text: &str, query: &str) -> Vec<usize> {
+        fuzzy_match(text, query).unwrap().positions
+    }
+
+    #[test]
+    fn folding_ignores_case_and_accents() {
+        assert_eq!(fold("Résumé"), "resume");
+        assert_eq!(fold("ÀÉÎÕÜ ñ"), "aeiou n");
+        assert_eq!(fold("Straße STRAẞE"), "strasse strasse");
+    }
+
+    #[test]
+    fn letters_in_order_match_without_typos() {
+        let found = fuzzy_match("Summarize", "sumarize").unwrap();
+        assert_eq!(found.typos, 0);
+        assert_eq!(found.positions, [0, 1, 3, 4, 5, 6, 7, 8]);
+        assert!(found.score > 0.0);
+
+        assert_eq!(fuzzy_match("Anything", "").unwrap().score, 0.0);
+        assert!(fuzzy_match("abc", "xyz").is_none());
+    }
+
+    #[test]
+    fn swapped_and_wrong_letters_count_as_typos() {
+        let swapped = fuzzy_match("Summarize", "sumamrize").unwrap();
+        assert_eq!(swapped.typos, 1);
+        let exact = fuzzy_match("Summarize", "summarize").unwrap();
+        assert!(swapped.score < exact.score);
+
+        assert_eq!(fuzzy_match("Translate", "tarnslate").unwrap().typos, 1);
+        assert_eq!(fuzzy_match("Code review", "cofe").unwrap().typos, 1);
+        assert_eq!(
+            fuzzy_match("Documentation", "documantatoin").unwrap().typos,
+            2
+        );
+
+        // Short queries must match exactly, longer ones allow one typo, then two
+        assert!(fuzzy_match("Code", "cxd").is_none());
+        assert!(fuzzy_match("Code review", "cxdx").is_none());
+        assert!(fuzzy_match("Documentation", "dxcumxntatxon").is_none());
+    }
+
+    #[test]
+    fn positions_refer_to_the_original_characters() {
+        assert_eq!(positions("Résumé", "resume"), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(positions("Mon résumé", "RÉSUMÉ"), [4, 5, 6, 7, 8, 9]);
+        assert_eq!(find_all("Mon Résumé, résumé", "resume"), [4..10, 12..18]);
+        assert_eq!(find_all("aaaa", "aa"), [0..2, 2..4]);
+        assert!(find_all("text", "").is_empty());
+    }
+
+    #[test]
+    fn a_character_folding_to_several_matches_as_one() {
+        assert_eq!(positions("Straße", "strasse"), [0, 1, 2, 3, 4, 5]);
+        assert_eq!(positions("Strasse", "straße"), [0, 1, 2, 3, 4, 5, 6]);
+        assert_eq!(positions("Große Straße", "gs"), [0, 6]);
+        assert_eq!(find_all("Große Straße", "strasse"), vec![6..12]);
+        assert_eq!(find_all("Straße", "s"), [0..1, 4..5]);
+    }
+
+    #[test]
+    fn word_starts_beat_earlier_scattered_letters() {
+        assert_eq!(positions("xgxc GitCommit", "gc"), [5, 8]);
+        assert_eq!(positions("agenda for code review", "cr"), [11, 16]);
+        assert_eq!(positions("my_file_name", "fn"), [3, 8]);
+        assert_eq!(positions("version2", "n2"), [6, 7]);
+
+        let boundary = fuzzy_match("Git Commit", "gc").unwrap();
+        let scattered = fuzzy_match("logic", "gc").unwrap();
+        assert!(boundary.score > scattered.score);
+        let consecutive = fuzzy_match("Code review", "code").unwrap();
+        let spread = fuzzy_match("Cat owl dog eel", "code").unwrap();
+        assert!(consecutive.score > spread.score);
+    }
+}
@@ -1,34 +1,76 @@
 use crate::error::{map_err, AppResult};
-use crate::models::prompt::PromptIndex;
-use crate::services::storage::{atomic_write, StoragePaths};
+use crate::models::prompt::{
+    FolderMeta, PromptIndex, PromptMetadata, SavedSearch, CURRENT_INDEX_VERSION,
+};
+use crate::services::folder_service;
+use crate::services::storage::{atomic_write, backup_corrupt_file, lock_library, StoragePaths};
 use chrono::{DateTime, Utc};
-use std::collections::{HashMap, HashSet};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
 
+type Migration = fn(&mut Value) -> AppResult<()>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` index to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
+/// The index, synced with the prompt files on disk.
 pub fn load_index(paths: &StoragePaths) -> AppResult<PromptIndex> {
+    // Syncing may write the index back
+    let _lock = lock_library(paths)?;
+    load_index_locked(paths)
+}
+
+/// Load the index, let `change` modify it and save it, all under the library
+/// lock. Nothing is saved if `change` fails.
+pub fn update_index<T>(
+    paths: &StoragePaths,
+    change: impl FnOnce(&mut PromptIndex) -> AppResult<T>,
+) -> AppResult<T> {
+    let _lock = lock_library(paths)?;
+    let mut index = load_index_locked(paths)?;
+    let result = change(&mut index)?;
+    save_index(paths, &index)?;
+    Ok(result)
+}
+
+fn load_index_locked(paths: &StoragePaths) -> AppResult<PromptIndex> {
+    let mut migrated = false;
+    // Rebuilt from the prompt files rather than loaded whole
+    let mut rebuilt = false;
     let mut index = if !paths.index_path.exists() {
+        rebuilt = true;
         PromptIndex::default()
     } else {
         let data = fs::read_to_string(&paths.index_path).map_err(map_err)?;
-        match serde_json::from_str::<PromptIndex>(&data) {
-            Ok(index) => index,
+        match parse_index(&data) {
+            Ok((index, from_version)) => {
+                if from_version < CURRENT_INDEX_VERSION {
+                    backup_before_migration(paths, from_version);
+                    migrated = true;
+                }
+                index
+            }
             Err(e) => {
-                // Rename corrupt file, create fresh
-                let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
-                let corrupt_path = paths
-                    .index_path
-                    .with_extension(format!("corrupt.{}", timestamp));
-                let _ = fs::rename(&paths.index_path, &corrupt_path);
-                eprintln!("Corrupt index.json renamed to {:?}: {}", corrupt_path, e);
-                PromptIndex::default()
+                // Keep the original, then rebuild from whatever entries are still readable
+                let corrupt_path = backup_corrupt_file(&paths.index_path);
+                let index = salvage_index(&data);
+                rebuilt = true;
+                eprintln!(
+                    "Corrupt index.json renamed to {:?}: {}; salvaged {} prompt entries",
+                    corrupt_path,
+                    e,
+                    index.prompts.len()
+                );
+                index
             }
         }
     };
 
-    let changed = sync_index_with_filesystem(paths, &mut index)?;
-    if changed || !paths.index_path.exists() {
+    let changed = sync_index_with_filesystem(paths, &mut index, rebuilt)?;
+    if changed || migrated || !paths.index_path.exists() {
         save_index(paths, &index)?;
     }
 
@@ -40,12 +82,206 @@ pub fn save_index(paths: &StoragePaths, index: &PromptIndex) -> AppResult<()> {
     atomic_write(&paths.index_path, json.as_bytes())
 }
 
-fn sync_index_with_filesystem(paths: &StoragePaths, index: &mut PromptIndex) -> AppResult<bool> {
+/// Parse and migrate an index file. Returns the index and the version it was stored as.
+fn parse_index(data: &str) -> AppResult<(PromptIndex, u32)> {
+    let mut value = serde_json::from_str::<Value>(data).map_err(map_err)?;
+    if !value.is_object() {
+        return Err("index.json is not a JSON object".to_string());
+    }
+
+    let version = index_version(&value);
+    if version > CURRENT_INDEX_VERSION {
+        // Unknown fields survive in `extra`, so a newer index can still be saved back
+        eprintln!(
+            "index.json is version {}, newer than supported version {}",
+            version, CURRENT_INDEX_VERSION
+        );
+    } else {
+        migrate(&mut value, version)?;
+    }
+
+    let index = serde_json::from_value::<PromptIndex>(value).map_err(map_err)?;
+    Ok((index, version))
+}
+
+fn index_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// Run every migration from `from` up to CURRENT_INDEX_VERSION.
+fn migrate(value: &mut Value, from: u32) -> AppResult<()> {
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(value)
+            .map_err(|e| format!("Index migration v{} -> v{} failed: {}", step, step + 1, e))?;
+        value["version"] = Value::from(step as u32 + 1);
+    }
+    Ok(())
+}
+
+/// v0 is every index written before it was versioned. Its layout is
+/// unchanged, so the only change is stamping the version.
+fn migrate_v0_to_v1(value: &mut Value) -> AppResult<()> {
+    let root = value
+        .as_object_mut()
+        .ok_or("index.json is not a JSON object")?;
+    root.insert("version".to_string(), Value::from(1));
+    Ok(())
+}
+
+fn backup_before_migration(paths: &StoragePaths, version: u32) {
+    let backup_path = paths.index_path.with_extension(format!("v{}.bak", version));
+    if let Err(e) = fs::copy(&paths.index_path, &backup_path) {
+        eprintln!("Failed to back up index before migration: {}", e);
+    }
+}
+
+/// Rebuild an index from a file that failed to load, keeping every prompt
+/// entry that still parses on its own. Entries that are lost are recreated
+/// from the prompt files by `sync_index_with_filesystem`, minus their metadata.
+fn salvage_index(data: &str) -> PromptIndex {
+    let mut index = PromptIndex {
+        // The library existed before, so don't add the samples again
+        seeded: true,
+        ..PromptIndex::default()
+    };
+
+    match serde_json::from_str::<Value>(data) {
+        // Well-formed JSON with bad entries: check each entry separately
+        Ok(value) => {
+            if let Some(prompts) = value.get("prompts").and_then(Value::as_array) {
+                index.prompts = prompts
+                    .iter()
+                    .filter_map(|p| serde_json::from_value::<PromptMetadata>(p.clone()).ok())
+                    .collect();
+            }
+            if let Some(folders) = value.get("folders").and_then(Value::as_array) {
+                index.folders = folders
+                    .iter()
+                    .filter_map(|f| f.as_str().map(str::to_string))
+                    .collect();
+            }
+            if let Some(folder_meta) = value.get("folderMeta").and_then(Value::as_object) {
+                let salvaged: HashMap<String, FolderMeta> = folder_meta
+                    .iter()
+                    .filter_map(|(name, meta)| {
+                        let meta = serde_json::from_value::<FolderMeta>(meta.clone()).ok()?;
+                        Some((name.clone(), meta))
+                    })
+                    .collect();
+                index.folder_meta = Some(salvaged).filter(|m| !m.is_empty());
+            }
+            if let Some(searches) = value.get("savedSearches").and_then(Value::as_array) {
+                index.saved_searches = searches
+                    .iter()
+                    .filter_map(|s| serde_json::from_value::<SavedSearch>(s.clone()).ok())
+                    .collect();
+            }
+        }
+        // Truncated or garbled text: pick out each complete prompt object
+        Err(_) => {
+            index.prompts = array_elements(data, "prompts")
+                .into_iter()
+                .filter_map(|object| serde_json::from_str::<PromptMetadata>(object).ok())
+                .collect();
+            index.folders = array_elements(data, "folders")
+                .into_iter()
+                .filter_map(|folder| serde_json::from_str::<String>(folder).ok())
+                .collect();
+        }
+    }
+
+    index
+}
+
+/// Raw text of each complete object or string element in the array following
+/// `"key":`. Scanning stops at the first element cut off by the end of the text.
+fn array_elements<'a>(data: &'a str, key: &str) -> Vec<&'a str> {
+    let needle = format!("\"{}\"", key);
+    // Skip string values that happen to equal the key, like a folder named "prompts"
+    let rest = data
+        .match_indices(&needle)
+        .map(|(pos, _)| &data[pos + needle.len()..])
+        .find(|rest| rest.trim_start().starts_with(':'));
+    let rest = match rest {
+        Some(rest) => rest,
+        None => return Vec::new(),
+    };
+    let open = match rest.find('[') {
+        Some(open) => open,
+        None => return Vec::new(),
+    };
+
+    let mut elements = Vec::new();
+    let mut depth = 0usize;
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    // Only ASCII delimiters are matched, so byte offsets are valid char boundaries
+    for (i, byte) in rest.bytes().enumerate().skip(open + 1) {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' {
+                in_string = false;
+                if depth == 0 {
+                    if let Some(s) = start.take() {
+                        elements.push(&rest[s..=i]);
+                    }
+                }
+            }
+            continue;
+        }
+
+        match byte {
+            b'"' => {
+                in_string = true;
+                if depth == 0 {
+                    start = Some(i);
+                }
+            }
+            b'{' => {
+                if depth == 0 {
+                    start = Some(i);
+                }
+                depth += 1;
+            }
+            b'}' => {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+                if depth == 0 {
+                    if let Some(s) = start.take() {
+                        elements.push(&rest[s..=i]);
+                    }
+                }
+            }
+            b']' if depth == 0 => break,
+            _ => {}
+        }
+    }
+
+    elements
+}
+
+/// `rebuilt` says the index didn't load whole, so folder metadata may be missing from it.
+fn sync_index_with_filesystem(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    rebuilt: bool,
+) -> AppResult<bool> {
     let mut changed = false;
     let existing_len = index.prompts.len();
+    let known_folders = folder_service::all_folders(index);
 
-    let mut existing_by_key: HashMap<(String, String), crate::models::prompt::PromptMetadata> =
-        HashMap::new();
+    let mut existing_by_key: HashMap<(String, String), PromptMetadata> = HashMap::new();
     for meta in index.prompts.drain(..) {
         existing_by_key.insert((meta.folder.clone(), meta.filename.clone()), meta);
     }
@@ -72,7 +308,7 @@ fn sync_index_with_filesystem(paths: &StoragePaths, index: &mut PromptIndex) ->
         let now = chrono::Utc::now().to_rfc3339();
         let ts = file_timestamp.unwrap_or_else(|| now.clone());
 
-        rebuilt_prompts.push(crate::models::prompt::PromptMetadata {
+        rebuilt_prompts.push(PromptMetadata {
             id: uuid::Uuid::new_v4().to_string(),
             name: filename_to_title(&filename),
             folder,
@@ -84,6 +320,10 @@ fn sync_index_with_filesystem(paths: &StoragePaths, index: &mut PromptIndex) ->
             updated: ts,
             icon: None,
             color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
         });
     }
 
@@ -115,6 +355,32 @@ fn sync_index_with_filesystem(paths: &StoragePaths, index: &mut PromptIndex) ->
         }
     }
 
+    // Sidecar files win over an index that was rebuilt without them. A whole
+    // index already has the metadata of the folders it knows, so otherwise only
+    // folders that appeared on disk are read, rather than walking the tree.
+    let sidecars = if rebuilt {
+        folder_service::read_sidecars(paths)?
+    } else {
+        new_folders(&discovered_folders, &known_folders)
+            .into_iter()
+            .filter_map(|folder| {
+                let meta = folder_service::read_sidecar(paths, &folder)?;
+                Some((folder, meta))
+            })
+            .collect()
+    };
+    for (folder, meta) in sidecars {
+        if !index.folders.contains(&folder) {
+            index.folders.push(folder.clone());
+            changed = true;
+        }
+        let all_meta = index.folder_meta.get_or_insert_with(HashMap::new);
+        if all_meta.get(&folder) != Some(&meta) {
+            all_meta.insert(folder, meta);
+            changed = true;
+        }
+    }
+
     // Dedupe while preserving order
     let mut seen = HashSet::new();
     let before = index.folders.len();
@@ -126,6 +392,18 @@ fn sync_index_with_filesystem(paths: &StoragePaths, index: &mut PromptIndex) ->
     Ok(changed)
 }
 
+/// Folders holding prompts, and their parents, that aren't in `known`.
+fn new_folders(discovered: &HashSet<String>, known: &BTreeSet<String>) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    for folder in discovered {
+        let mut path = folder.as_str();
+        while !path.is_empty() && !known.contains(path) && found.insert(path.to_string()) {
+            path = folder_service::parent(path);
+        }
+    }
+    found
+}
+
 fn scan_prompt_files(prompts_dir: &Path) -> AppResult<Vec<(String, String, PathBuf)>> {
     let mut files = Vec::new();
 
@@ -230,51 +508,202 @@ pub fn add_folder(index: &mut PromptIndex, name: String) -> AppResult<()> {
     Ok(())
 }
 
-pub fn rename_folder(index: &mut PromptIndex, old_name: &str, new_name: &str) -> AppResult<()> {
-    if index.folders.contains(&new_name.to_string()) {
-        return Err(format!("Folder '{}' already exists", new_name));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{metadata, temp_paths};
+    use serde_json::json;
+
+    fn prompt(id: &str, name: &str) -> PromptMetadata {
+        metadata(
+            id,
+            json!({
+                "name": name,
+                "filename": format!("{}.md", id),
+                "useCount": 3,
+                "lastUsed": "2026-03-01T12:00:00+00:00",
+                "created": "2026-01-01T00:00:00+00:00",
+                "updated": "2026-01-01T00:00:00+00:00",
+                "tags": ["a"]
+            }),
+        )
     }
 
-    let pos = index
-        .folders
-        .iter()
-        .position(|f| f == old_name)
-        .ok_or_else(|| format!("Folder '{}' not found", old_name))?;
-    index.folders[pos] = new_name.to_string();
-
-    // Update all prompt folder references
-    for prompt in &mut index.prompts {
-        if prompt.folder == old_name {
-            prompt.folder = new_name.to_string();
-        }
+    fn index_json(prompts: Vec<PromptMetadata>, folders: &[&str]) -> String {
+        let index = PromptIndex {
+            prompts,
+            folders: folders.iter().map(|f| f.to_string()).collect(),
+            ..PromptIndex::default()
+        };
+        serde_json::to_string_pretty(&index).unwrap()
     }
 
-    // Update folder_meta if present
-    if let Some(ref mut meta) = index.folder_meta {
-        if let Some(folder_meta) = meta.remove(old_name) {
-            let mut updated = folder_meta;
-            updated.name = new_name.to_string();
-            meta.insert(new_name.to_string(), updated);
-        }
+    fn ids(index: &PromptIndex) -> Vec<&str> {
+        index.prompts.iter().map(|p| p.id.as_str()).collect()
     }
 
-    Ok(())
-}
+    #[test]
+    fn truncated_index_keeps_every_complete_prompt() {
+        let data = index_json(
+            vec![prompt("a", "A"), prompt("b", "B"), prompt("c", "C")],
+            &[],
+        );
+        let cut = data.find("\"c\"").unwrap() + 10;
 
-pub fn delete_folder(index: &mut PromptIndex, name: &str) -> AppResult<()> {
-    index.folders.retain(|f| f != name);
+        let index = salvage_index(&data[..cut]);
 
-    // Move prompts to uncategorized
-    for prompt in &mut index.prompts {
-        if prompt.folder == name {
-            prompt.folder = String::new();
-        }
+        assert_eq!(ids(&index), ["a", "b"]);
+        assert!(index.seeded);
     }
 
-    // Remove from folder_meta if present
-    if let Some(ref mut meta) = index.folder_meta {
-        meta.remove(name);
+    #[test]
+    fn truncation_inside_a_string_keeps_the_prompts_before_it() {
+        let data = index_json(vec![prompt("a", "A"), prompt("b", "Long name")], &[]);
+        let cut = data.find("Long").unwrap() + 2;
+
+        assert_eq!(ids(&salvage_index(&data[..cut])), ["a"]);
     }
 
-    Ok(())
+    #[test]
+    fn a_bad_prompt_in_the_middle_is_skipped() {
+        let data = index_json(
+            vec![prompt("a", "A"), prompt("b", "B"), prompt("c", "C")],
+            &[],
+        );
+        let broken = data.replacen("\"useCount\": 3", "\"useCount\": \"three\"", 2);
+        let broken = broken.replacen("\"useCount\": \"three\"", "\"useCount\": 3", 1);
+
+        // Well-formed JSON: the entry fails on its own
+        assert_eq!(ids(&salvage_index(&broken)), ["a", "c"]);
+        // Garbled text: the entry fails while scanning
+        let garbled = format!("{}garbage", &broken[..broken.len() - 2]);
+        assert_eq!(ids(&salvage_index(&garbled)), ["a", "c"]);
+    }
+
+    #[test]
+    fn brackets_and_escaped_quotes_in_strings_do_not_end_elements() {
+        let tricky = prompt("a", "Say \"hi\" [then] {leave} \\");
+        let data = index_json(
+            vec![tricky.clone(), prompt("b", "B")],
+            &["with ] bracket", "quote \" here"],
+        );
+        let truncated = &data[..data.rfind('}').unwrap()];
+
+        let index = salvage_index(truncated);
+
+        assert_eq!(ids(&index), ["a", "b"]);
+        assert_eq!(index.prompts[0].name, tricky.name);
+        assert_eq!(index.folders, ["with ] bracket", "quote \" here"]);
+    }
+
+    #[test]
+    fn a_string_equal_to_the_key_is_not_the_key() {
+        let data = index_json(vec![prompt("a", "A")], &["prompts"]);
+        // The string "prompts" comes before the key, followed by another array
+        let value: Value = serde_json::from_str(&data).unwrap();
+        let reordered = format!(
+            "{{\"folders\": {}, \"other\": [\"x\"], \"prompts\": {}",
+            value["folders"], value["prompts"]
+        );
+
+        assert_eq!(ids(&salvage_index(&reordered)), ["a"]);
+    }
+
+    #[test]
+    fn salvaged_prompts_round_trip_unchanged() {
+        let mut original = prompt("a", "A");
+        original
+            .extra
+            .insert("fromLaterRelease".to_string(), json!({ "code.exe": 2 }));
+        let data = index_json(vec![original.clone(), prompt("b", "B")], &["x"]);
+
+        let index = salvage_index(&data[..data.len() - 30]);
+        let reloaded = parse_index(&serde_json::to_string(&index).unwrap())
+            .unwrap()
+            .0;
+
+        assert_eq!(
+            serde_json::to_value(&reloaded.prompts[0]).unwrap(),
+            serde_json::to_value(&original).unwrap()
+        );
+    }
+
+    #[test]
+    fn loading_a_truncated_index_keeps_metadata_and_backs_it_up() {
+        let paths = temp_paths("truncated");
+        fs::write(paths.prompts_dir.join("a.md"), "A").unwrap();
+        fs::write(paths.prompts_dir.join("b.md"), "B").unwrap();
+        let data = index_json(vec![prompt("a", "Named A"), prompt("b", "Named B")], &[]);
+        let cut = data.find("\"b\"").unwrap();
+        fs::write(&paths.index_path, &data[..cut]).unwrap();
+
+        let index = load_index(&paths).unwrap();
+
+        let a = index.prompts.iter().find(|p| p.filename == "a.md").unwrap();
+        assert_eq!(
+            (a.id.as_str(), a.name.as_str(), a.use_count),
+            ("a", "Named A", 3)
+        );
+        // The lost entry comes back from its file, without its metadata
+        let b = index.prompts.iter().find(|p| p.filename == "b.md").unwrap();
+        assert_eq!(b.use_count, 0);
+        assert!(parse_index(&fs::read_to_string(&paths.index_path).unwrap()).is_ok());
+        let backups = fs::read_dir(&paths.root)
+            .unwrap()
+            .filter(|e| {
+                e.as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_string_lossy()
+                    .contains("corrupt")
+            })
+            .count();
+        assert_eq!(backups, 1);
+    }
+
+    #[test]
+    fn sidecars_are_read_for_new_folders_and_rebuilt_indexes() {
+        let paths = temp_paths("sidecars");
+        let write = |folder: &str, description: &str| {
+            let meta = FolderMeta {
+                description: description.to_string(),
+                ..FolderMeta::default()
+            };
+            folder_service::write_sidecar(&paths, folder, &meta).unwrap();
+        };
+        let description = |index: &PromptIndex, folder: &str| {
+            index
+                .folder_meta
+                .as_ref()
+                .and_then(|meta| meta.get(folder))
+                .map(|meta| meta.description.clone())
+        };
+        write("work", "Work");
+        write("work/mail", "Mail");
+        fs::write(paths.prompts_dir.join("work/mail/reply.md"), "Hi").unwrap();
+
+        let index = load_index(&paths).unwrap();
+        assert_eq!(description(&index, "work").as_deref(), Some("Work"));
+        assert_eq!(description(&index, "work/mail").as_deref(), Some("Mail"));
+
+        // Known folders keep what the index says
+        write("work/mail", "Changed behind our back");
+        fs::create_dir_all(paths.prompts_dir.join("notes")).unwrap();
+        write("notes", "Notes");
+        fs::write(paths.prompts_dir.join("notes/todo.md"), "Todo").unwrap();
+        let index = load_index(&paths).unwrap();
+        assert_eq!(description(&index, "work/mail").as_deref(), Some("Mail"));
+        assert_eq!(description(&index, "notes").as_deref(), Some("Notes"));
+
+        // An index rebuilt from the files gets every sidecar, even of empty folders
+        write("archive", "Old");
+        fs::remove_file(&paths.index_path).unwrap();
+        let index = load_index(&paths).unwrap();
+        assert_eq!(
+            description(&index, "work/mail").as_deref(),
+            Some("Changed behind our back")
+        );
+        assert_eq!(description(&index, "archive").as_deref(), Some("Old"));
+        assert!(index.folders.contains(&"archive".to_string()));
+    }
 }
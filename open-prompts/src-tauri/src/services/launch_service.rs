@@ -0,0 +1,358 @@
+use crate::error::AppResult;
+use crate::models::launch::{DeepLink, LaunchArgs};
+use std::collections::HashMap;
+use url::Url;
+
+pub const URL_SCHEME: &str = "openprompts";
+
+const MAX_LINK_LENGTH: usize = 8 * 1024;
+const MAX_ID_LENGTH: usize = 64;
+const MAX_VARIABLES: usize = 32;
+const MAX_VARIABLE_NAME_LENGTH: usize = 64;
+
+/// Parse command-line arguments, without the program name. Unknown arguments
+/// are ignored so installers and autostart entries can add their own.
+pub fn parse_args<I: IntoIterator<Item = String>>(args: I) -> LaunchArgs {
+    let mut launch = LaunchArgs::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--open-editor" {
+            launch.open_editor = true;
+        } else if arg == "--search" {
+            launch.search = args.next();
+        } else if let Some(query) = arg.strip_prefix("--search=") {
+            launch.search = Some(query.to_string());
+        } else if is_app_url(&arg) {
+            launch.url = Some(arg);
+        }
+    }
+
+    launch
+}
+
+/// Parse and validate an `openprompts://` link. Anything not understood is
+/// rejected rather than guessed at, since links can come from any web page.
+pub fn parse_deep_link(link: &str) -> AppResult<DeepLink> {
+    if link.len() > MAX_LINK_LENGTH {
+        return Err("Link is too long".to_string());
+    }
+    let url = Url::parse(link).map_err(|e| format!("Invalid link: {}", e))?;
+    if url.scheme() != URL_SCHEME {
+        return Err(format!("Not an {}:// link", URL_SCHEME));
+    }
+
+    let action = url.host_str().unwrap_or("");
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    match action {
+        // `prompt/<id>` is the older spelling of `open/<id>`
+        "open" | "prompt" => {
+            no_query(&url)?;
+            Ok(DeepLink::Open {
+                id: single_id(&segments)?,
+            })
+        }
+        "paste" => Ok(DeepLink::Paste {
+            id: single_id(&segments)?,
+            variables: variables(&url)?,
+        }),
+        "import" => {
+            if !segments.is_empty() {
+                return Err("Import links take no path".to_string());
+            }
+            Ok(DeepLink::Import {
+                path: import_path(&url)?,
+            })
+        }
+        "" => Err("Link has no action".to_string()),
+        other => Err(format!("Unknown link action '{}'", other)),
+    }
+}
+
+fn single_id(segments: &[&str]) -> AppResult<String> {
+    match segments {
+        [id] if is_valid_id(id) => Ok(id.to_string()),
+        [id] => Err(format!("Invalid prompt id '{}'", id)),
+        [] => Err("Link is missing a prompt id".to_string()),
+        _ => Err("Link has extra path segments".to_string()),
+    }
+}
+
+/// Prompt ids are UUIDs, but anything made of ASCII letters, digits, `-` and `_` is accepted.
+fn is_valid_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_ID_LENGTH
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn no_query(url: &Url) -> AppResult<()> {
+    match url.query() {
+        Some(query) if !query.is_empty() => Err("Open links take no parameters".to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn variables(url: &Url) -> AppResult<HashMap<String, String>> {
+    let mut variables = HashMap::new();
+    for (name, value) in url.query_pairs() {
+        if name.trim().is_empty() || name.len() > MAX_VARIABLE_NAME_LENGTH {
+            return Err(format!("Invalid variable name '{}'", name));
+        }
+        if variables.len() >= MAX_VARIABLES {
+            return Err(format!("Links may set at most {} variables", MAX_VARIABLES));
+        }
+        if variables
+            .insert(name.to_string(), value.to_string())
+            .is_some()
+        {
+            return Err(format!("Variable '{}' is set twice", name));
+        }
+    }
+    Ok(variables)
+}
+
+/// Only local `.md` and `.txt` files can be imported.
+fn import_path(url: &Url) -> AppResult<String> {
+    let mut source = None;
+    for (key, value) in url.query_pairs() {
+        match key.as_ref() {
+            "url" if source.is_none() => source = Some(value.to_string()),
+            "url" => return Err("Import links take one url".to_string()),
+            other => return Err(format!("Unknown import parameter '{}'", other)),
+        }
+    }
+    let source = source.ok_or("Import link is missing url=")?;
+
+    let file_url = Url::parse(&source).map_err(|e| format!("Invalid import url: {}", e))?;
+    if file_url.scheme() != "file" {
+        return Err("Only file:// urls can be imported".to_string());
+    }
+    // On Windows a host would make this a UNC path on another machine
+    let local = file_url
+        .host_str()
+        .is_none_or(|host| host.is_empty() || host.eq_ignore_ascii_case("localhost"));
+    if !local {
+        return Err("Only files on this computer can be imported".to_string());
+    }
+    let path = file_url
+        .to_file_path()
+        .map_err(|_| "Import url is not a local file path".to_string())?;
+
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_ascii_lowercase());
+    if !matches!(extension.as_deref(), Some("md") | Some("txt")) {
+        return Err("Only .md and .txt files can be imported".to_string());
+    }
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+fn is_app_url(arg: &str) -> bool {
+    arg.get(..URL_SCHEME.len() + 3)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(&format!("{}://", URL_SCHEME)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(id: &str) -> DeepLink {
+        DeepLink::Open { id: id.to_string() }
+    }
+
+    fn import(link: &str) -> AppResult<String> {
+        match parse_deep_link(link)? {
+            DeepLink::Import { path } => Ok(path),
+            other => panic!("not an import link: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn args_take_flags_search_and_links() {
+        let args = parse_args(
+            [
+                "--open-editor",
+                "--search",
+                "code review",
+                "--installer-flag",
+                "OpenPrompts://open/abc",
+            ]
+            .map(String::from),
+        );
+        assert!(args.open_editor);
+        assert_eq!(args.search.as_deref(), Some("code review"));
+        assert_eq!(args.url.as_deref(), Some("OpenPrompts://open/abc"));
+
+        let args = parse_args(["--search=email".to_string()]);
+        assert_eq!(args.search.as_deref(), Some("email"));
+        assert!(parse_args(["https://example.com".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn open_links_take_one_id() {
+        assert_eq!(
+            parse_deep_link("openprompts://open/abc-123").unwrap(),
+            open("abc-123")
+        );
+        assert_eq!(
+            parse_deep_link("openprompts://prompt/abc_1/").unwrap(),
+            open("abc_1")
+        );
+        assert!(parse_deep_link("openprompts://open").is_err());
+        assert!(parse_deep_link("openprompts://open/a/b").is_err());
+        assert!(parse_deep_link("openprompts://open/abc?x=1").is_err());
+        assert!(parse_deep_link(&format!("openprompts://open/{}", "a".repeat(65))).is_err());
+    }
+
+    #[test]
+    fn malformed_links_and_unknown_actions_are_rejected() {
+        for link in [
+            "",
+            "not a link",
+            "openprompts:open/abc",
+            "openprompts://",
+            "openprompts:///abc",
+            "https://open/abc",
+            "file:///open/abc",
+            "openprompts://delete/abc",
+            "openprompts://OPEN%2Fabc",
+        ] {
+            assert!(parse_deep_link(link).is_err(), "{:?} was accepted", link);
+        }
+        let too_long = format!("openprompts://open/abc?{}", "x".repeat(MAX_LINK_LENGTH));
+        assert_eq!(parse_deep_link(&too_long).unwrap_err(), "Link is too long");
+    }
+
+    #[test]
+    fn percent_encoded_ids_are_not_decoded_into_paths() {
+        for link in [
+            "openprompts://open/a%2Fb",
+            "openprompts://open/%2E%2E%2Fsecret",
+            "openprompts://open/a%00b",
+            "openprompts://paste/a%20b",
+            "openprompts://open/%C3%A9",
+        ] {
+            assert!(parse_deep_link(link).is_err(), "{:?} was accepted", link);
+        }
+    }
+
+    #[test]
+    fn dot_segments_never_reach_the_id() {
+        // The URL parser resolves dot segments, so what's left must still be one plain id
+        assert_eq!(
+            parse_deep_link("openprompts://open/x/../abc").unwrap(),
+            open("abc")
+        );
+        assert_eq!(
+            parse_deep_link("openprompts://open/%2e%2e/abc").unwrap(),
+            open("abc")
+        );
+        assert!(parse_deep_link("openprompts://open/..").is_err());
+        assert!(parse_deep_link("openprompts://open/abc/..").is_err());
+    }
+
+    #[test]
+    fn paste_links_decode_variables() {
+        let link = "openprompts://paste/abc?name=Ada%20Lovelace&topic=a%26b";
+        let variables = HashMap::from([
+            ("name".to_string(), "Ada Lovelace".to_string()),
+            ("topic".to_string(), "a&b".to_string()),
+        ]);
+        assert_eq!(
+            parse_deep_link(link).unwrap(),
+            DeepLink::Paste {
+                id: "abc".to_string(),
+                variables,
+            }
+        );
+
+        assert!(parse_deep_link("openprompts://paste/abc?a=1&a=2").is_err());
+        assert!(parse_deep_link("openprompts://paste/abc?=1").is_err());
+        let many: Vec<String> = (0..=MAX_VARIABLES).map(|i| format!("v{}=x", i)).collect();
+        assert!(parse_deep_link(&format!("openprompts://paste/abc?{}", many.join("&"))).is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn import_links_take_one_local_text_file() {
+        let path =
+            import("openprompts://import?url=file%3A%2F%2F%2Ftmp%2Fmy%2520notes.MD").unwrap();
+        assert_eq!(path, "/tmp/my notes.MD");
+        assert!(import("openprompts://import?url=file:///tmp/a.txt").is_ok());
+
+        assert!(import("openprompts://import").is_err());
+        assert!(import("openprompts://import/x?url=file:///tmp/a.md").is_err());
+        assert!(import("openprompts://import?url=https://example.com/a.md").is_err());
+        assert!(import("openprompts://import?url=file:///tmp/a.exe").is_err());
+        assert!(import("openprompts://import?url=file:///tmp/a.md&url=file:///tmp/b.md").is_err());
+        assert!(import("openprompts://import?url=file:///tmp/a.md&then=paste").is_err());
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn import_links_take_one_local_text_file() {
+        let path =
+            import("openprompts://import?url=file%3A%2F%2F%2FC%3A%2FUsers%2Fmy%2520notes.MD")
+                .unwrap();
+        assert_eq!(path, r"C:\Users\my notes.MD");
+        assert!(import("openprompts://import?url=file:///C:/Users/a.txt").is_ok());
+
+        assert!(import("openprompts://import").is_err());
+        assert!(import("openprompts://import/x?url=file:///C:/Users/a.md").is_err());
+        assert!(import("openprompts://import?url=https://example.com/a.md").is_err());
+        assert!(import("openprompts://import?url=file:///C:/Users/a.exe").is_err());
+        assert!(import("openprompts://import?url=file:///C:/a.md&url=file:///C:/b.md").is_err());
+        assert!(import("openprompts://import?url=file:///C:/Users/a.md&then=paste").is_err());
+    }
+
+    #[test]
+    fn import_links_only_take_files_on_this_computer() {
+        for url in [
+            "file://server/share/a.md",
+            "file://192.168.1.5/share/a.md",
+            "file://localhost.example.com/a.md",
+        ] {
+            let link = format!("openprompts://import?url={}", url);
+            assert_eq!(
+                import(&link).unwrap_err(),
+                "Only files on this computer can be imported",
+                "{}",
+                url
+            );
+        }
+
+        #[cfg(unix)]
+        let local = "file://LOCALHOST/tmp/a.md";
+        #[cfg(windows)]
+        let local = "file://localhost/C:/Users/a.md";
+        assert!(import(&format!("openprompts://import?url={}", local)).is_ok());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn dot_segments_in_import_paths_are_resolved() {
+        let link = "openprompts://import?url=file:///tmp/prompts/../../etc/notes.md";
+        assert_eq!(import(link).unwrap(), "/etc/notes.md");
+
+        let encoded = "openprompts://import?url=file:///tmp/a/%2E%2E/notes.md";
+        assert_eq!(import(encoded).unwrap(), "/tmp/notes.md");
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn dot_segments_in_import_paths_are_resolved() {
+        let link = "openprompts://import?url=file:///C:/Users/prompts/../../notes.md";
+        assert_eq!(import(link).unwrap(), r"C:\notes.md");
+
+        let encoded = "openprompts://import?url=file:///C:/Users/%2E%2E/notes.md";
+        assert_eq!(import(encoded).unwrap(), r"C:\notes.md");
+    }
+}
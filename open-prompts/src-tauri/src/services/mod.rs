@@ -1,6 +1,16 @@
 pub mod storage;
 pub mod index_service;
 pub mod prompt_service;
+pub mod bulk_service;
+pub mod duplicate_service;
+pub mod folder_service;
 pub mod settings_service;
 pub mod seed_service;
 pub mod search_service;
+pub mod fuzzy_service;
+#[cfg(feature = "semantic-search")]
+pub mod embedding_service;
+pub mod frecency_service;
+pub mod usage_log_service;
+pub mod template_service;
+pub mod launch_service;
@@ -1,8 +1,16 @@
 use crate::error::{map_err, AppResult};
-use crate::models::prompt::{Prompt, PromptIndex, PromptMetadata};
-use crate::services::storage::{atomic_write, StoragePaths};
+use crate::models::prompt::{
+    FolderDeleteMode, Prompt, PromptDependencies, PromptIndex, PromptMetadata, RenderedPrompt,
+    TemplateError,
+};
+use crate::services::storage::{self, atomic_write, FileStamp, StoragePaths};
+use crate::services::{folder_service, template_service};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+/// Imported files larger than this are refused.
+const MAX_IMPORT_BYTES: u64 = 1024 * 1024;
 
 /// Get the filesystem path for a prompt's .md file
 pub fn get_prompt_path(paths: &StoragePaths, folder: &str, filename: &str) -> PathBuf {
@@ -72,6 +80,30 @@ pub fn ensure_unique_filename(paths: &StoragePaths, folder: &str, base: &str) ->
     format!("{}-{}.md", sanitized, uuid::Uuid::new_v4())
 }
 
+/// Derive a prompt name from the first non-empty line of its content,
+/// dropping markdown heading/list/quote markers.
+pub fn derive_name(content: &str) -> String {
+    const MAX_NAME_CHARS: usize = 60;
+
+    let first_line = content
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("");
+    let stripped = first_line
+        .trim_start_matches(['#', '>', '-', '*'])
+        .trim_matches('*')
+        .trim();
+
+    let name: String = stripped.chars().take(MAX_NAME_CHARS).collect();
+    let name = name.trim_end();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
 /// Load a prompt's content from disk, combining metadata from index with .md file
 pub fn load_prompt(paths: &StoragePaths, index: &PromptIndex, id: &str) -> AppResult<Prompt> {
     let meta = index
@@ -90,6 +122,298 @@ pub fn load_prompt(paths: &StoragePaths, index: &PromptIndex, id: &str) -> AppRe
     })
 }
 
+/// Every prompt's content by id, for searching bodies. Prompts whose file
+/// can't be read are left out.
+pub fn load_bodies(paths: &StoragePaths, index: &PromptIndex) -> HashMap<String, String> {
+    index
+        .prompts
+        .iter()
+        .filter_map(|meta| {
+            let path = get_prompt_path(paths, &meta.folder, &meta.filename);
+            let content = fs::read_to_string(path).ok()?;
+            Some((meta.id.clone(), content))
+        })
+        .collect()
+}
+
+/// Prompt bodies kept between searches with the stamp of the file each was
+/// read from, so typing a query only reads the files that changed.
+#[derive(Default)]
+pub struct BodyCache {
+    bodies: HashMap<String, String>,
+    stamps: HashMap<String, (PathBuf, FileStamp)>,
+}
+
+impl BodyCache {
+    /// Every prompt's content by id, as `load_bodies` returns it, reading
+    /// only files that are new or changed since the last call.
+    pub fn refresh(
+        &mut self,
+        paths: &StoragePaths,
+        index: &PromptIndex,
+    ) -> &HashMap<String, String> {
+        let mut bodies = HashMap::new();
+        let mut stamps = HashMap::new();
+        for meta in &index.prompts {
+            let path = get_prompt_path(paths, &meta.folder, &meta.filename);
+            let stamp = match storage::stamp(&path) {
+                Some(stamp) => stamp,
+                None => continue,
+            };
+            let current = (path, stamp);
+            let cached = match self.stamps.get(&meta.id) {
+                Some(read) if *read == current => self.bodies.remove(&meta.id),
+                _ => None,
+            };
+            let body = match cached {
+                Some(body) => body,
+                None => match fs::read_to_string(&current.0) {
+                    Ok(body) => body,
+                    Err(_) => continue,
+                },
+            };
+            bodies.insert(meta.id.clone(), body);
+            stamps.insert(meta.id.clone(), current);
+        }
+        self.bodies = bodies;
+        self.stamps = stamps;
+        &self.bodies
+    }
+}
+
+/// `folder/stem` of a prompt's file, e.g. `coding/code-review`. This is the
+/// name `{{> ...}}` includes and MCP clients use.
+pub fn qualified_name(meta: &PromptMetadata) -> String {
+    let stem = Path::new(&meta.filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(&meta.filename);
+    if meta.folder.is_empty() {
+        stem.to_string()
+    } else {
+        format!("{}/{}", meta.folder, stem)
+    }
+}
+
+/// The prompt an include target names: its `folder/file-stem`, or failing
+/// that its `folder/Prompt Name`, ignoring case.
+pub fn find_by_qualified_name<'a>(
+    index: &'a PromptIndex,
+    target: &str,
+) -> Option<&'a PromptMetadata> {
+    index
+        .prompts
+        .iter()
+        .find(|p| qualified_name(p) == target)
+        .or_else(|| {
+            let (folder, name) = target.rsplit_once('/').unwrap_or(("", target));
+            index
+                .prompts
+                .iter()
+                .find(|p| p.folder == folder && p.name.eq_ignore_ascii_case(name))
+        })
+}
+
+/// Load a prompt with its `{{> folder/name}}` includes replaced by the included prompts.
+pub fn load_expanded(paths: &StoragePaths, index: &PromptIndex, id: &str) -> AppResult<Prompt> {
+    let mut prompt = load_prompt(paths, index, id)?;
+    prompt.content = template_service::expand_includes(id, &prompt.content, &mut |target| {
+        let meta = find_by_qualified_name(index, target)
+            .ok_or_else(|| format!("Included prompt '{}' not found", target))?;
+        let included = load_prompt(paths, index, &meta.id)?;
+        Ok((included.meta.id, included.content))
+    })?;
+    Ok(prompt)
+}
+
+/// Load a prompt, expand its includes and fill in its `{{variables}}` from `values`.
+pub fn render_prompt(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    id: &str,
+    values: &HashMap<String, String>,
+) -> AppResult<RenderedPrompt> {
+    let prompt = load_expanded(paths, index, id)?;
+    render_expanded(paths, index, prompt, values).map_err(|e| e.to_string())
+}
+
+/// Fill in the `{{variables}}` of a prompt from `load_expanded`. A template
+/// error is placed in the prompt's own file, naming the included prompt it
+/// comes from if it isn't the prompt's own.
+pub fn render_expanded(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    expanded: Prompt,
+    values: &HashMap<String, String>,
+) -> Result<RenderedPrompt, TemplateError> {
+    match template_service::render(&expanded.content, values) {
+        Ok((content, missing_variables)) => Ok(RenderedPrompt {
+            id: expanded.meta.id,
+            content,
+            missing_variables,
+        }),
+        Err(e) => {
+            let raw = match load_prompt(paths, index, &expanded.meta.id) {
+                Ok(raw) => raw.content,
+                Err(_) => return Err(e),
+            };
+            Err(template_service::locate_error(&raw, e, &mut |target| {
+                let meta = find_by_qualified_name(index, target)
+                    .ok_or_else(|| format!("Included prompt '{}' not found", target))?;
+                Ok(load_prompt(paths, index, &meta.id)?.content)
+            }))
+        }
+    }
+}
+
+/// The text pasting or copying `id` delivers. Without `values` the prompt goes
+/// out as written with its includes expanded, since rendering with nothing
+/// filled in would drop every block. A template that doesn't parse also goes
+/// out as written rather than failing the paste.
+pub fn paste_content(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    id: &str,
+    values: &HashMap<String, String>,
+) -> AppResult<String> {
+    let prompt = load_expanded(paths, index, id)?;
+    if values.is_empty() {
+        return Ok(prompt.content);
+    }
+    match template_service::render(&prompt.content, values) {
+        Ok((content, _)) => Ok(content),
+        Err(_) => Ok(prompt.content),
+    }
+}
+
+/// Template problems in `content`: bad blocks, and includes that name no prompt
+/// or would include `id` again.
+pub fn validate_template(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    id: Option<&str>,
+    content: &str,
+) -> Vec<TemplateError> {
+    template_service::validate(content, &mut |target| {
+        let meta = find_by_qualified_name(index, target)
+            .ok_or_else(|| format!("Included prompt '{}' not found", target))?;
+        // Expanding the included prompt with this one as the root catches cycles back to it
+        let included = load_prompt(paths, index, &meta.id)?;
+        let root = id.unwrap_or_default();
+        template_service::expand_includes(root, &included.content, &mut |nested| {
+            let nested = find_by_qualified_name(index, nested)
+                .ok_or_else(|| format!("Included prompt '{}' not found", nested))?;
+            let loaded = load_prompt(paths, index, &nested.id)?;
+            Ok((loaded.meta.id, loaded.content))
+        })
+        .map(|_| ())
+    })
+}
+
+/// Which prompts `id` includes and which prompts end up including it.
+/// Prompts whose files can't be read are left out.
+pub fn prompt_dependencies(
+    paths: &StoragePaths,
+    index: &PromptIndex,
+    id: &str,
+) -> AppResult<PromptDependencies> {
+    let prompt = load_prompt(paths, index, id)?;
+
+    let mut includes = Vec::new();
+    let mut unresolved = Vec::new();
+    for target in template_service::extract_includes(&prompt.content) {
+        match find_by_qualified_name(index, &target) {
+            Some(meta) if !includes.iter().any(|m: &PromptMetadata| m.id == meta.id) => {
+                includes.push(meta.clone())
+            }
+            Some(_) => {}
+            None => unresolved.push(target),
+        }
+    }
+
+    // Reverse edges: included id -> ids of the prompts including it
+    let mut includers: HashMap<&str, Vec<&str>> = HashMap::new();
+    for meta in &index.prompts {
+        let file_path = get_prompt_path(paths, &meta.folder, &meta.filename);
+        let content = match fs::read_to_string(file_path) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        for target in template_service::extract_includes(&content) {
+            if let Some(included) = find_by_qualified_name(index, &target) {
+                includers.entry(&included.id).or_default().push(&meta.id);
+            }
+        }
+    }
+
+    let mut seen: HashSet<&str> = HashSet::from([id]);
+    let mut queue: VecDeque<&str> = VecDeque::from([id]);
+    let mut included_by = Vec::new();
+    while let Some(current) = queue.pop_front() {
+        for &includer in includers.get(current).into_iter().flatten() {
+            if seen.insert(includer) {
+                queue.push_back(includer);
+                if let Some(meta) = index.prompts.iter().find(|p| p.id == includer) {
+                    included_by.push(meta.clone());
+                }
+            }
+        }
+    }
+
+    Ok(PromptDependencies {
+        id: prompt.meta.id,
+        includes,
+        unresolved,
+        included_by,
+    })
+}
+
+/// Add a Markdown or text file from disk as a new prompt in the root folder,
+/// named after the file.
+pub fn import_file(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    path: &Path,
+) -> AppResult<PromptMetadata> {
+    let size = fs::metadata(path)
+        .map_err(|e| format!("Could not read {:?}: {}", path, e))?
+        .len();
+    if size > MAX_IMPORT_BYTES {
+        return Err(format!("{:?} is too large to import", path));
+    }
+    let content =
+        fs::read_to_string(path).map_err(|e| format!("Could not read {:?}: {}", path, e))?;
+
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.trim().is_empty())
+        .map(str::to_string)
+        .unwrap_or_else(|| derive_name(&content));
+
+    let prompt = Prompt {
+        meta: PromptMetadata {
+            id: String::new(), // save_prompt will generate UUID
+            name,
+            folder: String::new(),
+            description: String::new(),
+            filename: String::new(),
+            use_count: 0,
+            last_used: None,
+            created: String::new(),
+            updated: String::new(),
+            icon: None,
+            color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
+        },
+        content,
+    };
+    save_prompt(paths, index, prompt)
+}
+
 /// Save a prompt: create or update. Write .md first, then update index.
 /// Returns the saved metadata.
 pub fn save_prompt(
@@ -132,6 +456,7 @@ pub fn save_prompt(
         existing.description = prompt.meta.description.clone();
         existing.icon = prompt.meta.icon.clone();
         existing.color = prompt.meta.color.clone();
+        existing.tags = normalize_tags(&prompt.meta.tags);
         existing.updated = now;
 
         return Ok(existing.clone());
@@ -176,6 +501,10 @@ pub fn save_prompt(
         updated: now,
         icon: prompt.meta.icon,
         color: prompt.meta.color,
+        pinned: false,
+        sort_index: None,
+        tags: normalize_tags(&prompt.meta.tags),
+        extra: prompt.meta.extra,
     };
 
     index.prompts.push(meta.clone());
@@ -201,6 +530,62 @@ pub fn delete_prompt(paths: &StoragePaths, index: &mut PromptIndex, id: &str) ->
     Ok(())
 }
 
+/// Copy a prompt into `target_folder`, or next to the original if `None`.
+/// A copy in the same folder is named "<name> (copy)"; usage starts from zero.
+pub fn duplicate_prompt(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    id: &str,
+    target_folder: Option<&str>,
+) -> AppResult<PromptMetadata> {
+    let original = load_prompt(paths, index, id)?;
+    let folder = match target_folder {
+        Some(folder) => folder_service::normalize_path(folder)?,
+        None => original.meta.folder.clone(),
+    };
+    let name = if folder == original.meta.folder {
+        format!("{} (copy)", original.meta.name)
+    } else {
+        original.meta.name.clone()
+    };
+
+    let copy = Prompt {
+        meta: PromptMetadata {
+            id: String::new(), // save_prompt will generate UUID
+            name,
+            folder,
+            filename: String::new(),
+            use_count: 0,
+            last_used: None,
+            pinned: false,
+            sort_index: None,
+            ..original.meta
+        },
+        content: original.content,
+    };
+    save_prompt(paths, index, copy)
+}
+
+/// Trim tags, drop a leading `#` and empty or repeated ones (ignoring case),
+/// keeping the first spelling of each.
+pub fn normalize_tags(tags: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .map(|tag| tag.trim().trim_start_matches('#').trim())
+        .filter(|tag| !tag.is_empty() && seen.insert(tag.to_lowercase()))
+        .map(str::to_string)
+        .collect()
+}
+
+/// A stable hash of `bytes`, as hex. FNV-1a, which unlike the standard
+/// library's hasher gives the same result across Rust versions.
+pub fn content_hash(bytes: &[u8]) -> String {
+    let hash = bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
 pub fn create_folder(paths: &StoragePaths, name: &str) -> AppResult<()> {
     let folder_path = if name.is_empty() {
         paths.prompts_dir.clone()
@@ -210,26 +595,39 @@ pub fn create_folder(paths: &StoragePaths, name: &str) -> AppResult<()> {
     fs::create_dir_all(folder_path).map_err(map_err)
 }
 
+/// Rename a folder, or move it anywhere by giving a new full path. Subfolders
+/// and every prompt below it move along.
 pub fn rename_folder(
     paths: &StoragePaths,
     index: &mut PromptIndex,
     old_name: &str,
     new_name: &str,
 ) -> AppResult<()> {
+    let old_name = folder_service::normalize_path(old_name)?;
+    let new_name = folder_service::normalize_path(new_name)?;
     if old_name == new_name {
         return Ok(());
     }
 
+    if old_name.is_empty() {
+        return Err("Cannot rename the root folder".to_string());
+    }
     if new_name.is_empty() {
         return Err("New folder name cannot be empty".to_string());
     }
+    if folder_service::is_within(&new_name, &old_name) {
+        return Err(format!("Cannot move folder '{}' into itself", old_name));
+    }
 
-    let old_path = paths.prompts_dir.join(old_name);
-    let new_path = paths.prompts_dir.join(new_name);
+    let old_path = paths.prompts_dir.join(&old_name);
+    let new_path = paths.prompts_dir.join(&new_name);
 
+    if new_path.exists() || folder_service::all_folders(index).contains(&new_name) {
+        return Err(format!("Folder '{}' already exists", new_name));
+    }
     if old_path.exists() {
-        if new_path.exists() {
-            return Err(format!("Folder '{}' already exists", new_name));
+        if let Some(parent) = new_path.parent() {
+            fs::create_dir_all(parent).map_err(map_err)?;
         }
         fs::rename(&old_path, &new_path).map_err(map_err)?;
     } else {
@@ -237,68 +635,225 @@ pub fn rename_folder(
         fs::create_dir_all(&new_path).map_err(map_err)?;
     }
 
+    let now = chrono::Utc::now().to_rfc3339();
     for prompt in &mut index.prompts {
-        if prompt.folder == old_name {
-            prompt.folder = new_name.to_string();
-            prompt.updated = chrono::Utc::now().to_rfc3339();
+        if let Some(folder) = folder_service::reparent(&prompt.folder, &old_name, &new_name) {
+            prompt.folder = folder;
+            prompt.updated = now.clone();
         }
     }
 
-    if let Some(pos) = index.folders.iter().position(|folder| folder == old_name) {
-        index.folders[pos] = new_name.to_string();
+    for folder in &mut index.folders {
+        if let Some(moved) = folder_service::reparent(folder, &old_name, &new_name) {
+            *folder = moved;
+        }
     }
 
     if let Some(ref mut meta) = index.folder_meta {
-        if let Some(folder_meta) = meta.remove(old_name) {
-            let mut updated = folder_meta;
-            updated.name = new_name.to_string();
-            meta.insert(new_name.to_string(), updated);
-        }
+        *meta = meta
+            .drain()
+            .map(|(key, mut folder_meta)| {
+                match folder_service::reparent(&key, &old_name, &new_name) {
+                    Some(moved) => {
+                        folder_meta.name = moved.clone();
+                        (moved, folder_meta)
+                    }
+                    None => (key, folder_meta),
+                }
+            })
+            .collect();
     }
 
     Ok(())
 }
 
-pub fn delete_folder(paths: &StoragePaths, index: &mut PromptIndex, name: &str) -> AppResult<()> {
+/// Move a folder, with everything in it, into `new_parent` ("" for the top level).
+pub fn move_folder(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    name: &str,
+    new_parent: &str,
+) -> AppResult<()> {
+    let name = folder_service::normalize_path(name)?;
+    let new_parent = folder_service::normalize_path(new_parent)?;
+    let target = folder_service::join(&new_parent, folder_service::leaf(&name));
+    rename_folder(paths, index, &name, &target)
+}
+
+/// Delete a folder. Depending on `mode` its prompts and subfolders either move
+/// up (to the top level or to the folder's parent) or are deleted with it.
+pub fn delete_folder(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    name: &str,
+    mode: FolderDeleteMode,
+) -> AppResult<()> {
+    let name = folder_service::normalize_path(name)?;
     if name.is_empty() {
         return Err("Cannot delete the root folder".to_string());
     }
 
+    match mode {
+        FolderDeleteMode::DeleteContents => {
+            let ids: Vec<String> = index
+                .prompts
+                .iter()
+                .filter(|p| folder_service::is_within(&p.folder, &name))
+                .map(|p| p.id.clone())
+                .collect();
+            for id in ids {
+                delete_prompt(paths, index, &id)?;
+            }
+        }
+        FolderDeleteMode::MoveToRoot | FolderDeleteMode::MoveToParent => {
+            let target = match mode {
+                FolderDeleteMode::MoveToParent => folder_service::parent(&name).to_string(),
+                _ => String::new(),
+            };
+            move_subfolders(paths, index, &name, &target)?;
+            move_prompts_out(paths, index, &name, &target)?;
+        }
+    }
+
+    remove_empty_dirs(&paths.prompts_dir.join(&name))?;
+
+    index
+        .folders
+        .retain(|folder| !folder_service::is_within(folder, &name));
+    if let Some(ref mut meta) = index.folder_meta {
+        meta.retain(|key, _| !folder_service::is_within(key, &name));
+    }
+
+    Ok(())
+}
+
+/// Move the direct subfolders of `name` into `target`. Checks every
+/// destination first so a clash doesn't leave the move half done.
+fn move_subfolders(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    name: &str,
+    target: &str,
+) -> AppResult<()> {
+    let known = folder_service::all_folders(index);
+    let moves: Vec<(String, String)> = known
+        .iter()
+        .filter(|folder| folder_service::parent(folder) == name)
+        .map(|folder| {
+            let dest = folder_service::join(target, folder_service::leaf(folder));
+            (folder.clone(), dest)
+        })
+        .collect();
+
+    for (_, dest) in &moves {
+        if known.contains(dest) || paths.prompts_dir.join(dest).exists() {
+            return Err(format!("Folder '{}' already exists", dest));
+        }
+    }
+    for (folder, dest) in moves {
+        rename_folder(paths, index, &folder, &dest)?;
+    }
+    Ok(())
+}
+
+/// Move the prompts directly in `name` into `target`, renaming files that would clash.
+fn move_prompts_out(
+    paths: &StoragePaths,
+    index: &mut PromptIndex,
+    name: &str,
+    target: &str,
+) -> AppResult<()> {
     for prompt in index.prompts.iter_mut().filter(|p| p.folder == name) {
         let old_path = get_prompt_path(paths, &prompt.folder, &prompt.filename);
-        let target_filename = if get_prompt_path(paths, "", &prompt.filename).exists() {
-            ensure_unique_filename(paths, "", &prompt.name)
+        let target_filename = if get_prompt_path(paths, target, &prompt.filename).exists() {
+            ensure_unique_filename(paths, target, &prompt.name)
         } else {
             prompt.filename.clone()
         };
 
-        let new_path = get_prompt_path(paths, "", &target_filename);
+        let new_path = get_prompt_path(paths, target, &target_filename);
         if old_path.exists() {
+            if let Some(parent) = new_path.parent() {
+                fs::create_dir_all(parent).map_err(map_err)?;
+            }
             fs::rename(&old_path, &new_path).map_err(map_err)?;
         }
 
-        prompt.folder = String::new();
+        prompt.folder = target.to_string();
         prompt.filename = target_filename;
         prompt.updated = chrono::Utc::now().to_rfc3339();
     }
+    Ok(())
+}
 
-    let folder_path = paths.prompts_dir.join(name);
-    if folder_path.exists() {
-        let is_empty = fs::read_dir(&folder_path)
-            .map_err(map_err)?
-            .next()
-            .is_none();
-
-        if is_empty {
-            fs::remove_dir(&folder_path).map_err(map_err)?;
+/// Remove `path` and the directories below it, bottom up, as far as they are
+/// empty once their folder metadata sidecars are gone. Other files are never deleted.
+fn remove_empty_dirs(path: &Path) -> AppResult<()> {
+    if !path.is_dir() {
+        return Ok(());
+    }
+    let sidecar = path.join(folder_service::FOLDER_META_FILE);
+    if sidecar.exists() {
+        fs::remove_file(&sidecar).map_err(map_err)?;
+    }
+    for entry in fs::read_dir(path).map_err(map_err)? {
+        let entry = entry.map_err(map_err)?;
+        if entry.file_type().map_err(map_err)?.is_dir() {
+            remove_empty_dirs(&entry.path())?;
         }
     }
+    let is_empty = fs::read_dir(path).map_err(map_err)?.next().is_none();
+    if is_empty {
+        fs::remove_dir(path).map_err(map_err)?;
+    }
+    Ok(())
+}
 
-    index.folders.retain(|folder| folder != name);
-    if let Some(ref mut meta) = index.folder_meta {
-        meta.remove(name);
+/// Pin or unpin a prompt.
+pub fn set_pinned(index: &mut PromptIndex, id: &str, pinned: bool) -> AppResult<PromptMetadata> {
+    let meta = index
+        .prompts
+        .iter_mut()
+        .find(|p| p.id == id)
+        .ok_or_else(|| format!("Prompt '{}' not found", id))?;
+    meta.pinned = pinned;
+    Ok(meta.clone())
+}
+
+/// Put `ids` in this order among themselves. They take over the positions they
+/// already hold, and those without one go after every ordered prompt, so
+/// prompts not listed, e.g. in other folders, keep their place. Nothing
+/// changes if an id is unknown or repeated.
+pub fn reorder_prompts(index: &mut PromptIndex, ids: &[String]) -> AppResult<()> {
+    let mut seen = HashSet::new();
+    let mut positions: Vec<u32> = Vec::with_capacity(ids.len());
+    let mut unordered = 0;
+    for id in ids {
+        if !seen.insert(id.as_str()) {
+            return Err(format!("Prompt '{}' is listed twice", id));
+        }
+        match index.prompts.iter().find(|p| &p.id == id) {
+            Some(prompt) => match prompt.sort_index {
+                Some(position) => positions.push(position),
+                None => unordered += 1,
+            },
+            None => return Err(format!("Prompt '{}' not found", id)),
+        }
     }
 
+    let next = index
+        .prompts
+        .iter()
+        .filter_map(|p| p.sort_index)
+        .max()
+        .map_or(0, |last| last + 1);
+    positions.extend(next..next + unordered);
+    positions.sort_unstable();
+    for (id, position) in ids.iter().zip(positions) {
+        if let Some(prompt) = index.prompts.iter_mut().find(|p| &p.id == id) {
+            prompt.sort_index = Some(position);
+        }
+    }
     Ok(())
 }
 
@@ -314,3 +869,253 @@ pub fn record_usage(index: &mut PromptIndex, id: &str) -> AppResult<()> {
     meta.last_used = Some(chrono::Utc::now().to_rfc3339());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::index_service;
+    use crate::test_support::{add_prompt, temp_paths, TempLibrary};
+
+    #[test]
+    fn pasting_without_values_keeps_the_template_and_expands_includes() {
+        let paths = temp_paths("paste-raw");
+        add_prompt(&paths, "parts", "Sign off", "Thanks, {{name}}");
+        let id = add_prompt(
+            &paths,
+            "",
+            "Reply",
+            "{{#if urgent}}URGENT {{/if}}{{#each items}}- {{this}}\n{{/each}}{{> parts/Sign off}}",
+        );
+        let index = index_service::load_index(&paths).unwrap();
+
+        let content = paste_content(&paths, &index, &id, &HashMap::new()).unwrap();
+        assert_eq!(
+            content,
+            "{{#if urgent}}URGENT {{/if}}{{#each items}}- {{this}}\n{{/each}}Thanks, {{name}}"
+        );
+    }
+
+    #[test]
+    fn pasting_with_values_renders_them() {
+        let paths = temp_paths("paste-values");
+        let content = "{{#if urgent}}URGENT {{/if}}Hi {{name}}";
+        let id = add_prompt(&paths, "", "Reply", content);
+        let index = index_service::load_index(&paths).unwrap();
+
+        let values = HashMap::from([
+            ("urgent".to_string(), "yes".to_string()),
+            ("name".to_string(), "Ada".to_string()),
+        ]);
+        let content = paste_content(&paths, &index, &id, &values).unwrap();
+        assert_eq!(content, "URGENT Hi Ada");
+    }
+
+    #[test]
+    fn a_template_that_does_not_parse_is_pasted_as_written() {
+        let paths = temp_paths("paste-broken");
+        let written = "{{#if open}} is never closed, {{name}}";
+        let id = add_prompt(&paths, "", "Notes", written);
+        let index = index_service::load_index(&paths).unwrap();
+
+        let values = HashMap::from([("name".to_string(), "Ada".to_string())]);
+        let content = paste_content(&paths, &index, &id, &values).unwrap();
+        assert_eq!(content, written);
+        assert!(render_prompt(&paths, &index, &id, &values).is_err());
+    }
+
+    fn positions(index: &PromptIndex, ids: &[&str]) -> Vec<Option<u32>> {
+        ids.iter()
+            .map(|id| {
+                index
+                    .prompts
+                    .iter()
+                    .find(|p| p.id == *id)
+                    .unwrap()
+                    .sort_index
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reordering_one_folder_keeps_the_order_of_the_others() {
+        let paths = temp_paths("reorder");
+        let a1 = add_prompt(&paths, "a", "One", "1");
+        let a2 = add_prompt(&paths, "a", "Two", "2");
+        let b1 = add_prompt(&paths, "b", "Three", "3");
+        let b2 = add_prompt(&paths, "b", "Four", "4");
+        let loose = add_prompt(&paths, "b", "Five", "5");
+        let mut index = index_service::load_index(&paths).unwrap();
+
+        let ids = |ids: &[&String]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        reorder_prompts(&mut index, &ids(&[&a1, &a2, &b1, &b2])).unwrap();
+        reorder_prompts(&mut index, &ids(&[&a2, &a1])).unwrap();
+        assert_eq!(
+            positions(&index, &[&a2, &a1, &b1, &b2, &loose]),
+            [Some(0), Some(1), Some(2), Some(3), None]
+        );
+
+        // Prompts without a place are put after every ordered one
+        reorder_prompts(&mut index, &ids(&[&loose, &b2, &b1])).unwrap();
+        assert_eq!(
+            positions(&index, &[&a2, &a1, &loose, &b2, &b1]),
+            [Some(0), Some(1), Some(2), Some(3), Some(4)]
+        );
+    }
+
+    #[test]
+    fn reordering_rejects_unknown_and_repeated_ids() {
+        let paths = temp_paths("reorder-invalid");
+        let a = add_prompt(&paths, "", "A", "a");
+        let mut index = index_service::load_index(&paths).unwrap();
+
+        let repeated = reorder_prompts(&mut index, &[a.clone(), a.clone()]);
+        assert_eq!(
+            repeated.unwrap_err(),
+            format!("Prompt '{}' is listed twice", a)
+        );
+        assert!(reorder_prompts(&mut index, &[a.clone(), "missing".to_string()]).is_err());
+        assert_eq!(positions(&index, &[&a]), [None]);
+    }
+
+    /// Folder of each prompt, by name.
+    fn folders(index: &PromptIndex) -> HashMap<&str, &str> {
+        index
+            .prompts
+            .iter()
+            .map(|p| (p.name.as_str(), p.folder.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn renaming_a_folder_moves_everything_below_it() {
+        let paths = temp_paths("rename-folder");
+        add_prompt(&paths, "a", "Top", "1");
+        add_prompt(&paths, "a/b", "Middle", "2");
+        add_prompt(&paths, "a/b/c", "Bottom", "3");
+        add_prompt(&paths, "ab", "Sibling", "4");
+        let mut index = index_service::load_index(&paths).unwrap();
+        index.folders.push("a/b/empty".to_string());
+
+        rename_folder(&paths, &mut index, "a", "x/y").unwrap();
+        let moved = folders(&index);
+        assert_eq!(moved["Top"], "x/y");
+        assert_eq!(moved["Middle"], "x/y/b");
+        assert_eq!(moved["Bottom"], "x/y/b/c");
+        assert_eq!(moved["Sibling"], "ab");
+        assert!(index.folders.contains(&"x/y/b/empty".to_string()));
+        assert!(!paths.prompts_dir.join("a").exists());
+        for prompt in &index.prompts {
+            assert!(get_prompt_path(&paths, &prompt.folder, &prompt.filename).exists());
+        }
+    }
+
+    #[test]
+    fn folders_cannot_move_into_themselves_or_onto_others() {
+        let paths = temp_paths("move-folder");
+        add_prompt(&paths, "a/b", "Inner", "1");
+        add_prompt(&paths, "c", "Other", "2");
+        let mut index = index_service::load_index(&paths).unwrap();
+
+        assert!(move_folder(&paths, &mut index, "a", "a/b").is_err());
+        assert!(move_folder(&paths, &mut index, "a", "a").is_err());
+        assert!(rename_folder(&paths, &mut index, "a", "a/b/c").is_err());
+        assert!(rename_folder(&paths, &mut index, "a", "c").is_err());
+        assert!(rename_folder(&paths, &mut index, "", "z").is_err());
+        assert_eq!(folders(&index)["Inner"], "a/b");
+
+        move_folder(&paths, &mut index, "a/b", "c").unwrap();
+        assert_eq!(folders(&index)["Inner"], "c/b");
+        move_folder(&paths, &mut index, "c/b", "").unwrap();
+        assert_eq!(folders(&index)["Inner"], "b");
+    }
+
+    /// A library with prompts directly in `p/f` and in its subfolder.
+    fn folder_to_delete(name: &str) -> (TempLibrary, PromptIndex) {
+        let paths = temp_paths(name);
+        add_prompt(&paths, "p/f", "Direct", "1");
+        add_prompt(&paths, "p/f/sub", "Nested", "2");
+        add_prompt(&paths, "p", "Outside", "3");
+        let index = index_service::load_index(&paths).unwrap();
+        (paths, index)
+    }
+
+    #[test]
+    fn deleting_a_folder_can_move_its_contents_up() {
+        let (paths, mut index) = folder_to_delete("delete-folder-parent");
+        delete_folder(&paths, &mut index, "p/f", FolderDeleteMode::MoveToParent).unwrap();
+        let moved = folders(&index);
+        assert_eq!(moved["Direct"], "p");
+        assert_eq!(moved["Nested"], "p/sub");
+        assert_eq!(moved["Outside"], "p");
+        assert!(!paths.prompts_dir.join("p/f").exists());
+
+        let (paths, mut index) = folder_to_delete("delete-folder-root");
+        delete_folder(&paths, &mut index, "p/f", FolderDeleteMode::MoveToRoot).unwrap();
+        let moved = folders(&index);
+        assert_eq!(moved["Direct"], "");
+        assert_eq!(moved["Nested"], "sub");
+        assert!(index
+            .prompts
+            .iter()
+            .all(|p| get_prompt_path(&paths, &p.folder, &p.filename).exists()));
+    }
+
+    #[test]
+    fn deleting_a_folder_with_its_contents() {
+        let (paths, mut index) = folder_to_delete("delete-folder-contents");
+        fs::write(paths.prompts_dir.join("p/f/sub/notes.txt"), "keep me").unwrap();
+
+        delete_folder(&paths, &mut index, "p/f", FolderDeleteMode::DeleteContents).unwrap();
+        assert_eq!(folders(&index).into_keys().collect::<Vec<_>>(), ["Outside"]);
+        // Files that aren't prompts are never deleted
+        assert!(paths.prompts_dir.join("p/f/sub/notes.txt").exists());
+        assert!(!paths.prompts_dir.join("p/f").join("Direct.md").exists());
+        assert!(delete_folder(&paths, &mut index, "", FolderDeleteMode::DeleteContents).is_err());
+    }
+
+    #[test]
+    fn a_clash_when_moving_contents_up_changes_nothing() {
+        let (paths, mut index) = folder_to_delete("delete-folder-clash");
+        add_prompt(&paths, "sub", "Taken", "4");
+        let mut index_with_clash = index_service::load_index(&paths).unwrap();
+        assert!(delete_folder(
+            &paths,
+            &mut index_with_clash,
+            "p/f",
+            FolderDeleteMode::MoveToRoot
+        )
+        .is_err());
+        assert_eq!(folders(&index_with_clash)["Nested"], "p/f/sub");
+        assert_eq!(folders(&index_with_clash)["Direct"], "p/f");
+        assert!(paths.prompts_dir.join("p/f/sub").exists());
+
+        // Moving up to the parent doesn't clash
+        delete_folder(&paths, &mut index, "p/f", FolderDeleteMode::MoveToParent).unwrap();
+    }
+
+    #[test]
+    fn cached_bodies_are_read_again_only_when_their_file_changes() {
+        let paths = temp_paths("body-cache");
+        let kept = add_prompt(&paths, "", "Kept", "Unchanged");
+        let edited = add_prompt(&paths, "", "Edited", "Before");
+        let removed = add_prompt(&paths, "", "Removed", "Gone soon");
+        let mut cache = BodyCache::default();
+        let index = index_service::load_index(&paths).unwrap();
+        assert_eq!(cache.refresh(&paths, &index).len(), 3);
+
+        // Only a body read again from disk would lose this
+        cache.bodies.insert(kept.clone(), "From the cache".to_string());
+        let meta = index.prompts.iter().find(|p| p.id == edited).unwrap();
+        fs::write(get_prompt_path(&paths, "", &meta.filename), "After the edit").unwrap();
+        let index = index_service::update_index(&paths, |index| {
+            delete_prompt(&paths, index, &removed)?;
+            Ok(index.clone())
+        })
+        .unwrap();
+
+        let bodies = cache.refresh(&paths, &index);
+        assert_eq!(bodies[&kept], "From the cache");
+        assert_eq!(bodies[&edited], "After the edit");
+        assert!(!bodies.contains_key(&removed));
+    }
+}
@@ -1,54 +1,650 @@
-use crate::models::prompt::PromptMetadata;
+use crate::error::AppResult;
+use crate::models::prompt::{
+    PromptIndex, PromptMetadata, SavedSearch, SavedSearchSort, UsageFilter,
+};
+use crate::models::search::{FieldMatch, MatchRange, SearchField, SearchHit};
+use crate::models::settings::SearchSettings;
+use crate::models::usage::UsageStore;
+use crate::services::{folder_service, frecency_service, fuzzy_service, prompt_service};
+use chrono::{DateTime, Duration, Utc};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ops::Range;
 
-/// Search prompts. Empty query returns recency-sorted. Non-empty does fuzzy matching.
-pub fn search_prompts(prompts: &[PromptMetadata], query: &str) -> Vec<PromptMetadata> {
-    if query.trim().is_empty() {
-        return sort_by_recency(prompts);
+/// Everything besides the query that influences ranking.
+/// `now` is explicit so ranking is deterministic for a given clock.
+pub struct SearchContext<'a> {
+    pub usage: &'a UsageStore,
+    pub settings: &'a SearchSettings,
+    /// Application captured at hotkey time; prompts used there rank higher
+    pub target_app: Option<&'a str>,
+    pub now: DateTime<Utc>,
+    /// Prompt bodies by id, for `in:body` and snippets; see `Query::uses_bodies`
+    pub bodies: Option<&'a HashMap<String, String>>,
+    /// Cosine similarity of each prompt to the query text by id, when
+    /// semantic search is on
+    pub similarities: Option<&'a HashMap<String, f32>>,
+}
+
+/// A launcher query split into its parts. A prompt must satisfy every clause,
+/// and `text` is matched fuzzily.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    /// The plain words, in order
+    pub text: String,
+    pub clauses: Vec<Clause>,
+    /// `in:body`: text and phrases are also looked for in prompt bodies
+    pub in_body: bool,
+}
+
+impl Query {
+    /// Whether searching with this query wants prompt bodies: to match them or
+    /// to show where the words appear in them.
+    pub fn uses_bodies(&self) -> bool {
+        self.in_body
+            || !self.text.is_empty()
+            || self
+                .clauses
+                .iter()
+                .any(|clause| matches!(clause.filter, Filter::Phrase(_)))
+    }
+}
+
+/// A filter, or its opposite when written with a leading `-`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clause {
+    pub negated: bool,
+    pub filter: Filter,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    /// `"exact words"`, or a single `-word`; matched ignoring case
+    Phrase(String),
+    /// `folder:Writing`: the folder or any folder below it
+    Folder(String),
+    /// `tag:review`
+    Tag(String),
+    /// `used:>5`: use count
+    Used(Comparison),
+    /// `updated:<30d`: days since the last edit
+    Updated(Comparison),
+}
+
+/// `>5`, `>=5`, `<5`, `<=5`, `=5` or just `5`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Comparison {
+    pub op: CompareOp,
+    pub value: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    pub fn holds(&self, actual: u64) -> bool {
+        match self.op {
+            CompareOp::Less => actual < self.value,
+            CompareOp::LessOrEqual => actual <= self.value,
+            CompareOp::Equal => actual == self.value,
+            CompareOp::GreaterOrEqual => actual >= self.value,
+            CompareOp::Greater => actual > self.value,
+        }
+    }
+}
+
+/// Added to a pinned prompt's score: enough to settle near-ties, never enough
+/// to beat a clearly better text match.
+const PINNED_BOOST: f64 = 0.5;
+
+/// Search prompts with a query in the grammar of `parse_query`. Prompts failing
+/// a filter are dropped. Without plain words the rest come pinned first, then
+/// manually ordered ones, then by frecency. Plain words are matched fuzzily and
+/// blended with frecency, with a small boost for pinned prompts. Each hit says
+/// where it matched, with a body snippet if `ctx` has bodies.
+pub fn search_prompts(
+    prompts: &[PromptMetadata],
+    query: &str,
+    ctx: &SearchContext,
+) -> Vec<SearchHit> {
+    let query = parse_query(query);
+    let body = |p: &PromptMetadata| {
+        ctx.bodies
+            .filter(|_| query.in_body)
+            .and_then(|bodies| bodies.get(&p.id))
+            .map(|body| fuzzy_service::fold(body))
+    };
+    let candidates = prompts.iter().filter(|p| {
+        let body = body(p);
+        query
+            .clauses
+            .iter()
+            .all(|clause| clause_holds(clause, p, body.as_deref(), ctx.now))
+    });
+
+    let scored = if query.text.is_empty() {
+        sort_by_frecency(candidates, ctx)
+    } else {
+        let query_folded = fuzzy_service::fold(&query.text);
+        let mut scored: Vec<(f64, &PromptMetadata)> = candidates
+            .filter_map(|p| {
+                let score =
+                    compute_score(p, body(p).as_deref(), &query_folded) + semantic_score(p, ctx);
+                if score > 0.0 {
+                    Some((score + usage_boost(p, ctx) + pinned_boost(p), p))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        scored
+    };
+
+    scored
+        .into_iter()
+        .map(|(score, p)| {
+            let body = ctx.bodies.and_then(|bodies| bodies.get(&p.id));
+            build_hit(p, score, &query, body.map(String::as_str))
+        })
+        .collect()
+}
+
+/// Prompts matching a saved search's folder, tags and usage filters and its
+/// query, in its sort order. Ties keep the launcher's order.
+pub fn run_saved_search(
+    prompts: &[PromptMetadata],
+    search: &SavedSearch,
+    ctx: &SearchContext,
+) -> Vec<SearchHit> {
+    let candidates: Vec<PromptMetadata> = prompts
+        .iter()
+        .filter(|p| matches_saved_search(p, search, ctx.now))
+        .cloned()
+        .collect();
+    let mut results = search_prompts(&candidates, &search.query, ctx);
+
+    match search.sort {
+        SavedSearchSort::Relevance => {}
+        SavedSearchSort::RecentlyUsed => {
+            results.sort_by(|a, b| b.meta.last_used.cmp(&a.meta.last_used))
+        }
+        SavedSearchSort::MostUsed => results.sort_by_key(|hit| Reverse(hit.meta.use_count)),
+        SavedSearchSort::RecentlyUpdated => {
+            results.sort_by(|a, b| b.meta.updated.cmp(&a.meta.updated))
+        }
+        SavedSearchSort::Name => results.sort_by_key(|hit| hit.meta.name.to_lowercase()),
+    }
+    results
+}
+
+fn matches_saved_search(prompt: &PromptMetadata, search: &SavedSearch, now: DateTime<Utc>) -> bool {
+    let in_folder = folder_service::is_within(&prompt.folder, &search.folder_prefix);
+    let tagged = search.tags.iter().all(|tag| {
+        prompt
+            .tags
+            .iter()
+            .any(|t| t.to_lowercase() == tag.to_lowercase())
+    });
+    let used = match search.usage {
+        UsageFilter::Any => true,
+        UsageFilter::Never => prompt.last_used.is_none(),
+        UsageFilter::WithinDays { days } => {
+            // A window reaching back before the earliest date covers every use
+            let since = now.checked_sub_signed(Duration::days(days as i64));
+            prompt
+                .last_used
+                .as_deref()
+                .and_then(|used| DateTime::parse_from_rfc3339(used).ok())
+                .is_some_and(|used| since.is_none_or(|since| used >= since))
+        }
+    };
+    in_folder && tagged && used
+}
+
+/// Save `search`, replacing any saved search with the same name (ignoring case).
+pub fn create_saved_search(index: &mut PromptIndex, search: SavedSearch) -> AppResult<SavedSearch> {
+    let name = search.name.trim().to_string();
+    if name.is_empty() {
+        return Err("A saved search needs a name".to_string());
+    }
+    let search = SavedSearch {
+        query: search.query.trim().to_string(),
+        tags: prompt_service::normalize_tags(&search.tags),
+        folder_prefix: folder_service::normalize_path(&search.folder_prefix)?,
+        name,
+        ..search
+    };
+
+    match index
+        .saved_searches
+        .iter_mut()
+        .find(|s| s.name.to_lowercase() == search.name.to_lowercase())
+    {
+        Some(existing) => *existing = search.clone(),
+        None => index.saved_searches.push(search.clone()),
     }
+    Ok(search)
+}
 
-    let query_lower = query.to_lowercase();
-    let mut scored: Vec<(f64, &PromptMetadata)> = prompts
+pub fn find_saved_search<'a>(index: &'a PromptIndex, name: &str) -> AppResult<&'a SavedSearch> {
+    index
+        .saved_searches
         .iter()
-        .filter_map(|p| {
-            let score = compute_score(p, &query_lower);
-            if score > 0.0 {
-                Some((score, p))
+        .find(|s| s.name.to_lowercase() == name.trim().to_lowercase())
+        .ok_or_else(|| format!("Saved search '{}' not found", name))
+}
+
+pub fn delete_saved_search(index: &mut PromptIndex, name: &str) -> AppResult<()> {
+    let name = find_saved_search(index, name)?.name.clone();
+    index.saved_searches.retain(|s| s.name != name);
+    Ok(())
+}
+
+/// Parse a launcher query. Besides plain words it understands `folder:Writing`,
+/// `tag:review`, `used:>5`, `updated:<30d` (units `d`, `w`, `m`, `y`),
+/// `"quoted phrases"`, `in:body`, and `-` before a word, phrase or filter to
+/// exclude it. Values may be quoted, as in `folder:"Client work"`. Anything
+/// that doesn't parse as a filter is kept as a plain word.
+pub fn parse_query(query: &str) -> Query {
+    let mut parsed = Query::default();
+    let mut words = Vec::new();
+
+    for token in tokenize(query) {
+        if let Some(key) = &token.key {
+            if key.eq_ignore_ascii_case("in")
+                && token.value.eq_ignore_ascii_case("body")
+                && !token.negated
+            {
+                parsed.in_body = true;
+                continue;
+            }
+            if let Some(filter) = parse_filter(key, &token.value) {
+                parsed.clauses.push(Clause {
+                    negated: token.negated,
+                    filter,
+                });
+                continue;
+            }
+        }
+
+        let text = match &token.key {
+            Some(key) => format!("{}:{}", key, token.value),
+            None => token.value,
+        };
+        if text.trim().is_empty() {
+            if !token.quoted {
+                words.push(token.raw);
+            }
+        } else if token.negated || token.quoted {
+            parsed.clauses.push(Clause {
+                negated: token.negated,
+                filter: Filter::Phrase(text),
+            });
+        } else {
+            words.push(text);
+        }
+    }
+
+    parsed.text = words.join(" ");
+    parsed
+}
+
+/// One whitespace-separated part of a query, with its `-` and `key:` split off.
+struct Token {
+    negated: bool,
+    key: Option<String>,
+    value: String,
+    quoted: bool,
+    /// The token as typed
+    raw: String,
+}
+
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut raw = String::new();
+        let negated = chars.next_if_eq(&'-').is_some();
+        if negated {
+            raw.push('-');
+        }
+        let mut key = None;
+        let mut value = String::new();
+        let mut quoted = false;
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+            raw.push(c);
+            if c == '"' && value.is_empty() {
+                // A quoted value runs to the closing quote, or to the end if there is none
+                quoted = true;
+                for c in chars.by_ref() {
+                    raw.push(c);
+                    if c == '"' {
+                        break;
+                    }
+                    value.push(c);
+                }
+                break;
+            } else if c == ':' && key.is_none() && !value.is_empty() {
+                key = Some(std::mem::take(&mut value));
             } else {
-                None
+                value.push(c);
             }
+        }
+        tokens.push(Token {
+            negated,
+            key,
+            value,
+            quoted,
+            raw,
+        });
+    }
+    tokens
+}
+
+fn parse_filter(key: &str, value: &str) -> Option<Filter> {
+    match key.to_lowercase().as_str() {
+        "folder" => folder_service::normalize_path(value)
+            .ok()
+            .filter(|folder| !folder.is_empty())
+            .map(Filter::Folder),
+        "tag" => Some(value.trim().trim_start_matches('#'))
+            .filter(|tag| !tag.is_empty())
+            .map(|tag| Filter::Tag(tag.to_string())),
+        "used" => parse_comparison(value, |n| n.parse().ok()).map(Filter::Used),
+        "updated" => parse_comparison(value, parse_days).map(Filter::Updated),
+        _ => None,
+    }
+}
+
+fn parse_comparison(value: &str, number: impl Fn(&str) -> Option<u64>) -> Option<Comparison> {
+    let (op, rest) = [
+        ("<=", CompareOp::LessOrEqual),
+        (">=", CompareOp::GreaterOrEqual),
+        ("<", CompareOp::Less),
+        (">", CompareOp::Greater),
+        ("=", CompareOp::Equal),
+    ]
+    .into_iter()
+    .find_map(|(prefix, op)| value.strip_prefix(prefix).map(|rest| (op, rest)))
+    .unwrap_or((CompareOp::Equal, value));
+    Some(Comparison {
+        op,
+        value: number(rest)?,
+    })
+}
+
+/// `30d`, `2w`, `6m` or `1y` in days; a bare number is days.
+fn parse_days(value: &str) -> Option<u64> {
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let days_per_unit = match unit.to_lowercase().as_str() {
+        "" | "d" => 1,
+        "w" => 7,
+        "m" => 30,
+        "y" => 365,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(days_per_unit)
+}
+
+/// `body` is the prompt's folded body when the query searches bodies.
+fn clause_holds(
+    clause: &Clause,
+    prompt: &PromptMetadata,
+    body: Option<&str>,
+    now: DateTime<Utc>,
+) -> bool {
+    let holds = match &clause.filter {
+        Filter::Phrase(phrase) => {
+            let phrase = fuzzy_service::fold(phrase);
+            [&prompt.name, &prompt.description, &prompt.folder]
+                .into_iter()
+                .any(|field| fuzzy_service::fold(field).contains(&phrase))
+                || body.is_some_and(|body| body.contains(&phrase))
+        }
+        Filter::Folder(folder) => {
+            folder_service::is_within(&prompt.folder.to_lowercase(), &folder.to_lowercase())
+        }
+        Filter::Tag(tag) => prompt
+            .tags
+            .iter()
+            .any(|t| t.to_lowercase() == tag.to_lowercase()),
+        Filter::Used(comparison) => comparison.holds(prompt.use_count),
+        Filter::Updated(comparison) => DateTime::parse_from_rfc3339(&prompt.updated)
+            .ok()
+            .map(|updated| (now - updated.with_timezone(&Utc)).num_days().max(0) as u64)
+            .is_some_and(|age| comparison.holds(age)),
+    };
+    holds != clause.negated
+}
+
+/// Prompts with their frecency rank, pinned first, then manually ordered ones,
+/// then by rank.
+fn sort_by_frecency<'a>(
+    prompts: impl Iterator<Item = &'a PromptMetadata>,
+    ctx: &SearchContext,
+) -> Vec<(f64, &'a PromptMetadata)> {
+    let mut keyed: Vec<(f64, &PromptMetadata)> = prompts
+        .map(|p| {
+            let (frecency, app_frecency) = frecencies(p, ctx);
+            let rank = ctx.settings.frecency_weight * frecency
+                + ctx.settings.context_weight * app_frecency;
+            (rank, p)
         })
         .collect();
 
-    scored.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
-    scored.into_iter().map(|(_, p)| p.clone()).collect()
+    keyed.sort_by(|(a_rank, a), (b_rank, b)| {
+        b.pinned
+            .cmp(&a.pinned)
+            .then_with(|| match (a.sort_index, b.sort_index) {
+                (Some(a_index), Some(b_index)) => a_index.cmp(&b_index),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => std::cmp::Ordering::Equal,
+            })
+            .then_with(|| {
+                b_rank
+                    .partial_cmp(a_rank)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            // Then by last_used desc (Some > None), then by updated desc
+            .then_with(|| match (&b.last_used, &a.last_used) {
+                (Some(b_used), Some(a_used)) => b_used.cmp(a_used),
+                (Some(_), None) => std::cmp::Ordering::Less,
+                (None, Some(_)) => std::cmp::Ordering::Greater,
+                (None, None) => b.updated.cmp(&a.updated),
+            })
+    });
+    keyed
 }
 
-fn sort_by_recency(prompts: &[PromptMetadata]) -> Vec<PromptMetadata> {
-    let mut sorted: Vec<PromptMetadata> = prompts.to_vec();
-    sorted.sort_by(|a, b| {
-        // Sort by last_used desc (Some > None), then by updated desc
-        match (&b.last_used, &a.last_used) {
-            (Some(b_used), Some(a_used)) => b_used.cmp(a_used),
-            (Some(_), None) => std::cmp::Ordering::Less,
-            (None, Some(_)) => std::cmp::Ordering::Greater,
-            (None, None) => b.updated.cmp(&a.updated),
+/// Characters of the snippet kept before the body's best match
+const SNIPPET_LEAD: usize = 40;
+const SNIPPET_LENGTH: usize = 160;
+
+/// A result for `prompt`, with the ranges of its fields that matched the
+/// query's words and phrases. `body` is the prompt's unfolded body, if loaded.
+fn build_hit(prompt: &PromptMetadata, score: f64, query: &Query, body: Option<&str>) -> SearchHit {
+    let phrases: Vec<&str> = query
+        .clauses
+        .iter()
+        .filter(|clause| !clause.negated)
+        .filter_map(|clause| match &clause.filter {
+            Filter::Phrase(phrase) => Some(phrase.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let fields = [
+        (SearchField::Name, &prompt.name),
+        (SearchField::Description, &prompt.description),
+        (SearchField::Folder, &prompt.folder),
+    ];
+    let mut matches: Vec<FieldMatch> = fields
+        .into_iter()
+        .filter_map(|(field, text)| {
+            let mut ranges: Vec<Range<usize>> = phrases
+                .iter()
+                .flat_map(|phrase| fuzzy_service::find_all(text, phrase))
+                .collect();
+            if let Some(found) = fuzzy_service::fuzzy_match(text, &query.text) {
+                ranges.extend(found.positions.into_iter().map(|p| p..p + 1));
+            }
+            let ranges = merge_ranges(ranges);
+            (!ranges.is_empty()).then_some(FieldMatch { field, ranges })
+        })
+        .collect();
+
+    let snippet = body.map(|body| {
+        let terms: Vec<&str> = query.text.split_whitespace().chain(phrases).collect();
+        let (snippet, ranges) = body_snippet(body, &terms);
+        if !ranges.is_empty() {
+            matches.push(FieldMatch {
+                field: SearchField::Body,
+                ranges,
+            });
         }
+        snippet
     });
-    sorted
+
+    SearchHit {
+        meta: prompt.clone(),
+        score,
+        matches,
+        snippet,
+    }
+}
+
+/// About `SNIPPET_LENGTH` characters of `body` from a little before the first
+/// place a term appears, or from the start, on one line. Returns the snippet
+/// and where the terms appear in it.
+fn body_snippet(body: &str, terms: &[&str]) -> (String, Vec<MatchRange>) {
+    let chars: Vec<char> = body.chars().collect();
+    let found: Vec<Range<usize>> = terms
+        .iter()
+        .flat_map(|term| fuzzy_service::find_all(body, term))
+        .collect();
+
+    let first = found.iter().map(|range| range.start).min().unwrap_or(0);
+    let mut start = first.saturating_sub(SNIPPET_LEAD);
+    // Start on a word rather than in the middle of one
+    if start > 0 {
+        if let Some(space) = chars[start..first].iter().position(|c| c.is_whitespace()) {
+            start += space + 1;
+        }
+    }
+    let end = (start + SNIPPET_LENGTH).min(chars.len());
+
+    // Whitespace becomes single spaces so offsets in the snippet stay put
+    let lead = usize::from(start > 0);
+    let mut snippet: String = "…".repeat(lead);
+    snippet.extend(
+        chars[start..end]
+            .iter()
+            .map(|&c| if c.is_whitespace() { ' ' } else { c }),
+    );
+    if end < chars.len() {
+        snippet.push('…');
+    }
+
+    let ranges = found
+        .into_iter()
+        .filter(|range| range.start >= start && range.end <= end)
+        .map(|range| range.start - start + lead..range.end - start + lead)
+        .collect();
+    (snippet, merge_ranges(ranges))
+}
+
+/// Sort ranges and join those that overlap or touch.
+fn merge_ranges(mut ranges: Vec<Range<usize>>) -> Vec<MatchRange> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<MatchRange> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(MatchRange {
+                start: range.start,
+                end: range.end,
+            }),
+        }
+    }
+    merged
+}
+
+/// Overall frecency and frecency inside the target app.
+fn frecencies(prompt: &PromptMetadata, ctx: &SearchContext) -> (f64, f64) {
+    let overall = frecency_service::frecency(ctx.usage, &prompt.id, None, ctx.settings, ctx.now);
+    let in_app = match ctx.target_app {
+        Some(app) => {
+            frecency_service::frecency(ctx.usage, &prompt.id, Some(app), ctx.settings, ctx.now)
+        }
+        None => 0.0,
+    };
+    (overall, in_app)
+}
+
+/// Each signal saturates below its weight so usage never outweighs a clearly better text match.
+fn usage_boost(prompt: &PromptMetadata, ctx: &SearchContext) -> f64 {
+    let (frecency, app_frecency) = frecencies(prompt, ctx);
+    ctx.settings.frecency_weight * saturate(frecency)
+        + ctx.settings.context_weight * saturate(app_frecency)
+}
+
+/// Similarity to the query, rescaled so the minimum similarity scores 0 and
+/// an identical meaning scores the full weight.
+fn semantic_score(prompt: &PromptMetadata, ctx: &SearchContext) -> f64 {
+    let min = ctx.settings.semantic_min_similarity;
+    match ctx.similarities.and_then(|s| s.get(&prompt.id)) {
+        Some(similarity) if f64::from(*similarity) > min && min < 1.0 => {
+            ctx.settings.semantic_weight * (f64::from(*similarity) - min) / (1.0 - min)
+        }
+        _ => 0.0,
+    }
+}
+
+fn pinned_boost(prompt: &PromptMetadata) -> f64 {
+    if prompt.pinned {
+        PINNED_BOOST
+    } else {
+        0.0
+    }
+}
+
+fn saturate(value: f64) -> f64 {
+    value / (value + 1.0)
 }
 
-fn compute_score(prompt: &PromptMetadata, query: &str) -> f64 {
-    let name_score = fuzzy_score(&prompt.name.to_lowercase(), query) * 3.0;
-    let desc_score = fuzzy_score(&prompt.description.to_lowercase(), query) * 2.0;
-    let folder_score = fuzzy_score(&prompt.folder.to_lowercase(), query) * 1.0;
+/// `query` and `body`, the prompt's body when the query searches bodies, are
+/// folded. Bodies are too long for a fuzzy match to mean much, so they must
+/// contain every word.
+fn compute_score(prompt: &PromptMetadata, body: Option<&str>, query: &str) -> f64 {
+    let name_score = fuzzy_score(&prompt.name, query) * 3.0;
+    let desc_score = fuzzy_score(&prompt.description, query) * 2.0;
+    let folder_score = fuzzy_score(&prompt.folder, query) * 1.0;
+    let in_body = body.is_some_and(|body| query.split_whitespace().all(|w| body.contains(w)));
+    let body_score = if in_body { 1.0 } else { 0.0 };
 
-    let max = name_score.max(desc_score).max(folder_score);
+    let max = name_score.max(desc_score).max(folder_score).max(body_score);
     if max <= 0.0 {
         return 0.0;
     }
 
     // Bonus for starts-with match on name
-    let starts_bonus = if prompt.name.to_lowercase().starts_with(query) {
+    let starts_bonus = if fuzzy_service::fold(&prompt.name).starts_with(query) {
         10.0
     } else {
         0.0
@@ -57,45 +653,383 @@ fn compute_score(prompt: &PromptMetadata, query: &str) -> f64 {
     max + starts_bonus
 }
 
-/// Simple subsequence fuzzy scoring. Returns 0.0 if no match.
-/// Higher score = better match (consecutive matches and position matter).
+/// Score of the best fuzzy match of `query` in `text`, or 0.0 if there is none.
 fn fuzzy_score(text: &str, query: &str) -> f64 {
-    let text_chars: Vec<char> = text.chars().collect();
-    let query_chars: Vec<char> = query.chars().collect();
+    fuzzy_service::fuzzy_match(text, query).map_or(0.0, |m| m.score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::usage::UsageEvent;
+    use crate::test_support::metadata;
+    use chrono::TimeZone;
+    use serde_json::json;
 
-    if query_chars.is_empty() {
-        return 1.0;
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
     }
-    if query_chars.len() > text_chars.len() {
-        return 0.0;
+
+    fn prompt(id: &str, name: &str) -> PromptMetadata {
+        metadata(id, json!({ "name": name, "updated": now().to_rfc3339() }))
     }
 
-    let mut score = 0.0;
-    let mut qi = 0;
-    let mut prev_match_idx: Option<usize> = None;
+    fn ids(hits: &[SearchHit]) -> Vec<&str> {
+        hits.iter().map(|hit| hit.meta.id.as_str()).collect()
+    }
 
-    for (ti, &tc) in text_chars.iter().enumerate() {
-        if qi < query_chars.len() && tc == query_chars[qi] {
-            score += 1.0;
+    fn use_prompt(usage: &mut UsageStore, id: &str, app: Option<&str>, days_ago: i64) {
+        usage
+            .events
+            .entry(id.to_string())
+            .or_default()
+            .push(UsageEvent {
+                timestamp: (now() - Duration::days(days_ago)).to_rfc3339(),
+                app: app.map(str::to_lowercase),
+            });
+    }
 
-            // Consecutive match bonus
-            if let Some(prev) = prev_match_idx {
-                if ti == prev + 1 {
-                    score += 2.0;
-                }
-            }
+    fn search(
+        prompts: &[PromptMetadata],
+        query: &str,
+        usage: &UsageStore,
+        target_app: Option<&str>,
+    ) -> Vec<SearchHit> {
+        let settings = SearchSettings::default();
+        let ctx = SearchContext {
+            usage,
+            settings: &settings,
+            target_app,
+            now: now(),
+            bodies: None,
+            similarities: None,
+        };
+        search_prompts(prompts, query, &ctx)
+    }
+
+    #[test]
+    fn recent_uses_outrank_old_ones() {
+        let prompts = vec![prompt("old", "Email reply"), prompt("new", "Email draft")];
+        let mut usage = UsageStore::default();
+        for _ in 0..3 {
+            use_prompt(&mut usage, "old", None, 120);
+        }
+        use_prompt(&mut usage, "new", None, 1);
+
+        assert_eq!(
+            ids(&search(&prompts, "email", &usage, None)),
+            ["new", "old"]
+        );
+        assert_eq!(ids(&search(&prompts, "", &usage, None)), ["new", "old"]);
+    }
+
+    #[test]
+    fn uses_in_the_target_app_outrank_uses_elsewhere() {
+        let prompts = vec![prompt("chat", "Email reply"), prompt("code", "Email draft")];
+        let mut usage = UsageStore::default();
+        use_prompt(&mut usage, "chat", Some("slack.exe"), 0);
+        use_prompt(&mut usage, "chat", Some("slack.exe"), 0);
+        use_prompt(&mut usage, "code", Some("code.exe"), 0);
+
+        let in_code = search(&prompts, "email", &usage, Some("Code.exe"));
+        assert_eq!(ids(&in_code), ["code", "chat"]);
+        let anywhere = search(&prompts, "email", &usage, None);
+        assert_eq!(ids(&anywhere), ["chat", "code"]);
+    }
+
+    #[test]
+    fn usage_never_outweighs_a_clearly_better_match() {
+        let prompts = vec![
+            prompt("exact", "Summarize"),
+            prompt("loose", "Meeting summary"),
+        ];
+        let mut usage = UsageStore::default();
+        for _ in 0..50 {
+            use_prompt(&mut usage, "loose", Some("code.exe"), 0);
+        }
 
-            // Position bonus (earlier matches score higher)
-            score += 1.0 / (ti as f64 + 1.0);
+        let hits = search(&prompts, "summ", &usage, Some("code.exe"));
+        assert_eq!(ids(&hits), ["exact", "loose"]);
+    }
 
-            prev_match_idx = Some(ti);
-            qi += 1;
+    #[test]
+    fn pinned_prompts_come_first_in_their_own_order() {
+        let mut prompts = vec![
+            prompt("recent", "Recent"),
+            prompt("pinned", "Pinned"),
+            prompt("second", "Second"),
+            prompt("first", "First"),
+            prompt("ordered", "Ordered"),
+        ];
+        for (i, sort_index) in [(1, None), (2, Some(1)), (3, Some(0))] {
+            prompts[i].pinned = true;
+            prompts[i].sort_index = sort_index;
         }
+        prompts[4].sort_index = Some(0);
+        let mut usage = UsageStore::default();
+        use_prompt(&mut usage, "recent", None, 0);
+
+        assert_eq!(
+            ids(&search(&prompts, "", &usage, None)),
+            ["first", "second", "pinned", "ordered", "recent"]
+        );
     }
 
-    if qi == query_chars.len() {
-        score
-    } else {
-        0.0 // Not all query chars matched
+    #[test]
+    fn pinning_settles_near_ties_but_never_beats_a_clearly_better_match() {
+        let mut prompts = vec![
+            prompt("draft", "Email draft"),
+            prompt("reply", "Email reply"),
+            prompt("exact", "Summarize"),
+            prompt("loose", "Meeting summary"),
+        ];
+        prompts[1].pinned = true;
+        prompts[3].pinned = true;
+        let usage = UsageStore::default();
+
+        assert_eq!(
+            ids(&search(&prompts, "email", &usage, None)),
+            ["reply", "draft"]
+        );
+        assert_eq!(
+            ids(&search(&prompts, "summ", &usage, None)),
+            ["exact", "loose"]
+        );
+    }
+
+    #[test]
+    fn ranking_is_the_same_for_the_same_clock() {
+        let prompts: Vec<PromptMetadata> = (0..6)
+            .map(|i| prompt(&format!("p{}", i), &format!("Prompt {}", i)))
+            .collect();
+        let mut usage = UsageStore::default();
+        for (i, days_ago) in [40, 3, 12, 0, 90, 7].iter().enumerate() {
+            use_prompt(&mut usage, &format!("p{}", i), None, *days_ago);
+        }
+
+        let first = search(&prompts, "prompt", &usage, None);
+        let second = search(&prompts, "prompt", &usage, None);
+        assert_eq!(ids(&first), ["p3", "p1", "p5", "p2", "p0", "p4"]);
+        assert_eq!(ids(&first), ids(&second));
+    }
+
+    #[test]
+    fn saved_searches_take_any_number_of_days() {
+        let mut recent = prompt("recent", "Recent");
+        recent.last_used = Some((now() - Duration::days(3)).to_rfc3339());
+        let mut old = prompt("old", "Old");
+        old.last_used = Some("1970-01-01T00:00:00+00:00".to_string());
+        let never = prompt("never", "Never");
+        let within = |days: u32| {
+            let search = SavedSearch {
+                name: "Used".to_string(),
+                query: String::new(),
+                tags: Vec::new(),
+                folder_prefix: String::new(),
+                usage: UsageFilter::WithinDays { days },
+                sort: SavedSearchSort::default(),
+            };
+            [&recent, &old, &never]
+                .into_iter()
+                .filter(|p| matches_saved_search(p, &search, now()))
+                .map(|p| p.id.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(within(0), Vec::<&str>::new());
+        assert_eq!(within(7), ["recent"]);
+        assert_eq!(within(u32::MAX), ["recent", "old"]);
+    }
+
+    fn clause(negated: bool, filter: Filter) -> Clause {
+        Clause { negated, filter }
+    }
+
+    fn compare(op: CompareOp, value: u64) -> Comparison {
+        Comparison { op, value }
+    }
+
+    #[test]
+    fn queries_split_into_words_filters_and_phrases() {
+        let query = parse_query(
+            r#"email "code review" -draft used:>5 updated:<30d in:body TAG:#Work folder:/Writing/ reply"#,
+        );
+        assert_eq!(query.text, "email reply");
+        assert!(query.in_body);
+        assert_eq!(
+            query.clauses,
+            [
+                clause(false, Filter::Phrase("code review".to_string())),
+                clause(true, Filter::Phrase("draft".to_string())),
+                clause(false, Filter::Used(compare(CompareOp::Greater, 5))),
+                clause(false, Filter::Updated(compare(CompareOp::Less, 30))),
+                clause(false, Filter::Tag("Work".to_string())),
+                clause(false, Filter::Folder("Writing".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn quotes_and_exclusions_take_what_follows() {
+        let query = parse_query(r#"-"old draft" "unclosed phrase"#);
+        assert_eq!(query.text, "");
+        assert_eq!(
+            query.clauses,
+            [
+                clause(true, Filter::Phrase("old draft".to_string())),
+                clause(false, Filter::Phrase("unclosed phrase".to_string())),
+            ]
+        );
+
+        let query = parse_query(r#"a "" - -tag:x b"#);
+        assert_eq!(query.text, "a - b");
+        assert_eq!(query.clauses, [clause(true, Filter::Tag("x".to_string()))]);
+    }
+
+    #[test]
+    fn comparisons_and_durations_parse() {
+        let used = |value: &str| parse_comparison(value, |n| n.parse().ok());
+        assert_eq!(used(">=3"), Some(compare(CompareOp::GreaterOrEqual, 3)));
+        assert_eq!(used("<=3"), Some(compare(CompareOp::LessOrEqual, 3)));
+        assert_eq!(used("=3"), Some(compare(CompareOp::Equal, 3)));
+        assert_eq!(used("3"), Some(compare(CompareOp::Equal, 3)));
+        assert_eq!(used(">"), None);
+        assert_eq!(used("><3"), None);
+
+        assert_eq!(parse_days("10"), Some(10));
+        assert_eq!(parse_days("10D"), Some(10));
+        assert_eq!(parse_days("2w"), Some(14));
+        assert_eq!(parse_days("6m"), Some(180));
+        assert_eq!(parse_days("1y"), Some(365));
+        assert_eq!(parse_days("d"), None);
+        assert_eq!(parse_days("5h"), None);
+        assert_eq!(parse_days("99999999999999999999"), None);
+        assert_eq!(parse_days(&format!("{}y", u64::MAX / 2)), None);
+    }
+
+    #[test]
+    fn invalid_filters_are_searched_as_text() {
+        let query = parse_query("used:lots updated:5x folder: tag:# colour:red :x -in:body");
+        assert_eq!(
+            query.text,
+            "used:lots updated:5x folder: tag:# colour:red :x"
+        );
+        assert!(!query.in_body);
+        assert_eq!(
+            query.clauses,
+            [clause(true, Filter::Phrase("in:body".to_string()))]
+        );
+    }
+
+    #[test]
+    fn clauses_hold_for_the_right_prompts() {
+        let mut p = prompt("p", "Résumé builder");
+        p.folder = "Work/Letters".to_string();
+        p.description = "For job applications".to_string();
+        p.tags = vec!["Career".to_string()];
+        p.use_count = 5;
+        p.updated = (now() - Duration::days(10)).to_rfc3339();
+        let holds = |query: &str, body: Option<&str>| {
+            let query = parse_query(query);
+            query
+                .clauses
+                .iter()
+                .all(|clause| clause_holds(clause, &p, body, now()))
+        };
+
+        assert!(holds(r#""resume builder""#, None));
+        assert!(holds(r#""job app""#, None));
+        assert!(holds(r#""work/letters""#, None));
+        assert!(!holds(r#""cover letter""#, None));
+        assert!(holds(r#""cover letter""#, Some("write a cover letter")));
+        assert!(holds("-draft", None));
+        assert!(!holds("-builder", None));
+
+        assert!(holds("folder:work", None));
+        assert!(holds("folder:Work/Letters", None));
+        assert!(!holds("folder:Work/Let", None));
+        assert!(holds("-folder:Home", None));
+        assert!(holds("tag:career", None));
+        assert!(!holds("-tag:CAREER", None));
+
+        assert!(holds("used:5 used:>=5 used:<6", None));
+        assert!(!holds("used:>5", None));
+        assert!(holds("updated:<2w updated:>7", None));
+        assert!(!holds("updated:<1w", None));
+    }
+
+    /// The highlighted parts of `text`, sliced by character as the launcher does.
+    fn highlighted(text: &str, ranges: &[MatchRange]) -> Vec<String> {
+        let chars: Vec<char> = text.chars().collect();
+        ranges
+            .iter()
+            .map(|range| chars[range.start..range.end].iter().collect())
+            .collect()
+    }
+
+    #[test]
+    fn ranges_are_merged_when_they_overlap_or_touch() {
+        let merged = merge_ranges(vec![5..7, 0..2, 11..12, 6..9, 2..3]);
+        let spans: Vec<(usize, usize)> = merged.iter().map(|r| (r.start, r.end)).collect();
+        assert_eq!(spans, [(0, 3), (5, 9), (11, 12)]);
+        assert!(merge_ranges(Vec::new()).is_empty());
+    }
+
+    #[test]
+    fn snippets_start_on_a_word_shortly_before_the_first_match() {
+        let body = format!("{}needle in the haystack", "word ".repeat(20));
+        let (snippet, ranges) = body_snippet(&body, &["needle", "hay"]);
+        assert!(snippet.starts_with("…word "));
+        assert!(!snippet.ends_with('…'));
+        assert!(snippet.chars().count() <= SNIPPET_LEAD + 1 + "needle in the haystack".len());
+        // Offsets count the leading `…`
+        assert_eq!(highlighted(&snippet, &ranges), ["needle", "hay"]);
+
+        let (snippet, ranges) = body_snippet("No match\nhere", &["needle"]);
+        assert_eq!(snippet, "No match here");
+        assert!(ranges.is_empty());
+    }
+
+    #[test]
+    fn matches_cut_off_by_the_end_of_the_snippet_are_left_out() {
+        let body = format!("needle {} needle tail", "x".repeat(SNIPPET_LENGTH - 11));
+        let (snippet, ranges) = body_snippet(&body, &["needle"]);
+        assert_eq!(snippet.chars().count(), SNIPPET_LENGTH + 1);
+        assert!(snippet.ends_with("nee…"));
+        assert_eq!(highlighted(&snippet, &ranges), ["needle"]);
+    }
+
+    #[test]
+    fn offsets_count_characters_not_bytes() {
+        let body = format!("{}Straße, naïve café — mit Grüßen", "über ".repeat(12));
+        let (snippet, ranges) = body_snippet(&body, &["strasse", "cafe", "grussen"]);
+        assert!(snippet.starts_with('…'));
+        assert_eq!(highlighted(&snippet, &ranges), ["Straße", "café", "Grüßen"]);
+    }
+
+    #[test]
+    fn hits_say_which_fields_matched() {
+        let mut p = prompt("p", "Cover letter");
+        p.description = "Applying for jobs".to_string();
+        let bodies = HashMap::from([("p".to_string(), "Dear hiring manager".to_string())]);
+        let settings = SearchSettings::default();
+        let ctx = SearchContext {
+            usage: &UsageStore::default(),
+            settings: &settings,
+            target_app: None,
+            now: now(),
+            bodies: Some(&bodies),
+            similarities: None,
+        };
+
+        let hits = search_prompts(std::slice::from_ref(&p), r#"letter "hiring" in:body"#, &ctx);
+        let fields: Vec<SearchField> = hits[0].matches.iter().map(|m| m.field).collect();
+        assert_eq!(fields, [SearchField::Name, SearchField::Body]);
+        assert_eq!(highlighted(&p.name, &hits[0].matches[0].ranges), ["letter"]);
+        assert_eq!(hits[0].snippet.as_deref(), Some("Dear hiring manager"));
+
+        let hit = build_hit(&p, 1.0, &parse_query("jobs"), None);
+        assert_eq!(hit.matches[0].field, SearchField::Description);
+        assert_eq!(hit.snippet, None);
     }
 }
@@ -29,6 +29,10 @@ pub fn seed_if_needed(paths: &StoragePaths, index: &mut PromptIndex) -> AppResul
             updated: String::new(),
             icon: Some("file-text".to_string()),
             color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
         },
           content: r#"
 # **Task**
@@ -170,6 +174,10 @@ Before submitting your summary, ensure:
             updated: String::new(),
             icon: Some("pencil".to_string()),
             color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
         },
         content: "Create a full Markov Chain state graph to find any possible flaws in this"
             .to_string(),
@@ -190,6 +198,10 @@ Before submitting your summary, ensure:
             updated: String::new(),
             icon: Some("lightbulb".to_string()),
             color: None,
+            pinned: false,
+            sort_index: None,
+            tags: Vec::new(),
+            extra: Default::default(),
         },
         content: r#"
 # Critical Thinking
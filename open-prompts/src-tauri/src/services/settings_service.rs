@@ -1,8 +1,17 @@
 use crate::error::{map_err, AppResult};
-use crate::models::settings::AppSettings;
-use crate::services::storage::{atomic_write, StoragePaths};
+use crate::models::hotkey::{HotkeyAction, HotkeyBinding};
+use crate::models::prompt::PromptIndex;
+use crate::models::settings::{AppSettings, PromptHotkey, CURRENT_SETTINGS_VERSION};
+use crate::services::storage::{atomic_write, backup_corrupt_file, StoragePaths};
+use serde_json::Value;
+use std::collections::HashSet;
 use std::fs;
 
+type Migration = fn(&mut Value) -> AppResult<()>;
+
+/// `MIGRATIONS[n]` upgrades a version `n` document to version `n + 1`.
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
 pub fn load_settings(paths: &StoragePaths) -> AppResult<AppSettings> {
     if !paths.settings_path.exists() {
         let settings = AppSettings::default();
@@ -11,17 +20,385 @@ pub fn load_settings(paths: &StoragePaths) -> AppResult<AppSettings> {
     }
 
     let data = fs::read_to_string(&paths.settings_path).map_err(map_err)?;
-    match serde_json::from_str::<AppSettings>(&data) {
-        Ok(settings) => Ok(settings),
-        Err(_) => {
-            let settings = AppSettings::default();
-            save_settings(paths, &settings)?;
-            Ok(settings)
+    let mut value = match serde_json::from_str::<Value>(&data) {
+        Ok(value) if value.is_object() => value,
+        Ok(_) => return reset_corrupt(paths, "settings.json is not a JSON object"),
+        Err(e) => return reset_corrupt(paths, &e.to_string()),
+    };
+
+    let version = settings_version(&value);
+    if version > CURRENT_SETTINGS_VERSION {
+        // Written by a newer release: read what we understand. The version is
+        // kept, so save_settings won't write it back.
+        eprintln!(
+            "settings.json is version {}, newer than supported version {}; loading read-only",
+            version, CURRENT_SETTINGS_VERSION
+        );
+        return serde_json::from_value::<AppSettings>(value).map_err(map_err);
+    }
+
+    migrate(&mut value, version)?;
+
+    let settings = match serde_json::from_value::<AppSettings>(value) {
+        Ok(settings) => settings,
+        Err(e) => return reset_corrupt(paths, &e.to_string()),
+    };
+
+    if version < CURRENT_SETTINGS_VERSION {
+        // Keep the pre-migration file around in case a migration gets something wrong
+        let backup_path = paths
+            .settings_path
+            .with_extension(format!("v{}.bak", version));
+        if let Err(e) = fs::copy(&paths.settings_path, &backup_path) {
+            eprintln!("Failed to back up settings before migration: {}", e);
         }
+        save_settings(paths, &settings)?;
     }
+
+    Ok(settings)
 }
 
+/// Refuses settings from a newer release, since writing them back would drop
+/// every field this release doesn't know.
 pub fn save_settings(paths: &StoragePaths, settings: &AppSettings) -> AppResult<()> {
+    if settings.version > CURRENT_SETTINGS_VERSION {
+        return Err(format!(
+            "settings.json was written by a newer version of OpenPrompts (settings version {}); update the app to change settings",
+            settings.version
+        ));
+    }
     let json = serde_json::to_string_pretty(settings).map_err(map_err)?;
     atomic_write(&paths.settings_path, json.as_bytes())
 }
+
+fn settings_version(value: &Value) -> u32 {
+    value
+        .get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// Run every migration from `from` up to CURRENT_SETTINGS_VERSION.
+fn migrate(value: &mut Value, from: u32) -> AppResult<()> {
+    for (step, migration) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        migration(value).map_err(|e| {
+            format!("Settings migration v{} -> v{} failed: {}", step, step + 1, e)
+        })?;
+        value["version"] = Value::from(step as u32 + 1);
+    }
+    Ok(())
+}
+
+/// v0 is every file written before settings were versioned. Sections added
+/// since (`search`, `hotkeys`) are filled in by serde defaults, so the only
+/// change is stamping the version.
+fn migrate_v0_to_v1(value: &mut Value) -> AppResult<()> {
+    let root = value
+        .as_object_mut()
+        .ok_or("settings.json is not a JSON object")?;
+    root.insert("version".to_string(), Value::from(1));
+    Ok(())
+}
+
+/// Move an unparsable file aside and start over from defaults.
+fn reset_corrupt(paths: &StoragePaths, reason: &str) -> AppResult<AppSettings> {
+    let backup_path = backup_corrupt_file(&paths.settings_path);
+    eprintln!("Corrupt settings.json renamed to {:?}: {}", backup_path, reason);
+    let settings = AppSettings::default();
+    save_settings(paths, &settings)?;
+    Ok(settings)
+}
+
+/// Flatten the launcher hotkey and `settings.hotkeys` into one binding list.
+/// Empty accelerators are treated as unbound.
+pub fn hotkey_bindings(settings: &AppSettings) -> Vec<HotkeyBinding> {
+    let mut bindings = vec![HotkeyBinding {
+        accelerator: settings.general.hotkey.clone(),
+        action: HotkeyAction::ToggleLauncher,
+    }];
+
+    if let Some(accelerator) = &settings.hotkeys.quick_add {
+        bindings.push(HotkeyBinding {
+            accelerator: accelerator.clone(),
+            action: HotkeyAction::QuickAdd,
+        });
+    }
+
+    if let Some(accelerator) = &settings.hotkeys.paste_last_used {
+        bindings.push(HotkeyBinding {
+            accelerator: accelerator.clone(),
+            action: HotkeyAction::PasteLastUsed,
+        });
+    }
+
+    for prompt in &settings.hotkeys.prompts {
+        bindings.push(HotkeyBinding {
+            accelerator: prompt.accelerator.clone(),
+            action: HotkeyAction::PastePrompt {
+                prompt_id: prompt.prompt_id.clone(),
+            },
+        });
+    }
+
+    bindings.retain(|binding| !binding.accelerator.trim().is_empty());
+    bindings
+}
+
+/// Store `bindings` in the launcher hotkey and `settings.hotkeys`, the reverse
+/// of `hotkey_bindings`. Actions missing from `bindings` become unbound.
+pub fn set_hotkey_bindings(settings: &mut AppSettings, bindings: &[HotkeyBinding]) {
+    settings.general.hotkey = String::new();
+    settings.hotkeys.quick_add = None;
+    settings.hotkeys.paste_last_used = None;
+    settings.hotkeys.prompts.clear();
+
+    for binding in bindings {
+        let accelerator = binding.accelerator.clone();
+        match &binding.action {
+            HotkeyAction::ToggleLauncher => settings.general.hotkey = accelerator,
+            HotkeyAction::QuickAdd => settings.hotkeys.quick_add = Some(accelerator),
+            HotkeyAction::PasteLastUsed => settings.hotkeys.paste_last_used = Some(accelerator),
+            HotkeyAction::PastePrompt { prompt_id } => {
+                settings.hotkeys.prompts.push(PromptHotkey {
+                    accelerator,
+                    prompt_id: prompt_id.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// Point the hotkeys bound to any of `from_ids` at `into_id`, e.g. after
+/// merging prompts. Returns whether any changed.
+pub fn reassign_prompt_hotkeys(
+    settings: &mut AppSettings,
+    from_ids: &[String],
+    into_id: &str,
+) -> bool {
+    let mut changed = false;
+    for hotkey in settings
+        .hotkeys
+        .prompts
+        .iter_mut()
+        .filter(|hotkey| from_ids.contains(&hotkey.prompt_id))
+    {
+        hotkey.prompt_id = into_id.to_string();
+        changed = true;
+    }
+    changed
+}
+
+/// Unbind the hotkeys of prompts `index` no longer has. Returns whether any were.
+pub fn drop_missing_prompt_hotkeys(settings: &mut AppSettings, index: &PromptIndex) -> bool {
+    let ids: HashSet<&str> = index.prompts.iter().map(|p| p.id.as_str()).collect();
+    let before = settings.hotkeys.prompts.len();
+    settings
+        .hotkeys
+        .prompts
+        .retain(|hotkey| ids.contains(hotkey.prompt_id.as_str()));
+    settings.hotkeys.prompts.len() != before
+}
+
+/// Settings that need more than a write to disk to take effect.
+/// `None`/`false` means unchanged.
+#[derive(Debug, Default)]
+pub struct SettingsChanges {
+    pub auto_launch: Option<bool>,
+    pub editor_always_on_top: Option<bool>,
+    pub hotkeys: bool,
+    pub api: bool,
+}
+
+pub fn diff_settings(old: &AppSettings, new: &AppSettings) -> SettingsChanges {
+    SettingsChanges {
+        auto_launch: Some(new.general.auto_launch)
+            .filter(|enabled| *enabled != old.general.auto_launch),
+        editor_always_on_top: Some(new.general.editor_always_on_top)
+            .filter(|on_top| *on_top != old.general.editor_always_on_top),
+        hotkeys: hotkey_bindings(old) != hotkey_bindings(new),
+        api: old.api != new.api,
+    }
+}
+
+/// Give an enabled API a token if it has none. Returns whether one was generated.
+pub fn ensure_api_token(settings: &mut AppSettings) -> bool {
+    if !settings.api.enabled || !settings.api.token.is_empty() {
+        return false;
+    }
+    settings.api.token = generate_api_token();
+    true
+}
+
+pub fn generate_api_token() -> String {
+    // Two v4 UUIDs give 244 random bits
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{metadata, temp_paths, TempLibrary};
+    use serde_json::json;
+
+    fn load_fixture(name: &str, json: &str) -> (TempLibrary, AppSettings) {
+        let paths = temp_paths(name);
+        fs::write(&paths.settings_path, json).unwrap();
+        let settings = load_settings(&paths).unwrap();
+        (paths, settings)
+    }
+
+    fn file_version(paths: &StoragePaths) -> u32 {
+        let json = fs::read_to_string(&paths.settings_path).unwrap();
+        settings_version(&serde_json::from_str(&json).unwrap())
+    }
+
+    #[test]
+    fn first_release_settings_migrate_to_current() {
+        // The original layout: only `general` and `appearance`, no version
+        let (paths, settings) = load_fixture(
+            "v0-original",
+            r#"{
+                "general": {
+                    "autoLaunch": true,
+                    "hotkey": "Alt+Space",
+                    "editorAlwaysOnTop": false,
+                    "welcomeScreenDismissed": true
+                },
+                "appearance": { "theme": "light", "accentColor": "blue" }
+            }"#,
+        );
+
+        assert_eq!(settings.version, CURRENT_SETTINGS_VERSION);
+        assert!(settings.general.auto_launch);
+        assert_eq!(settings.general.hotkey, "Alt+Space");
+        assert_eq!(settings.appearance.theme, "light");
+        assert_eq!(settings.general.quick_add_folder, "");
+        assert!(!settings.api.enabled);
+        assert_eq!(file_version(&paths), CURRENT_SETTINGS_VERSION);
+        assert!(paths.settings_path.with_extension("v0.bak").exists());
+    }
+
+    #[test]
+    fn unversioned_settings_with_later_sections_keep_them() {
+        // Written after search and hotkey settings were added, before versioning
+        let (_, settings) = load_fixture(
+            "v0-sections",
+            r#"{
+                "general": { "hotkey": "Ctrl+8", "quickAddFolder": "inbox" },
+                "appearance": { "theme": "dark", "accentColor": "avocado" },
+                "search": { "frecencyHalfLifeDays": 7, "contextWeight": 0 },
+                "hotkeys": {
+                    "quickAdd": "Ctrl+Shift+A",
+                    "prompts": [{ "accelerator": "Ctrl+1", "promptId": "p1" }]
+                }
+            }"#,
+        );
+
+        assert_eq!(settings.version, CURRENT_SETTINGS_VERSION);
+        assert_eq!(settings.general.quick_add_folder, "inbox");
+        assert_eq!(settings.search.frecency_half_life_days, 7.0);
+        assert_eq!(settings.search.context_weight, 0.0);
+        assert_eq!(settings.hotkeys.quick_add.as_deref(), Some("Ctrl+Shift+A"));
+        assert_eq!(settings.hotkeys.prompts[0].prompt_id, "p1");
+    }
+
+    #[test]
+    fn current_settings_load_without_rewriting() {
+        let json = r#"{
+            "version": 1,
+            "general": { "hotkey": "Ctrl+9" },
+            "api": { "enabled": true, "port": 5000, "token": "secret" }
+        }"#;
+        let (paths, settings) = load_fixture("v1", json);
+
+        assert_eq!(settings.general.hotkey, "Ctrl+9");
+        assert_eq!(settings.api.port, 5000);
+        assert_eq!(fs::read_to_string(&paths.settings_path).unwrap(), json);
+        assert!(!paths.settings_path.with_extension("v1.bak").exists());
+    }
+
+    #[test]
+    fn newer_settings_are_read_but_never_written() {
+        let json = r#"{
+            "version": 99,
+            "general": { "hotkey": "Ctrl+7" },
+            "somethingNew": { "enabled": true }
+        }"#;
+        let (paths, mut settings) = load_fixture("newer", json);
+
+        assert_eq!(settings.general.hotkey, "Ctrl+7");
+        settings.general.hotkey = "Ctrl+6".to_string();
+        assert!(save_settings(&paths, &settings).is_err());
+        assert_eq!(fs::read_to_string(&paths.settings_path).unwrap(), json);
+    }
+
+    #[test]
+    fn corrupt_settings_are_backed_up_and_reset() {
+        let (paths, settings) = load_fixture("corrupt", "{ not json");
+
+        assert_eq!(settings.general.hotkey, AppSettings::default().general.hotkey);
+        assert_eq!(file_version(&paths), CURRENT_SETTINGS_VERSION);
+    }
+
+    #[test]
+    fn hotkey_bindings_round_trip_through_settings() {
+        let bindings = vec![
+            HotkeyBinding {
+                accelerator: "Ctrl+Alt+P".to_string(),
+                action: HotkeyAction::ToggleLauncher,
+            },
+            HotkeyBinding {
+                accelerator: "Ctrl+Alt+N".to_string(),
+                action: HotkeyAction::QuickAdd,
+            },
+            HotkeyBinding {
+                accelerator: "Ctrl+Alt+1".to_string(),
+                action: HotkeyAction::PastePrompt {
+                    prompt_id: "abc".to_string(),
+                },
+            },
+        ];
+        let mut settings = AppSettings::default();
+        settings.hotkeys.paste_last_used = Some("Ctrl+Alt+L".to_string());
+
+        set_hotkey_bindings(&mut settings, &bindings);
+
+        assert_eq!(settings.general.hotkey, "Ctrl+Alt+P");
+        assert_eq!(settings.hotkeys.paste_last_used, None);
+        assert_eq!(hotkey_bindings(&settings), bindings);
+    }
+
+    #[test]
+    fn prompt_hotkeys_follow_merges_and_deletes() {
+        let mut settings = AppSettings::default();
+        for (key, prompt_id) in [("1", "keep"), ("2", "merged"), ("3", "gone")] {
+            settings.hotkeys.prompts.push(PromptHotkey {
+                accelerator: format!("Ctrl+{}", key),
+                prompt_id: prompt_id.to_string(),
+            });
+        }
+
+        let (merged, other) = (["merged".to_string()], ["other".to_string()]);
+        assert!(reassign_prompt_hotkeys(&mut settings, &merged, "keep"));
+        assert!(!reassign_prompt_hotkeys(&mut settings, &other, "keep"));
+
+        let index = PromptIndex {
+            prompts: vec![metadata("keep", json!({}))],
+            ..PromptIndex::default()
+        };
+        assert!(drop_missing_prompt_hotkeys(&mut settings, &index));
+        assert!(!drop_missing_prompt_hotkeys(&mut settings, &index));
+        let bound: Vec<(&str, &str)> = settings
+            .hotkeys
+            .prompts
+            .iter()
+            .map(|h| (h.accelerator.as_str(), h.prompt_id.as_str()))
+            .collect();
+        assert_eq!(bound, [("Ctrl+1", "keep"), ("Ctrl+2", "keep")]);
+    }
+}
@@ -2,6 +2,7 @@ use crate::error::{map_err, AppResult};
 use std::fs;
 use std::path::Path;
 use std::path::PathBuf;
+use std::time::SystemTime;
 
 #[derive(Clone, Debug)]
 pub struct StoragePaths {
@@ -9,6 +10,43 @@ pub struct StoragePaths {
     pub prompts_dir: PathBuf,
     pub index_path: PathBuf,
     pub settings_path: PathBuf,
+    pub usage_log_path: PathBuf,
+}
+
+impl StoragePaths {
+    /// The files of a data folder at `root`.
+    pub fn in_dir(root: PathBuf) -> Self {
+        Self {
+            prompts_dir: root.join("prompts"),
+            index_path: root.join("index.json"),
+            settings_path: root.join("settings.json"),
+            usage_log_path: root.join("usage.log.jsonl"),
+            root,
+        }
+    }
+}
+
+/// Lock file in the data folder, see `lock_library`
+const LOCK_FILE: &str = "library.lock";
+
+/// Exclusive hold on the library, released when dropped.
+pub struct LibraryLock {
+    _file: fs::File,
+}
+
+/// Wait until no other writer, in this process or another such as the `--mcp`
+/// server, holds the library. Held across a load, change and save of the index
+/// so concurrent changes can't overwrite each other.
+pub fn lock_library(paths: &StoragePaths) -> AppResult<LibraryLock> {
+    let file = fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(paths.root.join(LOCK_FILE))
+        .map_err(map_err)?;
+    file.lock()
+        .map_err(|e| format!("Could not lock the prompt library: {}", e))?;
+    Ok(LibraryLock { _file: file })
 }
 
 pub fn ensure_storage_dirs(paths: &StoragePaths) -> AppResult<()> {
@@ -23,3 +61,25 @@ pub fn atomic_write(path: &Path, content: &[u8]) -> AppResult<()> {
     fs::rename(&temp, path).map_err(map_err)?;
     Ok(())
 }
+
+/// Move an unreadable file aside as `<name>.corrupt.<timestamp>` so a fresh one
+/// can be written without losing the original. Returns the backup path.
+pub fn backup_corrupt_file(path: &Path) -> Option<PathBuf> {
+    let timestamp = chrono::Utc::now().format("%Y%m%d%H%M%S");
+    let backup_path = path.with_extension(format!("corrupt.{}", timestamp));
+    match fs::rename(path, &backup_path) {
+        Ok(()) => Some(backup_path),
+        Err(e) => {
+            eprintln!("Failed to back up corrupt file {:?}: {}", path, e);
+            None
+        }
+    }
+}
+
+/// Modification time and length of a file; `None` if it's missing.
+pub type FileStamp = (SystemTime, u64);
+
+pub fn stamp(path: &Path) -> Option<FileStamp> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
@@ -0,0 +1,786 @@
+//! Prompt templates: `{{name}}` variables, `{{> folder/name}}` includes and
+//! `{{#if}}`, `{{#unless}}` and `{{#each}}` blocks. Content is split into tags,
+//! parsed into a tree and rendered from that. `{{...}}` that isn't a known tag
+//! is plain text, so prompts that merely mention braces keep working.
+
+use crate::error::AppResult;
+use crate::models::prompt::TemplateError;
+use std::collections::HashMap;
+
+/// Deepest chain of nested `{{> ...}}` includes a render will follow.
+pub const MAX_INCLUDE_DEPTH: usize = 8;
+
+/// Inside `{{#each list}}`, the current item.
+const ITEM_NAME: &str = "this";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum BlockKind {
+    If,
+    Unless,
+    Each,
+}
+
+impl BlockKind {
+    fn parse(word: &str) -> Option<Self> {
+        match word {
+            "if" => Some(Self::If),
+            "unless" => Some(Self::Unless),
+            "each" => Some(Self::Each),
+            _ => None,
+        }
+    }
+
+    fn word(self) -> &'static str {
+        match self {
+            Self::If => "if",
+            Self::Unless => "unless",
+            Self::Each => "each",
+        }
+    }
+}
+
+/// One `{{...}}` tag, without the text around it.
+#[derive(Debug)]
+enum Tag<'a> {
+    Variable(&'a str),
+    Include(&'a str),
+    /// `{{#if name}}`, `{{#unless name}}` or `{{#each name}}`; `name` is checked by the parser
+    Open {
+        kind: BlockKind,
+        name: &'a str,
+    },
+    Else,
+    Close(BlockKind),
+}
+
+struct Token<'a> {
+    start: usize,
+    end: usize,
+    tag: Tag<'a>,
+}
+
+#[derive(Debug)]
+enum Node<'a> {
+    Text(&'a str),
+    /// `raw` is the whole tag, kept in the output when there is no value
+    Variable {
+        name: &'a str,
+        raw: &'a str,
+    },
+    /// Includes are expanded before rendering; one still here is left as written
+    Include {
+        raw: &'a str,
+    },
+    Block {
+        kind: BlockKind,
+        name: &'a str,
+        body: Vec<Node<'a>>,
+        otherwise: Vec<Node<'a>>,
+    },
+}
+
+/// Variables used by `content`, in order of first appearance: `{{name}}`
+/// placeholders and the names blocks test or loop over.
+pub fn extract_variables(content: &str) -> Vec<String> {
+    variable_names(content, true)
+}
+
+/// The `{{name}}` placeholders of `content`, in order of first appearance.
+/// Unlike block names, these are reported missing when rendered without a value.
+pub fn extract_placeholders(content: &str) -> Vec<String> {
+    variable_names(content, false)
+}
+
+fn variable_names(content: &str, with_blocks: bool) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for token in tokenize(content) {
+        let name = match token.tag {
+            Tag::Variable(name) => name,
+            Tag::Open { name, .. } if with_blocks => name,
+            _ => continue,
+        };
+        if name != ITEM_NAME && !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+    }
+    names
+}
+
+/// Render `content` with `values`. Placeholders without a value are left
+/// untouched and returned as missing. Block variables are optional: a missing
+/// one counts as false or as an empty list.
+///
+/// `{{#if name}}` is true when the value is not blank, `false`, `no` or `0`.
+/// `{{#each name}}` repeats once per non-blank line of the value, with the
+/// line available as `{{this}}`. Both `if` and `unless` take an `{{else}}`.
+pub fn render(
+    content: &str,
+    values: &HashMap<String, String>,
+) -> Result<(String, Vec<String>), TemplateError> {
+    let nodes = parse(content)?;
+    let mut output = String::with_capacity(content.len());
+    let mut missing: Vec<String> = Vec::new();
+    render_nodes(&nodes, values, None, &mut output, &mut missing);
+    Ok((output, missing))
+}
+
+/// Every problem in `content`: the first structural error, if any, and each
+/// include that `check_include` rejects.
+pub fn validate<F>(content: &str, check_include: &mut F) -> Vec<TemplateError>
+where
+    F: FnMut(&str) -> Result<(), String>,
+{
+    let mut errors = Vec::new();
+    if let Err(e) = parse(content) {
+        errors.push(e);
+    }
+    for token in tokenize(content) {
+        if let Tag::Include(target) = token.tag {
+            if let Err(message) = check_include(target) {
+                errors.push(error_at(content, token.start, message));
+            }
+        }
+    }
+    errors.sort_by_key(|e| (e.line, e.column));
+    errors
+}
+
+/// Where a render of `content`, with its includes expanded, went wrong.
+/// `expanded` is the error, which is placed in the expanded text. An error in
+/// `content` itself is returned instead; one in an included prompt is placed
+/// on the `{{> ...}}` that brings it in and names that prompt. `load` returns
+/// the raw content of an include target.
+pub fn locate_error<F>(content: &str, expanded: TemplateError, load: &mut F) -> TemplateError
+where
+    F: FnMut(&str) -> AppResult<String>,
+{
+    if let Err(e) = parse(content) {
+        return e;
+    }
+    let includes: Vec<(usize, &str)> = tokenize(content)
+        .into_iter()
+        .filter_map(|token| match token.tag {
+            Tag::Include(target) => Some((token.start, target)),
+            _ => None,
+        })
+        .collect();
+    for &(start, target) in &includes {
+        if let Some(problem) = include_problem(target, load, 1) {
+            return error_at(content, start, problem);
+        }
+    }
+
+    // Prompts that parse alone also parse together, so an include must have
+    // changed since it was expanded
+    let start = includes.first().map_or(0, |&(start, _)| start);
+    error_at(
+        content,
+        start,
+        format!("Once includes are expanded: {}", expanded.message),
+    )
+}
+
+/// What's wrong with the included prompt `target` or anything it includes.
+fn include_problem<F>(target: &str, load: &mut F, depth: usize) -> Option<String>
+where
+    F: FnMut(&str) -> AppResult<String>,
+{
+    if depth > MAX_INCLUDE_DEPTH {
+        return None;
+    }
+    let content = load(target).ok()?;
+    if let Err(e) = parse(&content) {
+        return Some(format!("In included prompt '{}': {}", target, e));
+    }
+    tokenize(&content)
+        .into_iter()
+        .find_map(|token| match token.tag {
+            Tag::Include(nested) => include_problem(nested, load, depth + 1)
+                .map(|problem| format!("In included prompt '{}': {}", target, problem)),
+            _ => None,
+        })
+}
+
+/// Targets of the `{{> folder/name}}` includes in `content`, in order of first appearance.
+pub fn extract_includes(content: &str) -> Vec<String> {
+    let mut targets: Vec<String> = Vec::new();
+    for token in tokenize(content) {
+        if let Tag::Include(target) = token.tag {
+            if !targets.iter().any(|t| t == target) {
+                targets.push(target.to_string());
+            }
+        }
+    }
+    targets
+}
+
+/// Replace each `{{> target}}` with the content of the prompt it names, recursively.
+/// `resolve` looks a target up and returns a key identifying the prompt (used to
+/// detect cycles) and its raw content. `root` is the key of `content` itself.
+pub fn expand_includes<F>(root: &str, content: &str, resolve: &mut F) -> AppResult<String>
+where
+    F: FnMut(&str) -> AppResult<(String, String)>,
+{
+    let mut stack = vec![root.to_string()];
+    expand_nested(content, resolve, &mut stack)
+}
+
+fn expand_nested<F>(content: &str, resolve: &mut F, stack: &mut Vec<String>) -> AppResult<String>
+where
+    F: FnMut(&str) -> AppResult<(String, String)>,
+{
+    let found: Vec<(usize, usize, &str)> = tokenize(content)
+        .into_iter()
+        .filter_map(|token| match token.tag {
+            Tag::Include(target) => Some((token.start, token.end, target)),
+            _ => None,
+        })
+        .collect();
+    if found.is_empty() {
+        return Ok(content.to_string());
+    }
+    if stack.len() > MAX_INCLUDE_DEPTH {
+        return Err(format!(
+            "Includes are nested more than {} levels deep",
+            MAX_INCLUDE_DEPTH
+        ));
+    }
+
+    let mut output = String::with_capacity(content.len());
+    let mut last = 0;
+    for (start, end, target) in found {
+        output.push_str(&content[last..start]);
+
+        let (key, included) = resolve(target)?;
+        if let Some(pos) = stack.iter().position(|k| *k == key) {
+            let mut cycle = stack[pos..].to_vec();
+            cycle.push(key);
+            return Err(format!("Include cycle: {}", cycle.join(" -> ")));
+        }
+        stack.push(key);
+        output.push_str(&expand_nested(&included, resolve, stack)?);
+        stack.pop();
+
+        last = end;
+    }
+    output.push_str(&content[last..]);
+
+    Ok(output)
+}
+
+/// Every recognised tag, in order. Unterminated `{{` and unknown tags are text.
+fn tokenize(content: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut offset = 0;
+
+    while let Some(open) = content[offset..].find("{{") {
+        let start = offset + open;
+        let close = match content[start + 2..].find("}}") {
+            Some(close) => start + 2 + close,
+            None => break,
+        };
+        match classify(content[start + 2..close].trim()) {
+            Some(tag) => {
+                tokens.push(Token {
+                    start,
+                    end: close + 2,
+                    tag,
+                });
+                offset = close + 2;
+            }
+            None => offset = start + 2,
+        }
+    }
+
+    tokens
+}
+
+fn classify(inner: &str) -> Option<Tag<'_>> {
+    if inner.contains('\n') {
+        return None;
+    }
+    if let Some(rest) = inner.strip_prefix('#') {
+        let rest = rest.trim();
+        let (word, name) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        return BlockKind::parse(word).map(|kind| Tag::Open {
+            kind,
+            name: name.trim(),
+        });
+    }
+    if let Some(rest) = inner.strip_prefix('/') {
+        return BlockKind::parse(rest.trim()).map(Tag::Close);
+    }
+    if let Some(rest) = inner.strip_prefix('>') {
+        let target = rest.trim().trim_matches('/');
+        return (!target.is_empty()).then_some(Tag::Include(target));
+    }
+    if inner == "else" {
+        return Some(Tag::Else);
+    }
+    is_variable_name(inner).then_some(Tag::Variable(inner))
+}
+
+/// A block still waiting for its `{{/...}}`.
+struct Frame<'a> {
+    kind: BlockKind,
+    name: &'a str,
+    start: usize,
+    body: Vec<Node<'a>>,
+    otherwise: Option<Vec<Node<'a>>>,
+}
+
+impl<'a> Frame<'a> {
+    fn nodes(&mut self) -> &mut Vec<Node<'a>> {
+        self.otherwise.as_mut().unwrap_or(&mut self.body)
+    }
+}
+
+fn parse(content: &str) -> Result<Vec<Node<'_>>, TemplateError> {
+    let mut root: Vec<Node> = Vec::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut last = 0;
+
+    for token in tokenize(content) {
+        let current = match stack.last_mut() {
+            Some(frame) => frame.nodes(),
+            None => &mut root,
+        };
+        if token.start > last {
+            current.push(Node::Text(&content[last..token.start]));
+        }
+        last = token.end;
+        let raw = &content[token.start..token.end];
+
+        match token.tag {
+            Tag::Variable(name) => current.push(Node::Variable { name, raw }),
+            Tag::Include(_) => current.push(Node::Include { raw }),
+            Tag::Open { kind, name } => {
+                if !is_variable_name(name) {
+                    return Err(error_at(
+                        content,
+                        token.start,
+                        format!("{{{{#{}}}}} needs a variable name", kind.word()),
+                    ));
+                }
+                stack.push(Frame {
+                    kind,
+                    name,
+                    start: token.start,
+                    body: Vec::new(),
+                    otherwise: None,
+                });
+            }
+            Tag::Else => match stack.last_mut() {
+                Some(frame) if frame.kind != BlockKind::Each && frame.otherwise.is_none() => {
+                    frame.otherwise = Some(Vec::new());
+                }
+                Some(frame) if frame.otherwise.is_some() => {
+                    return Err(error_at(
+                        content,
+                        token.start,
+                        format!("Second {{{{else}}}} in {{{{#{}}}}}", frame.kind.word()),
+                    ));
+                }
+                _ => {
+                    return Err(error_at(
+                        content,
+                        token.start,
+                        "{{else}} outside {{#if}} or {{#unless}}".to_string(),
+                    ));
+                }
+            },
+            Tag::Close(kind) => {
+                let frame = stack.pop().ok_or_else(|| {
+                    error_at(
+                        content,
+                        token.start,
+                        format!("{{{{/{}}}}} has no matching block", kind.word()),
+                    )
+                })?;
+                if kind != frame.kind {
+                    let (line, column) = position(content, frame.start);
+                    return Err(error_at(
+                        content,
+                        token.start,
+                        format!(
+                            "Expected {{{{/{}}}}} to close the block at line {}, column {}",
+                            frame.kind.word(),
+                            line,
+                            column
+                        ),
+                    ));
+                }
+                let node = Node::Block {
+                    kind: frame.kind,
+                    name: frame.name,
+                    body: frame.body,
+                    otherwise: frame.otherwise.unwrap_or_default(),
+                };
+                match stack.last_mut() {
+                    Some(parent) => parent.nodes().push(node),
+                    None => root.push(node),
+                }
+            }
+        }
+    }
+
+    if let Some(frame) = stack.pop() {
+        return Err(error_at(
+            content,
+            frame.start,
+            format!("{{{{#{}}}}} is never closed", frame.kind.word()),
+        ));
+    }
+    if last < content.len() {
+        root.push(Node::Text(&content[last..]));
+    }
+    Ok(root)
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    values: &HashMap<String, String>,
+    item: Option<&str>,
+    output: &mut String,
+    missing: &mut Vec<String>,
+) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => output.push_str(text),
+            Node::Include { raw } => output.push_str(raw),
+            Node::Variable { name, raw } => match lookup(values, item, name) {
+                Some(value) => output.push_str(value),
+                None => {
+                    output.push_str(raw);
+                    if !missing.iter().any(|m| m == name) {
+                        missing.push(name.to_string());
+                    }
+                }
+            },
+            Node::Block {
+                kind: BlockKind::Each,
+                name,
+                body,
+                ..
+            } => {
+                let list = lookup(values, item, name).unwrap_or("");
+                for line in list.lines().map(str::trim).filter(|l| !l.is_empty()) {
+                    render_nodes(body, values, Some(line), output, missing);
+                }
+            }
+            Node::Block {
+                kind,
+                name,
+                body,
+                otherwise,
+            } => {
+                let truthy = lookup(values, item, name).is_some_and(is_truthy);
+                let taken = if truthy == (*kind == BlockKind::If) {
+                    body
+                } else {
+                    otherwise
+                };
+                render_nodes(taken, values, item, output, missing);
+            }
+        }
+    }
+}
+
+fn lookup<'v>(
+    values: &'v HashMap<String, String>,
+    item: Option<&'v str>,
+    name: &str,
+) -> Option<&'v str> {
+    match item {
+        Some(item) if name == ITEM_NAME => Some(item),
+        _ => values.get(name).map(String::as_str),
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    let value = value.trim();
+    !value.is_empty()
+        && !["false", "no", "0"]
+            .iter()
+            .any(|falsy| value.eq_ignore_ascii_case(falsy))
+}
+
+fn error_at(content: &str, offset: usize, message: String) -> TemplateError {
+    let (line, column) = position(content, offset);
+    TemplateError {
+        message,
+        line,
+        column,
+    }
+}
+
+/// 1-based line and column (in characters) of a byte offset.
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+fn is_variable_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.' | ' '))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn rendered(content: &str, pairs: &[(&str, &str)]) -> String {
+        render(content, &values(pairs)).unwrap().0
+    }
+
+    fn error(content: &str) -> TemplateError {
+        render(content, &HashMap::new()).unwrap_err()
+    }
+
+    /// Includes resolved from `(target, content)` pairs, keyed by target.
+    fn library<'a>(
+        prompts: &'a [(&'a str, &'a str)],
+    ) -> impl FnMut(&str) -> AppResult<(String, String)> + 'a {
+        move |target| {
+            prompts
+                .iter()
+                .find(|(name, _)| *name == target)
+                .map(|(name, content)| (name.to_string(), content.to_string()))
+                .ok_or_else(|| format!("Included prompt '{}' not found", target))
+        }
+    }
+
+    #[test]
+    fn tags_are_recognised_and_everything_else_is_text() {
+        let tags: Vec<String> = tokenize(
+            "{{name}} {{ spaced name }} {{> team/sign-off/}} {{#if a}}{{else}}{{/if}} {{#each xs}}{{/each}}",
+        )
+        .iter()
+        .map(|token| format!("{:?}", token.tag))
+        .collect();
+        assert_eq!(
+            tags,
+            [
+                "Variable(\"name\")",
+                "Variable(\"spaced name\")",
+                "Include(\"team/sign-off\")",
+                "Open { kind: If, name: \"a\" }",
+                "Else",
+                "Close(If)",
+                "Open { kind: Each, name: \"xs\" }",
+                "Close(Each)",
+            ]
+        );
+
+        for text in [
+            "{{#1}}",
+            "{{#word}}",
+            "{{/word}}",
+            "{{#}}",
+            "{{>}}",
+            "{{ a+b }}",
+            "{{}}",
+            "{{na\nme}}",
+            "{{ unterminated",
+            "{ {name} }",
+        ] {
+            assert!(tokenize(text).is_empty(), "{:?} was a tag", text);
+            assert_eq!(rendered(text, &[("name", "x")]), text);
+        }
+    }
+
+    #[test]
+    fn a_tag_can_follow_an_unrecognised_opening() {
+        assert_eq!(rendered("{{ {{name}}", &[("name", "Ada")]), "{{ Ada");
+        assert_eq!(
+            rendered("{{#1}} {{name}}", &[("name", "Ada")]),
+            "{{#1}} Ada"
+        );
+    }
+
+    #[test]
+    fn variables_are_listed_once_in_order() {
+        assert_eq!(
+            extract_variables("{{b}} {{#if a}}{{b}}{{/if}} {{#each xs}}{{this}}{{/each}} {{#1}}"),
+            ["b", "a", "xs"]
+        );
+        assert_eq!(
+            extract_placeholders("{{#if a}}{{b}}{{/if}} {{#each xs}}{{this}}{{/each}} {{a}}"),
+            ["b", "a"]
+        );
+    }
+
+    #[test]
+    fn missing_variables_are_left_as_written() {
+        let (content, missing) =
+            render("Hi {{name}}, {{ name }} and {{other}}", &values(&[])).unwrap();
+        assert_eq!(content, "Hi {{name}}, {{ name }} and {{other}}");
+        assert_eq!(missing, ["name", "other"]);
+    }
+
+    #[test]
+    fn blocks_render_from_their_values() {
+        let template = "{{#if urgent}}Now{{else}}Later{{/if}}";
+        assert_eq!(rendered(template, &[("urgent", "yes")]), "Now");
+        for falsy in ["", " ", "false", "No", "0"] {
+            assert_eq!(rendered(template, &[("urgent", falsy)]), "Later");
+        }
+        assert_eq!(rendered(template, &[]), "Later");
+        assert_eq!(rendered("{{#unless a}}x{{else}}y{{/unless}}", &[]), "x");
+
+        let list = "{{#each items}}- {{this}} for {{who}}\n{{/each}}";
+        assert_eq!(
+            rendered(list, &[("items", "one\n\n  two \n"), ("who", "Ada")]),
+            "- one for Ada\n- two for Ada\n"
+        );
+        assert_eq!(rendered(list, &[]), "");
+    }
+
+    #[test]
+    fn structural_errors_point_at_the_tag() {
+        let e = error("ok\n  {{#if a}}");
+        assert_eq!((e.line, e.column), (2, 3));
+        assert_eq!(e.message, "{{#if}} is never closed");
+
+        let e = error("{{#if a}}\n{{/each}}");
+        assert_eq!((e.line, e.column), (2, 1));
+        assert!(
+            e.message.contains("close the block at line 1, column 1"),
+            "{}",
+            e
+        );
+
+        // Columns count characters, not bytes
+        let e = error("héé {{/if}}");
+        assert_eq!((e.line, e.column), (1, 5));
+        assert_eq!(e.message, "{{/if}} has no matching block");
+
+        assert_eq!(
+            error("{{else}}").message,
+            "{{else}} outside {{#if}} or {{#unless}}"
+        );
+        assert_eq!(
+            error("{{#each xs}}{{else}}{{/each}}").message,
+            "{{else}} outside {{#if}} or {{#unless}}"
+        );
+        assert_eq!(
+            error("{{#if a}}{{else}}{{else}}{{/if}}").message,
+            "Second {{else}} in {{#if}}"
+        );
+        assert_eq!(
+            error("{{#if}}{{/if}}").message,
+            "{{#if}} needs a variable name"
+        );
+        assert_eq!(
+            error("{{#if a+b}}{{/if}}").message,
+            "{{#if}} needs a variable name"
+        );
+    }
+
+    #[test]
+    fn validate_reports_the_structure_and_every_include() {
+        let errors = validate("{{> a}}\n{{> b}}\n{{#if x}}", &mut |target| match target {
+            "a" => Ok(()),
+            other => Err(format!("Included prompt '{}' not found", other)),
+        });
+        let found: Vec<(usize, &str)> = errors
+            .iter()
+            .map(|e| (e.line, e.message.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (2, "Included prompt 'b' not found"),
+                (3, "{{#if}} is never closed")
+            ]
+        );
+    }
+
+    #[test]
+    fn includes_expand_recursively() {
+        let prompts = [("a", "A({{> b}})"), ("b", "B"), ("c", "{{> a}}{{> a}}")];
+        let expanded = expand_includes("root", "{{> c}}!", &mut library(&prompts)).unwrap();
+        assert_eq!(expanded, "A(B)A(B)!");
+        assert_eq!(extract_includes("{{> a}} {{> b}} {{>a}}"), ["a", "b"]);
+    }
+
+    #[test]
+    fn include_cycles_and_deep_chains_are_rejected() {
+        let prompts = [("a", "{{> b}}"), ("b", "{{> a}}")];
+        let e = expand_includes("a", "{{> b}}", &mut library(&prompts)).unwrap_err();
+        assert_eq!(e, "Include cycle: a -> b -> a");
+
+        let chain: Vec<(String, String)> = (0..=MAX_INCLUDE_DEPTH + 1)
+            .map(|i| (format!("p{}", i), format!("{{{{> p{}}}}}", i + 1)))
+            .collect();
+        let mut resolve = |target: &str| {
+            chain
+                .iter()
+                .find(|(name, _)| name == target)
+                .cloned()
+                .ok_or_else(|| "end".to_string())
+        };
+        let e = expand_includes("root", "{{> p0}}", &mut resolve).unwrap_err();
+        assert!(e.starts_with("Includes are nested"), "{}", e);
+    }
+
+    #[test]
+    fn errors_after_expansion_are_placed_in_the_prompt_itself() {
+        let prompts = [
+            ("ok", "fine"),
+            ("outer", "{{> broken}}"),
+            ("broken", "x\n{{/if}}"),
+        ];
+        let mut load = |target: &str| library(&prompts)(target).map(|(_, content)| content);
+
+        // The prompt's own mistake keeps its own position
+        let own = "{{> ok}}\n\n{{#if a}}";
+        let expanded = expand_includes("root", own, &mut library(&prompts)).unwrap();
+        let e = locate_error(own, error(&expanded), &mut load);
+        assert_eq!((e.line, e.column), (3, 1));
+
+        // A broken include is blamed, at the tag that brings it in
+        let content = "Hello\n  {{> ok}} {{> outer}}";
+        let expanded = expand_includes("root", content, &mut library(&prompts)).unwrap();
+        let e = locate_error(content, error(&expanded), &mut load);
+        assert_eq!((e.line, e.column), (2, 12));
+        assert_eq!(
+            e.message,
+            "In included prompt 'outer': In included prompt 'broken': \
+             Line 2, column 1: {{/if}} has no matching block"
+        );
+
+        // A block opened in one prompt and closed in another is blamed on the first
+        let split = [("open", "{{#if a}}"), ("close", "{{/if}}")];
+        let mut load = |target: &str| library(&split)(target).map(|(_, content)| content);
+        let content = "{{> open}}{{#each xs}}{{> close}}{{/each}}";
+        let expanded = expand_includes("root", content, &mut library(&split)).unwrap();
+        let e = locate_error(content, error(&expanded), &mut load);
+        assert_eq!((e.line, e.column), (1, 1));
+        assert!(
+            e.message.starts_with("In included prompt 'open': "),
+            "{}",
+            e
+        );
+
+        // An include that can't be read any more leaves the expanded error
+        let mut gone = |_: &str| Err("gone".to_string());
+        let e = locate_error("a {{> ok}}", error("{{/if}}"), &mut gone);
+        assert_eq!((e.line, e.column), (1, 3));
+        assert_eq!(
+            e.message,
+            "Once includes are expanded: {{/if}} has no matching block"
+        );
+    }
+}
@@ -0,0 +1,480 @@
+use crate::error::{map_err, AppResult};
+use crate::models::prompt::{PromptIndex, PromptMetadata};
+use crate::models::usage::{PromptUsageStat, UsageBucket, UsageLogEntry};
+use crate::services::storage::{atomic_write, StoragePaths};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+/// Entries older than this are dropped on compaction.
+pub const MAX_LOG_AGE_DAYS: i64 = 365;
+/// Hard cap on entries kept after compaction (newest win).
+pub const MAX_LOG_ENTRIES: usize = 20_000;
+/// Appending past this size triggers an automatic compaction.
+const COMPACT_THRESHOLD_BYTES: u64 = 4 * 1024 * 1024;
+/// Longest range the daily chart covers
+pub const MAX_DAYS: u32 = 366;
+/// Longest range the weekly chart covers
+pub const MAX_WEEKS: u32 = 104;
+
+/// Append one entry as a JSON line. Compacts when the file grows past the threshold.
+pub fn append(paths: &StoragePaths, index: &PromptIndex, entry: &UsageLogEntry) -> AppResult<()> {
+    let mut line = serde_json::to_string(entry).map_err(map_err)?;
+    line.push('\n');
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&paths.usage_log_path)
+        .map_err(map_err)?;
+    file.write_all(line.as_bytes()).map_err(map_err)?;
+
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    if size > COMPACT_THRESHOLD_BYTES {
+        compact(paths, index, Utc::now())?;
+    }
+
+    Ok(())
+}
+
+/// Read every parseable entry. Malformed lines (e.g. a torn final write) are skipped.
+pub fn read_log(paths: &StoragePaths) -> AppResult<Vec<UsageLogEntry>> {
+    if !paths.usage_log_path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let data = fs::read_to_string(&paths.usage_log_path).map_err(map_err)?;
+    Ok(data
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str::<UsageLogEntry>(line).ok())
+        .collect())
+}
+
+/// Rewrite the log without entries of deleted prompts, entries older than
+/// MAX_LOG_AGE_DAYS, and anything beyond the newest MAX_LOG_ENTRIES.
+/// Returns the number of entries kept.
+pub fn compact(paths: &StoragePaths, index: &PromptIndex, now: DateTime<Utc>) -> AppResult<usize> {
+    let cutoff = now - Duration::days(MAX_LOG_AGE_DAYS);
+    let ids: HashSet<&str> = index.prompts.iter().map(|p| p.id.as_str()).collect();
+    let mut entries: Vec<(DateTime<Utc>, UsageLogEntry)> = read_log(paths)?
+        .into_iter()
+        .filter(|entry| ids.contains(entry.prompt_id.as_str()))
+        .filter_map(|entry| Some((parse_timestamp(&entry.timestamp)?, entry)))
+        .filter(|(ts, _)| *ts >= cutoff)
+        .collect();
+
+    if entries.len() > MAX_LOG_ENTRIES {
+        // Processes appending at once can leave lines slightly out of order
+        entries.sort_by_key(|(ts, _)| *ts);
+        let excess = entries.len() - MAX_LOG_ENTRIES;
+        entries.drain(..excess);
+    }
+
+    let entries: Vec<UsageLogEntry> = entries.into_iter().map(|(_, entry)| entry).collect();
+    write_log(paths, &entries)?;
+    Ok(entries.len())
+}
+
+/// Replace the whole log with `entries`.
+pub fn write_log(paths: &StoragePaths, entries: &[UsageLogEntry]) -> AppResult<()> {
+    let mut content = String::new();
+    for entry in entries {
+        content.push_str(&serde_json::to_string(entry).map_err(map_err)?);
+        content.push('\n');
+    }
+    atomic_write(&paths.usage_log_path, content.as_bytes())
+}
+
+/// Count the uses of `from_ids` as uses of `into_id`, e.g. after merging prompts.
+pub fn reassign(paths: &StoragePaths, from_ids: &[String], into_id: &str) -> AppResult<()> {
+    let mut entries = read_log(paths)?;
+    let mut changed = false;
+    for entry in entries
+        .iter_mut()
+        .filter(|entry| from_ids.contains(&entry.prompt_id))
+    {
+        entry.prompt_id = into_id.to_string();
+        changed = true;
+    }
+    if changed {
+        write_log(paths, &entries)?;
+    }
+    Ok(())
+}
+
+/// Uses per local calendar day for the last `days` days, oldest first, including
+/// empty days. `days` is limited to `MAX_DAYS`.
+pub fn daily_usage(entries: &[UsageLogEntry], days: u32, now: DateTime<Utc>) -> Vec<UsageBucket> {
+    let today = now.with_timezone(&Local).date_naive();
+    let periods: Vec<NaiveDate> = (0..days.clamp(1, MAX_DAYS) as i64)
+        .rev()
+        .map(|offset| today - Duration::days(offset))
+        .collect();
+
+    let mut counts: HashMap<NaiveDate, u64> = HashMap::new();
+    for ts in entries.iter().filter_map(|e| parse_timestamp(&e.timestamp)) {
+        *counts.entry(ts.with_timezone(&Local).date_naive()).or_insert(0) += 1;
+    }
+
+    periods
+        .into_iter()
+        .map(|day| UsageBucket {
+            period: day.format("%Y-%m-%d").to_string(),
+            count: counts.get(&day).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+/// Uses per ISO week for the last `weeks` weeks, oldest first, including empty
+/// weeks. `weeks` is limited to `MAX_WEEKS`.
+pub fn weekly_usage(entries: &[UsageLogEntry], weeks: u32, now: DateTime<Utc>) -> Vec<UsageBucket> {
+    let today = now.with_timezone(&Local).date_naive();
+    let periods: Vec<String> = (0..weeks.clamp(1, MAX_WEEKS) as i64)
+        .rev()
+        .map(|offset| iso_week_label(today - Duration::weeks(offset)))
+        .collect();
+
+    let mut counts: HashMap<String, u64> = HashMap::new();
+    for ts in entries.iter().filter_map(|e| parse_timestamp(&e.timestamp)) {
+        *counts
+            .entry(iso_week_label(ts.with_timezone(&Local).date_naive()))
+            .or_insert(0) += 1;
+    }
+
+    periods
+        .into_iter()
+        .map(|period| UsageBucket {
+            count: counts.get(&period).copied().unwrap_or(0),
+            period,
+        })
+        .collect()
+}
+
+/// Most used prompts, optionally only counting uses within the last `days` days.
+pub fn top_prompts(
+    entries: &[UsageLogEntry],
+    index: &PromptIndex,
+    limit: usize,
+    days: Option<u32>,
+    now: DateTime<Utc>,
+) -> Vec<PromptUsageStat> {
+    let cutoff = days.and_then(|d| days_before(now, d));
+    let mut counts: HashMap<&str, (u64, Option<DateTime<Utc>>)> = HashMap::new();
+
+    for entry in entries {
+        let ts = match parse_timestamp(&entry.timestamp) {
+            Some(ts) => ts,
+            None => continue,
+        };
+        if cutoff.is_some_and(|cutoff| ts < cutoff) {
+            continue;
+        }
+        let slot = counts.entry(entry.prompt_id.as_str()).or_insert((0, None));
+        slot.0 += 1;
+        slot.1 = slot.1.max(Some(ts));
+    }
+
+    let mut stats: Vec<PromptUsageStat> = index
+        .prompts
+        .iter()
+        .filter_map(|p| {
+            let (count, last) = counts.get(p.id.as_str())?;
+            Some(to_stat(p, *count, last.map(|ts| ts.to_rfc3339())))
+        })
+        .collect();
+
+    stats.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| b.last_used.cmp(&a.last_used)));
+    stats.truncate(limit);
+    stats
+}
+
+/// Prompts with no recorded use at all, oldest first.
+pub fn never_used(entries: &[UsageLogEntry], index: &PromptIndex) -> Vec<PromptUsageStat> {
+    let used: HashSet<&str> = entries.iter().map(|e| e.prompt_id.as_str()).collect();
+    let mut never: Vec<&PromptMetadata> = index
+        .prompts
+        .iter()
+        .filter(|p| p.use_count == 0 && p.last_used.is_none())
+        .filter(|p| !used.contains(p.id.as_str()))
+        .collect();
+
+    never.sort_by(|a, b| a.created.cmp(&b.created));
+    never.into_iter().map(|p| to_stat(p, 0, None)).collect()
+}
+
+/// Prompts used before but not within the last `days` days, least recently used first.
+pub fn stale_prompts(
+    entries: &[UsageLogEntry],
+    index: &PromptIndex,
+    days: u32,
+    now: DateTime<Utc>,
+) -> Vec<PromptUsageStat> {
+    // Nothing can be unused for longer than dates go back
+    let cutoff = match days_before(now, days) {
+        Some(cutoff) => cutoff,
+        None => return Vec::new(),
+    };
+
+    let mut last_by_id: HashMap<&str, DateTime<Utc>> = HashMap::new();
+    for entry in entries {
+        if let Some(ts) = parse_timestamp(&entry.timestamp) {
+            let slot = last_by_id.entry(entry.prompt_id.as_str()).or_insert(ts);
+            if ts > *slot {
+                *slot = ts;
+            }
+        }
+    }
+
+    let mut stale: Vec<(DateTime<Utc>, PromptUsageStat)> = index
+        .prompts
+        .iter()
+        .filter_map(|p| {
+            let from_log = last_by_id.get(p.id.as_str()).copied();
+            let from_meta = p.last_used.as_deref().and_then(parse_timestamp);
+            let last = from_log.max(from_meta)?;
+            if last >= cutoff {
+                return None;
+            }
+            Some((last, to_stat(p, p.use_count, Some(last.to_rfc3339()))))
+        })
+        .collect();
+
+    stale.sort_by_key(|(last, _)| *last);
+    stale.into_iter().map(|(_, stat)| stat).collect()
+}
+
+fn to_stat(prompt: &PromptMetadata, count: u64, last_used: Option<String>) -> PromptUsageStat {
+    PromptUsageStat {
+        id: prompt.id.clone(),
+        name: prompt.name.clone(),
+        folder: prompt.folder.clone(),
+        count,
+        last_used,
+    }
+}
+
+fn iso_week_label(date: NaiveDate) -> String {
+    let week = date.iso_week();
+    format!("{}-W{:02}", week.year(), week.week())
+}
+
+/// `now` minus `days`, or `None` if that is before the earliest date there is.
+fn days_before(now: DateTime<Utc>, days: u32) -> Option<DateTime<Utc>> {
+    now.checked_sub_signed(Duration::days(i64::from(days)))
+}
+
+/// An RFC 3339 timestamp as UTC, as the log and the index store them.
+pub fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::usage::DeliveryMode;
+    use crate::test_support::{metadata, temp_paths};
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    fn entry(id: &str, at: DateTime<Utc>) -> UsageLogEntry {
+        UsageLogEntry {
+            prompt_id: id.to_string(),
+            timestamp: at.to_rfc3339(),
+            target_app: None,
+            delivery_mode: DeliveryMode::Paste,
+            variables_filled: false,
+        }
+    }
+
+    fn days_ago(days: i64) -> DateTime<Utc> {
+        now() - Duration::days(days)
+    }
+
+    /// An index of prompts with these ids, created in this order.
+    fn index_of(ids: &[&str]) -> PromptIndex {
+        let prompts = ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| {
+                metadata(
+                    id,
+                    json!({ "created": days_ago(100 - i as i64).to_rfc3339() }),
+                )
+            })
+            .collect();
+        PromptIndex {
+            prompts,
+            ..PromptIndex::default()
+        }
+    }
+
+    fn ids(stats: &[PromptUsageStat]) -> Vec<&str> {
+        stats.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn compaction_drops_old_entries_and_those_of_deleted_prompts() {
+        let paths = temp_paths("compact");
+        let index = index_of(&["kept"]);
+        let entries = [
+            entry("kept", days_ago(MAX_LOG_AGE_DAYS + 1)),
+            entry("kept", days_ago(MAX_LOG_AGE_DAYS - 1)),
+            entry("deleted", days_ago(1)),
+            entry("kept", days_ago(0)),
+        ];
+        write_log(&paths, &entries).unwrap();
+
+        assert_eq!(compact(&paths, &index, now()).unwrap(), 2);
+        let kept: Vec<String> = read_log(&paths)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.timestamp)
+            .collect();
+        assert_eq!(
+            kept,
+            [entries[1].timestamp.clone(), entries[3].timestamp.clone()]
+        );
+    }
+
+    #[test]
+    fn compaction_keeps_the_newest_entries() {
+        let paths = temp_paths("compact-cap");
+        let index = index_of(&["p"]);
+        let total = MAX_LOG_ENTRIES + 5;
+        let mut entries: Vec<UsageLogEntry> = (0..total)
+            .map(|i| entry("p", now() - Duration::minutes((total - i) as i64)))
+            .collect();
+        // The oldest lines written last, as a slow writer might
+        entries.rotate_left(5);
+        write_log(&paths, &entries).unwrap();
+
+        assert_eq!(compact(&paths, &index, now()).unwrap(), MAX_LOG_ENTRIES);
+        let oldest = read_log(&paths)
+            .unwrap()
+            .iter()
+            .filter_map(|e| parse_timestamp(&e.timestamp))
+            .min()
+            .unwrap();
+        assert_eq!(oldest, now() - Duration::minutes(MAX_LOG_ENTRIES as i64));
+    }
+
+    #[test]
+    fn appending_compacts_once_the_log_is_too_big() {
+        let paths = temp_paths("compact-append");
+        let index = index_of(&["p"]);
+        append(&paths, &index, &entry("deleted", Utc::now())).unwrap();
+        append(&paths, &index, &entry("p", Utc::now())).unwrap();
+        assert_eq!(read_log(&paths).unwrap().len(), 2);
+
+        let line = serde_json::to_string(&entry("deleted", Utc::now())).unwrap() + "\n";
+        let lines = COMPACT_THRESHOLD_BYTES as usize / line.len() + 1;
+        fs::write(&paths.usage_log_path, line.repeat(lines)).unwrap();
+        append(&paths, &index, &entry("p", Utc::now())).unwrap();
+        let log = read_log(&paths).unwrap();
+        assert_eq!(log.len(), 1);
+        assert_eq!(log[0].prompt_id, "p");
+    }
+
+    #[test]
+    fn uses_are_counted_per_local_day_and_week() {
+        let entries = [
+            entry("p", days_ago(0)),
+            entry("p", days_ago(1)),
+            entry("p", days_ago(1)),
+            entry("p", days_ago(7)),
+            entry("p", days_ago(400)),
+        ];
+        let today = now().with_timezone(&Local).date_naive();
+
+        let daily = daily_usage(&entries, 8, now());
+        let counts: Vec<u64> = daily.iter().map(|b| b.count).collect();
+        assert_eq!(counts, [1, 0, 0, 0, 0, 0, 2, 1]);
+        assert_eq!(daily[7].period, today.format("%Y-%m-%d").to_string());
+
+        let weekly = weekly_usage(&entries, 2, now());
+        assert_eq!(weekly[1].period, iso_week_label(today));
+        assert_eq!(weekly[0].period, iso_week_label(today - Duration::weeks(1)));
+        // Yesterday is last week when today is a Monday
+        let yesterday_last_week = today.weekday() == chrono::Weekday::Mon;
+        let last_week = if yesterday_last_week { 3 } else { 1 };
+        assert_eq!(weekly[0].count, last_week);
+        assert_eq!(weekly[1].count, 4 - last_week);
+    }
+
+    #[test]
+    fn top_prompts_are_the_most_used_in_the_period() {
+        let index = index_of(&["a", "b", "c", "unused"]);
+        let entries = [
+            entry("a", days_ago(40)),
+            entry("a", days_ago(40)),
+            entry("a", days_ago(40)),
+            entry("b", days_ago(2)),
+            entry("b", days_ago(1)),
+            entry("c", days_ago(3)),
+            entry("c", days_ago(5)),
+            entry("deleted", days_ago(1)),
+        ];
+
+        let all_time = top_prompts(&entries, &index, 10, None, now());
+        assert_eq!(ids(&all_time), ["a", "b", "c"]);
+        assert_eq!(all_time[1].count, 2);
+        assert_eq!(all_time[1].last_used, Some(days_ago(1).to_rfc3339()));
+
+        // Ties go to the more recently used
+        assert_eq!(
+            ids(&top_prompts(&entries, &index, 10, Some(30), now())),
+            ["b", "c"]
+        );
+        assert_eq!(ids(&top_prompts(&entries, &index, 1, None, now())), ["a"]);
+    }
+
+    #[test]
+    fn never_used_prompts_have_no_use_anywhere() {
+        let mut index = index_of(&["logged", "counted", "first", "second"]);
+        index.prompts[1].use_count = 3;
+        index.prompts.swap(2, 3);
+        let entries = [entry("logged", days_ago(1))];
+
+        // Oldest first
+        assert_eq!(ids(&never_used(&entries, &index)), ["first", "second"]);
+    }
+
+    #[test]
+    fn stale_prompts_were_used_but_not_lately() {
+        let mut index = index_of(&["recent", "old", "older", "only_meta", "never"]);
+        index.prompts[3].last_used = Some(days_ago(90).to_rfc3339());
+        let entries = [
+            entry("recent", days_ago(60)),
+            entry("recent", days_ago(5)),
+            entry("old", days_ago(45)),
+            entry("older", days_ago(200)),
+        ];
+
+        let stale = stale_prompts(&entries, &index, 30, now());
+        assert_eq!(ids(&stale), ["older", "only_meta", "old"]);
+        assert_eq!(stale[2].last_used, Some(days_ago(45).to_rfc3339()));
+        assert!(stale_prompts(&entries, &index, 365, now()).is_empty());
+    }
+
+    #[test]
+    fn chart_ranges_are_clamped() {
+        assert_eq!(daily_usage(&[], 0, now()).len(), 1);
+        assert_eq!(daily_usage(&[], u32::MAX, now()).len(), MAX_DAYS as usize);
+        assert_eq!(weekly_usage(&[], u32::MAX, now()).len(), MAX_WEEKS as usize);
+    }
+
+    #[test]
+    fn huge_day_ranges_do_not_overflow() {
+        let index = PromptIndex::default();
+        assert!(top_prompts(&[], &index, 10, Some(u32::MAX), now()).is_empty());
+        assert!(stale_prompts(&[], &index, u32::MAX, now()).is_empty());
+    }
+}
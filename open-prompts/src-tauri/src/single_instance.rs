@@ -0,0 +1,220 @@
+//! One running instance per user. A second launch forwards its arguments to
+//! the first over a named pipe (Windows) or a Unix socket, then exits.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::error::{map_err, AppResult};
+use crate::models::launch::LaunchArgs;
+use crate::services::storage::StoragePaths;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, BufReader};
+
+pub use imp::Listener;
+
+/// Forwarded messages are one JSON line; anything longer is cut off.
+const MAX_MESSAGE_BYTES: u64 = 64 * 1024;
+/// Failed accepts in a row before forwarding is given up
+const MAX_ACCEPT_FAILURES: u32 = 10;
+/// Wait after a failed accept, so a lasting error doesn't spin the loop
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(500);
+
+pub enum Instance {
+    /// No other instance is running. Pass the listener to `listen` once the app is up.
+    Primary(Listener),
+    /// Another instance is running and has received our arguments.
+    Forwarded,
+}
+
+/// Claim the instance endpoint, or hand `args` to whoever already holds it.
+pub fn acquire(paths: &StoragePaths, args: &LaunchArgs) -> AppResult<Instance> {
+    tauri::async_runtime::block_on(async {
+        if let Some(listener) = imp::bind(paths).await.map_err(map_err)? {
+            return Ok(Instance::Primary(listener));
+        }
+
+        let mut message = serde_json::to_vec(args).map_err(map_err)?;
+        message.push(b'\n');
+        imp::send(paths, &message)
+            .await
+            .map_err(|e| format!("Failed to reach the running instance: {}", e))?;
+        Ok(Instance::Forwarded)
+    })
+}
+
+/// Accept launches forwarded by later instances for the rest of the process.
+pub fn listen<F>(listener: Listener, on_launch: F)
+where
+    F: Fn(LaunchArgs) + Send + Sync + 'static,
+{
+    let on_launch: Arc<dyn Fn(LaunchArgs) + Send + Sync> = Arc::new(on_launch);
+    tauri::async_runtime::spawn(imp::accept_loop(listener, on_launch));
+}
+
+async fn read_message<S: AsyncRead + Unpin>(
+    stream: S,
+    on_launch: Arc<dyn Fn(LaunchArgs) + Send + Sync>,
+) {
+    let mut reader = BufReader::new(stream.take(MAX_MESSAGE_BYTES));
+    let mut line = String::new();
+    if let Err(e) = reader.read_line(&mut line).await {
+        eprintln!("Failed to read forwarded launch: {}", e);
+        return;
+    }
+
+    match serde_json::from_str::<LaunchArgs>(&line) {
+        Ok(args) => on_launch(args),
+        Err(e) => eprintln!("Ignoring malformed forwarded launch: {}", e),
+    }
+}
+
+#[cfg(windows)]
+mod imp {
+    use std::io;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::models::launch::LaunchArgs;
+    use crate::services::storage::StoragePaths;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeServer, ServerOptions};
+
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    pub struct Listener {
+        name: String,
+        server: NamedPipeServer,
+    }
+
+    /// Per user, so two people signed in at once each get their own instance.
+    fn pipe_name() -> String {
+        let user: String = std::env::var("USERNAME")
+            .unwrap_or_default()
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .collect();
+        format!(r"\\.\pipe\openprompts-{}", user)
+    }
+
+    pub async fn bind(_paths: &StoragePaths) -> io::Result<Option<Listener>> {
+        let name = pipe_name();
+        // Only the first instance may create the pipe; later ones get access denied
+        match ServerOptions::new().first_pipe_instance(true).create(&name) {
+            Ok(server) => Ok(Some(Listener { name, server })),
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub async fn send(_paths: &StoragePaths, message: &[u8]) -> io::Result<()> {
+        let name = pipe_name();
+        let mut attempts = 0;
+        let mut client = loop {
+            match ClientOptions::new().open(&name) {
+                Ok(client) => break client,
+                Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && attempts < 20 => {
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        };
+        client.write_all(message).await?;
+        client.flush().await
+    }
+
+    pub async fn accept_loop(listener: Listener, on_launch: Arc<dyn Fn(LaunchArgs) + Send + Sync>) {
+        let Listener { name, mut server } = listener;
+        let mut failures = 0;
+        loop {
+            if let Err(e) = server.connect().await {
+                failures += 1;
+                if failures >= super::MAX_ACCEPT_FAILURES {
+                    eprintln!("Instance pipe connect failed, forwarding disabled: {}", e);
+                    return;
+                }
+                eprintln!("Instance pipe connect failed: {}", e);
+                tokio::time::sleep(super::ACCEPT_RETRY_DELAY).await;
+                // The failed instance may be unusable, so start over with a new one
+                server = match ServerOptions::new().create(&name) {
+                    Ok(server) => server,
+                    Err(e) => {
+                        eprintln!("Failed to reopen instance pipe, forwarding disabled: {}", e);
+                        return;
+                    }
+                };
+                continue;
+            }
+            failures = 0;
+
+            // Open the next instance before serving this one so no launch finds the pipe missing
+            let connected = server;
+            server = match ServerOptions::new().create(&name) {
+                Ok(server) => server,
+                Err(e) => {
+                    eprintln!("Failed to reopen instance pipe, forwarding disabled: {}", e);
+                    return;
+                }
+            };
+            tauri::async_runtime::spawn(super::read_message(connected, on_launch.clone()));
+        }
+    }
+}
+
+#[cfg(unix)]
+mod imp {
+    use std::io;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    use crate::models::launch::LaunchArgs;
+    use crate::services::storage::StoragePaths;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{UnixListener, UnixStream};
+
+    pub struct Listener {
+        listener: UnixListener,
+    }
+
+    fn socket_path(paths: &StoragePaths) -> PathBuf {
+        paths.root.join("instance.sock")
+    }
+
+    pub async fn bind(paths: &StoragePaths) -> io::Result<Option<Listener>> {
+        let path = socket_path(paths);
+        if UnixStream::connect(&path).await.is_ok() {
+            return Ok(None);
+        }
+
+        // Nobody answered, so any socket file was left behind by a crashed instance
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path)?;
+        Ok(Some(Listener { listener }))
+    }
+
+    pub async fn send(paths: &StoragePaths, message: &[u8]) -> io::Result<()> {
+        let mut stream = UnixStream::connect(socket_path(paths)).await?;
+        stream.write_all(message).await?;
+        stream.shutdown().await
+    }
+
+    pub async fn accept_loop(listener: Listener, on_launch: Arc<dyn Fn(LaunchArgs) + Send + Sync>) {
+        let mut failures = 0;
+        loop {
+            match listener.listener.accept().await {
+                Ok((stream, _)) => {
+                    failures = 0;
+                    tauri::async_runtime::spawn(super::read_message(stream, on_launch.clone()));
+                }
+                Err(e) => {
+                    failures += 1;
+                    if failures >= super::MAX_ACCEPT_FAILURES {
+                        eprintln!("Instance socket accept failed, forwarding disabled: {}", e);
+                        return;
+                    }
+                    eprintln!("Instance socket accept failed: {}", e);
+                    tokio::time::sleep(super::ACCEPT_RETRY_DELAY).await;
+                }
+            }
+        }
+    }
+}
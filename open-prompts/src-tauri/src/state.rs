@@ -1,10 +1,31 @@
+use crate::api::ApiServer;
+use crate::models::hotkey::{HotkeyBinding, HotkeyFallback};
+use crate::models::launch::PendingDeepLink;
+use crate::services::prompt_service::BodyCache;
 use crate::services::storage::StoragePaths;
 use parking_lot::Mutex;
 
 pub struct AppState {
     pub paths: StoragePaths,
     pub last_external_hwnd: Mutex<Option<isize>>,
-    pub current_hotkey: Mutex<String>,
+    /// Executable name of the window captured at hotkey time, e.g. "code.exe"
+    pub last_target_app: Mutex<Option<String>>,
+    /// Hotkeys currently bound, filled from settings during setup
+    pub hotkeys: Mutex<Vec<HotkeyBinding>>,
+    /// Set when startup had to fall back from the configured launcher hotkey
+    pub hotkey_fallback: Mutex<Option<HotkeyFallback>>,
+    /// Selection copied by the quick-add hotkey, waiting for the capture window
+    pub quick_add_capture: Mutex<Option<String>>,
+    /// Query passed with `--search`, waiting for the launcher to show
+    pub pending_search: Mutex<Option<String>>,
+    /// Prompt a link asked to open, waiting for a newly created editor
+    pub pending_open_prompt: Mutex<Option<String>>,
+    /// Paste or import link waiting for the user to confirm it
+    pub pending_deep_link: Mutex<Option<PendingDeepLink>>,
+    /// Local HTTP API, running only while enabled in settings
+    pub api_server: Mutex<Option<ApiServer>>,
+    /// Prompt bodies read by earlier searches
+    pub body_cache: Mutex<BodyCache>,
 }
 
 impl AppState {
@@ -12,7 +33,15 @@ impl AppState {
         Self {
             paths,
             last_external_hwnd: Mutex::new(None),
-            current_hotkey: Mutex::new("CommandOrControl+8".to_string()),
+            last_target_app: Mutex::new(None),
+            hotkeys: Mutex::new(Vec::new()),
+            hotkey_fallback: Mutex::new(None),
+            quick_add_capture: Mutex::new(None),
+            pending_search: Mutex::new(None),
+            pending_open_prompt: Mutex::new(None),
+            pending_deep_link: Mutex::new(None),
+            api_server: Mutex::new(None),
+            body_cache: Mutex::new(BodyCache::default()),
         }
     }
 }
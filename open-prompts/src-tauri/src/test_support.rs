@@ -0,0 +1,75 @@
+//! Helpers shared by the test modules.
+
+use crate::models::prompt::{Prompt, PromptMetadata};
+use crate::services::storage::StoragePaths;
+use crate::services::{index_service, prompt_service};
+use serde_json::{json, Value};
+use std::fs;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A data folder of its own for one test, removed when dropped.
+pub struct TempLibrary {
+    paths: StoragePaths,
+}
+
+impl Deref for TempLibrary {
+    type Target = StoragePaths;
+
+    fn deref(&self) -> &StoragePaths {
+        &self.paths
+    }
+}
+
+impl Drop for TempLibrary {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.paths.root);
+    }
+}
+
+/// An empty data folder with a `prompts` folder in it.
+pub fn temp_paths(name: &str) -> TempLibrary {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    let root = std::env::temp_dir().join(format!(
+        "openprompts-{}-{}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed),
+        name
+    ));
+    let _ = fs::remove_dir_all(&root);
+    let paths = StoragePaths::in_dir(root);
+    fs::create_dir_all(&paths.prompts_dir).unwrap();
+    TempLibrary { paths }
+}
+
+/// Metadata with every field empty but the id, and the name set to it.
+/// `fields` overrides any of them, as in the index.
+pub fn metadata(id: &str, fields: Value) -> PromptMetadata {
+    let mut meta = json!({
+        "id": id, "name": id, "folder": "", "description": "", "filename": "",
+        "useCount": 0, "lastUsed": null, "created": "", "updated": "",
+        "icon": null, "color": null,
+    });
+    meta.as_object_mut()
+        .unwrap()
+        .extend(fields.as_object().unwrap().clone());
+    serde_json::from_value(meta).unwrap()
+}
+
+/// A prompt that isn't saved yet.
+pub fn new_prompt(folder: &str, name: &str, content: &str) -> Prompt {
+    Prompt {
+        meta: metadata("", json!({ "name": name, "folder": folder })),
+        content: content.to_string(),
+    }
+}
+
+/// Save a new prompt and return its id.
+pub fn add_prompt(paths: &StoragePaths, folder: &str, name: &str, content: &str) -> String {
+    let prompt = new_prompt(folder, name, content);
+    index_service::update_index(paths, |index| {
+        prompt_service::save_prompt(paths, index, prompt)
+    })
+    .unwrap()
+    .id
+}
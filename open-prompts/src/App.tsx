@@ -1,14 +1,39 @@
+import { useEffect } from 'react';
+import { listen } from '@tauri-apps/api/event';
 import { getCurrentWindow } from '@tauri-apps/api/window';
+import { useSettingsStore } from './stores/settingsStore';
+import type { AppSettings } from './types';
+import { ConfirmApp } from './windows/confirm/ConfirmApp';
 import { EditorApp } from './windows/editor/EditorApp';
 import { LauncherApp } from './windows/launcher/LauncherApp';
+import { QuickAddApp } from './windows/quickadd/QuickAddApp';
 
 function App() {
   const windowLabel = getCurrentWindow().label;
 
+  // Settings saved from any window are pushed to all of them
+  useEffect(() => {
+    const unlisten = listen<AppSettings>('settings-changed', (event) => {
+      useSettingsStore.setState({ settings: event.payload });
+      document.documentElement.setAttribute('data-theme', event.payload.appearance.theme);
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
+
   if (windowLabel === 'editor') {
     return <EditorApp />;
   }
 
+  if (windowLabel === 'quick-add') {
+    return <QuickAddApp />;
+  }
+
+  if (windowLabel === 'confirm') {
+    return <ConfirmApp />;
+  }
+
   return <LauncherApp />;
 }
 
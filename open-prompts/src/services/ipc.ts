@@ -4,21 +4,98 @@ import {
   enable as enableAutoStart,
   isEnabled as isAutoStartEnabled,
 } from '@tauri-apps/plugin-autostart';
-import type { Prompt, PromptMetadata, PromptIndex, AppSettings } from '../types';
+import type {
+  Prompt,
+  PromptMetadata,
+  PromptIndex,
+  BulkReport,
+  PromptExport,
+  DuplicateGroup,
+  SavedSearch,
+  SearchHit,
+  FolderNode,
+  FolderMeta,
+  FolderDeleteMode,
+  RenderedPrompt,
+  PromptDependencies,
+  TemplateError,
+  PendingDeepLink,
+  AppSettings,
+  DeliveryMode,
+  HotkeyBinding,
+  HotkeyFallback,
+  HotkeyReport,
+  UsageBucket,
+  PromptUsageStat,
+} from '../types';
 
 export const api = {
   // Data
   getIndex: () => invoke<PromptIndex>('get_index'),
   getFolders: () => invoke<string[]>('get_folders'),
+  getFolderTree: () => invoke<FolderNode>('get_folder_tree'),
   getPrompt: (id: string) => invoke<Prompt>('get_prompt', { id }),
   savePrompt: (prompt: Prompt) => invoke<PromptMetadata>('save_prompt', { prompt }),
   deletePrompt: (id: string) => invoke<void>('delete_prompt', { id }),
+  duplicatePrompt: (id: string, targetFolder?: string) =>
+    invoke<PromptMetadata>('duplicate_prompt', { id, targetFolder: targetFolder ?? null }),
+  movePrompts: (ids: string[], folder: string) => invoke<BulkReport>('move_prompts', { ids, folder }),
+  deletePrompts: (ids: string[]) => invoke<BulkReport>('delete_prompts', { ids }),
+  tagPrompts: (ids: string[], tags: string[]) => invoke<BulkReport>('tag_prompts', { ids, tags }),
+  exportPrompts: (ids: string[]) => invoke<PromptExport>('export_prompts', { ids }),
+  findDuplicates: (threshold?: number) =>
+    invoke<DuplicateGroup[]>('find_duplicates', { threshold: threshold ?? null }),
+  mergePrompts: (keepId: string, removeIds: string[]) =>
+    invoke<BulkReport>('merge_prompts', { keepId, removeIds }),
+  pinPrompt: (id: string) => invoke<PromptMetadata>('pin_prompt', { id }),
+  unpinPrompt: (id: string) => invoke<PromptMetadata>('unpin_prompt', { id }),
+  reorderPrompts: (ids: string[]) => invoke<PromptMetadata[]>('reorder_prompts', { ids }),
   addFolder: (name: string) => invoke<string[]>('add_folder', { name }),
   renameFolder: (oldName: string, newName: string) =>
     invoke<string[]>('rename_folder', { old: oldName, new: newName }),
-  deleteFolder: (name: string) => invoke<string[]>('delete_folder', { name }),
-  searchPrompts: (query: string) => invoke<PromptMetadata[]>('search_prompts', { query }),
-  recordUsage: (id: string) => invoke<void>('record_usage', { id }),
+  updateFolderMeta: (name: string, icon: string | null, color: string | null, description: string) =>
+    invoke<FolderMeta>('update_folder_meta', { name, icon, color, description }),
+  reorderFolders: (parent: string, order: string[]) =>
+    invoke<FolderNode>('reorder_folders', { parent, order }),
+  moveFolder: (name: string, newParent: string) =>
+    invoke<string[]>('move_folder', { name, newParent }),
+  deleteFolder: (name: string, mode?: FolderDeleteMode) =>
+    invoke<string[]>('delete_folder', { name, mode }),
+  renderPrompt: (id: string, variables: Record<string, string>) =>
+    invoke<RenderedPrompt>('render_prompt', { id, variables }),
+  getPasteContent: (id: string) => invoke<string>('get_paste_content', { id }),
+  validatePromptTemplate: (content: string, id?: string) =>
+    invoke<TemplateError[]>('validate_prompt_template', { id, content }),
+  getPromptDependencies: (id: string) =>
+    invoke<PromptDependencies>('get_prompt_dependencies', { id }),
+  searchPrompts: (query: string) => invoke<SearchHit[]>('search_prompts', { query }),
+  createSavedSearch: (search: SavedSearch) => invoke<SavedSearch>('create_saved_search', { search }),
+  listSavedSearches: () => invoke<SavedSearch[]>('list_saved_searches'),
+  runSavedSearch: (name: string) => invoke<SearchHit[]>('run_saved_search', { name }),
+  deleteSavedSearch: (name: string) => invoke<void>('delete_saved_search', { name }),
+  recordUsage: (id: string, deliveryMode?: DeliveryMode, variablesFilled?: boolean) =>
+    invoke<void>('record_usage', { id, deliveryMode, variablesFilled }),
+
+  // Analytics
+  getDailyUsage: (days?: number) => invoke<UsageBucket[]>('get_daily_usage', { days }),
+  getWeeklyUsage: (weeks?: number) => invoke<UsageBucket[]>('get_weekly_usage', { weeks }),
+  getTopPrompts: (limit?: number, days?: number) =>
+    invoke<PromptUsageStat[]>('get_top_prompts', { limit, days }),
+  getNeverUsedPrompts: () => invoke<PromptUsageStat[]>('get_never_used_prompts'),
+  getStalePrompts: (days?: number) => invoke<PromptUsageStat[]>('get_stale_prompts', { days }),
+  compactUsageLog: () => invoke<number>('compact_usage_log'),
+
+  // Quick add
+  getQuickAddCapture: () => invoke<string | null>('get_quick_add_capture'),
+  quickAddPrompt: (content: string, name?: string, folder?: string) =>
+    invoke<PromptMetadata>('quick_add_prompt', { content, name, folder }),
+
+  // Launch
+  takePendingSearch: () => invoke<string | null>('take_pending_search'),
+  takePendingOpenPrompt: () => invoke<string | null>('take_pending_open_prompt'),
+  getPendingDeepLink: () => invoke<PendingDeepLink | null>('get_pending_deep_link'),
+  confirmDeepLink: () => invoke<PromptMetadata | null>('confirm_deep_link'),
+  cancelDeepLink: () => invoke<void>('cancel_deep_link'),
 
   // Window
   pasteAndDismiss: (text: string) => invoke<void>('paste_and_dismiss', { text }),
@@ -31,12 +108,17 @@ export const api = {
   // Hotkey
   getCurrentHotkey: () => invoke<string>('get_current_hotkey'),
   setHotkey: (hotkey?: string) => invoke<string>('set_hotkey', { hotkey }),
+  getHotkeyBindings: () => invoke<HotkeyBinding[]>('get_hotkey_bindings'),
+  getHotkeyFallback: () => invoke<HotkeyFallback | null>('get_hotkey_fallback'),
+  setHotkeyBindings: (bindings: HotkeyBinding[]) =>
+    invoke<HotkeyReport>('set_hotkey_bindings', { bindings }),
   pauseHotkey: () => invoke<void>('pause_hotkey'),
-  resumeHotkey: () => invoke<void>('resume_hotkey'),
+  resumeHotkey: () => invoke<HotkeyReport>('resume_hotkey'),
 
   // Settings
   getSettings: () => invoke<AppSettings>('get_settings'),
   saveSettings: (settings: AppSettings) => invoke<AppSettings>('save_settings', { settings }),
+  regenerateApiToken: () => invoke<AppSettings>('regenerate_api_token'),
   getAutoLaunchEnabled: () => isAutoStartEnabled(),
   setAutoLaunchEnabled: async (enabled: boolean) => {
     if (enabled) {
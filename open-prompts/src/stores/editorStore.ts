@@ -1,5 +1,12 @@
 import { create } from 'zustand';
-import type { PromptMetadata, Prompt } from '../types';
+import type {
+  FolderDeleteMode,
+  FolderNode,
+  PromptMetadata,
+  Prompt,
+  SavedSearch,
+  TemplateError,
+} from '../types';
 import { api } from '../services/ipc';
 
 let autoSaveTimer: ReturnType<typeof setTimeout> | null = null;
@@ -22,37 +29,68 @@ function scheduleAutoSave(saveFn: () => Promise<void>) {
 interface EditorState {
   prompts: PromptMetadata[];
   folders: string[];
+  folderTree: FolderNode | undefined;
   activePromptId: string | undefined;
   activePrompt: Prompt | undefined;
   dirty: boolean;
   saveStatus: 'idle' | 'saving' | 'saved' | 'error';
   folderFilter: string | undefined;
+  savedSearches: SavedSearch[];
+  /** Name of the saved search listed instead of a folder, and its results */
+  savedSearchFilter: string | undefined;
+  savedSearchResults: PromptMetadata[];
+  /** Template problems found when the active prompt was last saved */
+  templateErrors: TemplateError[];
 
   loadInitial: () => Promise<void>;
   selectPrompt: (id: string) => Promise<void>;
   updateActive: (fields: Partial<Prompt>) => void;
   saveActive: () => Promise<void>;
   deleteActive: () => Promise<void>;
+  duplicateActive: () => Promise<void>;
+  togglePinned: (id: string) => Promise<void>;
+  reorderPrompts: (ids: string[]) => Promise<void>;
   createPrompt: (folder?: string) => void;
   addFolder: (name: string) => Promise<void>;
   renameFolder: (oldName: string, newName: string) => Promise<void>;
-  deleteFolder: (name: string) => Promise<void>;
+  moveFolder: (name: string, newParent: string) => Promise<void>;
+  updateFolderMeta: (
+    name: string,
+    icon: string | null,
+    color: string | null,
+    description: string,
+  ) => Promise<void>;
+  reorderFolders: (parent: string, order: string[]) => Promise<void>;
+  deleteFolder: (name: string, mode?: FolderDeleteMode) => Promise<void>;
   setFolderFilter: (folder: string | undefined) => void;
+  runSavedSearch: (name: string) => Promise<void>;
 }
 
 export const useEditorStore = create<EditorState>((set, get) => ({
   prompts: [],
   folders: [],
+  folderTree: undefined,
   activePromptId: undefined,
   activePrompt: undefined,
   dirty: false,
   saveStatus: 'idle',
   folderFilter: undefined,
+  savedSearches: [],
+  savedSearchFilter: undefined,
+  savedSearchResults: [],
+  templateErrors: [],
 
   loadInitial: async () => {
     try {
-      const index = await api.getIndex();
-      set({ prompts: index.prompts, folders: index.folders });
+      const [index, folderTree] = await Promise.all([api.getIndex(), api.getFolderTree()]);
+      set({
+        prompts: index.prompts,
+        folders: index.folders,
+        folderTree,
+        savedSearches: index.savedSearches ?? [],
+      });
+      const { savedSearchFilter } = get();
+      if (savedSearchFilter) await get().runSavedSearch(savedSearchFilter);
     } catch (e) {
       console.error('Failed to load index:', e);
     }
@@ -65,7 +103,13 @@ export const useEditorStore = create<EditorState>((set, get) => ({
         await get().saveActive();
       }
       const prompt = await api.getPrompt(id);
-      set({ activePromptId: id, activePrompt: prompt, dirty: false, saveStatus: 'idle' });
+      set({
+        activePromptId: id,
+        activePrompt: prompt,
+        dirty: false,
+        saveStatus: 'idle',
+        templateErrors: [],
+      });
     } catch (e) {
       console.error('Failed to load prompt:', e);
     }
@@ -91,6 +135,9 @@ export const useEditorStore = create<EditorState>((set, get) => ({
     try {
       const saved = await api.savePrompt(activePrompt);
       const index = await api.getIndex();
+      const templateErrors = await api
+        .validatePromptTemplate(activePrompt.content, saved.id)
+        .catch(() => [] as TemplateError[]);
       const currentActive = get().activePrompt;
       set({
         prompts: index.prompts,
@@ -109,6 +156,7 @@ export const useEditorStore = create<EditorState>((set, get) => ({
           : currentActive,
         dirty: false,
         saveStatus: 'saved',
+        templateErrors,
       });
       // Reset status after 2s
       setTimeout(() => {
@@ -139,6 +187,42 @@ export const useEditorStore = create<EditorState>((set, get) => ({
     }
   },
 
+  duplicateActive: async () => {
+    const { activePromptId } = get();
+    if (!activePromptId) return;
+    try {
+      const copy = await api.duplicatePrompt(activePromptId);
+      await get().loadInitial();
+      await get().selectPrompt(copy.id);
+    } catch (e) {
+      console.error('Failed to duplicate prompt:', e);
+    }
+  },
+
+  togglePinned: async (id: string) => {
+    const prompt = get().prompts.find((p) => p.id === id);
+    if (!prompt) return;
+    try {
+      const meta = prompt.pinned ? await api.unpinPrompt(id) : await api.pinPrompt(id);
+      set((state) => ({
+        prompts: state.prompts.map((p) => (p.id === id ? meta : p)),
+        activePrompt:
+          state.activePrompt?.id === id ? { ...state.activePrompt, pinned: meta.pinned } : state.activePrompt,
+      }));
+    } catch (e) {
+      console.error('Failed to pin prompt:', e);
+    }
+  },
+
+  reorderPrompts: async (ids: string[]) => {
+    try {
+      const prompts = await api.reorderPrompts(ids);
+      set({ prompts });
+    } catch (e) {
+      console.error('Failed to reorder prompts:', e);
+    }
+  },
+
   createPrompt: (folder?: string) => {
     const now = new Date().toISOString();
     const newPrompt: Prompt = {
@@ -151,40 +235,88 @@ export const useEditorStore = create<EditorState>((set, get) => ({
       lastUsed: null,
       created: now,
       updated: now,
+      pinned: false,
+      tags: [],
       content: '',
     };
-    set({ activePrompt: newPrompt, activePromptId: undefined, dirty: true, saveStatus: 'idle' });
+    set({
+      activePrompt: newPrompt,
+      activePromptId: undefined,
+      dirty: true,
+      saveStatus: 'idle',
+      templateErrors: [],
+    });
   },
 
   addFolder: async (name: string) => {
     try {
-      const folders = await api.addFolder(name);
-      set({ folders });
+      await api.addFolder(name);
+      await get().loadInitial();
     } catch (e) {
       console.error('Failed to add folder:', e);
     }
   },
 
+  // Renames, moves and deletes change the folders of every prompt below, so reload everything
   renameFolder: async (oldName: string, newName: string) => {
     try {
-      const folders = await api.renameFolder(oldName, newName);
-      set({ folders });
+      await api.renameFolder(oldName, newName);
+      await get().loadInitial();
     } catch (e) {
       console.error('Failed to rename folder:', e);
     }
   },
 
-  deleteFolder: async (name: string) => {
+  moveFolder: async (name: string, newParent: string) => {
     try {
-      const folders = await api.deleteFolder(name);
-      const index = await api.getIndex();
-      set({ folders, prompts: index.prompts });
+      await api.moveFolder(name, newParent);
+      await get().loadInitial();
+    } catch (e) {
+      console.error('Failed to move folder:', e);
+    }
+  },
+
+  updateFolderMeta: async (name, icon, color, description) => {
+    try {
+      await api.updateFolderMeta(name, icon, color, description);
+      set({ folderTree: await api.getFolderTree() });
+    } catch (e) {
+      console.error('Failed to update folder:', e);
+    }
+  },
+
+  reorderFolders: async (parent: string, order: string[]) => {
+    try {
+      const folderTree = await api.reorderFolders(parent, order);
+      set({ folderTree });
+    } catch (e) {
+      console.error('Failed to reorder folders:', e);
+    }
+  },
+
+  deleteFolder: async (name: string, mode?: FolderDeleteMode) => {
+    try {
+      await api.deleteFolder(name, mode);
+      await get().loadInitial();
     } catch (e) {
       console.error('Failed to delete folder:', e);
     }
   },
 
   setFolderFilter: (folder: string | undefined) => {
-    set({ folderFilter: folder });
+    set({ folderFilter: folder, savedSearchFilter: undefined, savedSearchResults: [] });
+  },
+
+  runSavedSearch: async (name: string) => {
+    try {
+      const hits = await api.runSavedSearch(name);
+      set({
+        folderFilter: undefined,
+        savedSearchFilter: name,
+        savedSearchResults: hits.map((hit) => hit.meta),
+      });
+    } catch (e) {
+      console.error('Failed to run saved search:', e);
+    }
   },
 }));
\ No newline at end of file
@@ -1,10 +1,10 @@
 import { create } from 'zustand';
-import type { PromptMetadata } from '../types';
+import type { SearchHit } from '../types';
 import { api } from '../services/ipc';
 
 interface LauncherState {
   query: string;
-  results: PromptMetadata[];
+  results: SearchHit[];
   selectedIndex: number;
   isLoading: boolean;
 
@@ -43,13 +43,13 @@ export const useLauncherStore = create<LauncherState>((set, get) => ({
 
   pasteSelected: async () => {
     const { results, selectedIndex } = get();
-    const selected = results[selectedIndex];
+    const selected = results[selectedIndex]?.meta;
     if (!selected) return;
 
     try {
-      const prompt = await api.getPrompt(selected.id);
-      await api.recordUsage(selected.id);
-      await api.pasteAndDismiss(prompt.content);
+      const content = await api.getPasteContent(selected.id);
+      await api.recordUsage(selected.id, 'paste');
+      await api.pasteAndDismiss(content);
       // Reset state for next invocation
       set({ query: '', results: [], selectedIndex: 0 });
     } catch (e) {
@@ -59,12 +59,13 @@ export const useLauncherStore = create<LauncherState>((set, get) => ({
 
   copySelected: async () => {
     const { results, selectedIndex } = get();
-    const selected = results[selectedIndex];
+    const selected = results[selectedIndex]?.meta;
     if (!selected) return;
 
     try {
-      const prompt = await api.getPrompt(selected.id);
-      await api.copyToClipboard(prompt.content);
+      const content = await api.getPasteContent(selected.id);
+      await api.copyToClipboard(content);
+      await api.recordUsage(selected.id, 'copy');
     } catch (e) {
       console.error('Copy failed:', e);
     }
@@ -10,22 +10,143 @@ export interface PromptMetadata {
   updated: string;
   icon?: string;
   color?: string;
+  pinned: boolean;
+  sortIndex?: number | null;
+  tags: string[];
 }
 
 export interface Prompt extends PromptMetadata {
   content: string;
 }
 
+export type UsageFilter = { kind: 'any' } | { kind: 'never' } | { kind: 'withinDays'; days: number };
+
+export type SavedSearchSort = 'relevance' | 'recentlyUsed' | 'mostUsed' | 'recentlyUpdated' | 'name';
+
+/** A named query listed like a folder, e.g. "tagged #review in Engineering/" */
+export interface SavedSearch {
+  name: string;
+  query: string;
+  tags: string[];
+  folderPrefix: string;
+  usage: UsageFilter;
+  sort: SavedSearchSort;
+}
+
+export type SearchField = 'name' | 'description' | 'folder' | 'body';
+
+/** Matched characters `start..end`, counted in code points (use `Array.from` on the text) */
+export interface MatchRange {
+  start: number;
+  end: number;
+}
+
+export interface FieldMatch {
+  field: SearchField;
+  /** For `body`, these index into the hit's snippet */
+  ranges: MatchRange[];
+}
+
+export interface SearchHit {
+  meta: PromptMetadata;
+  score: number;
+  matches: FieldMatch[];
+  snippet: string | null;
+}
+
+export interface BulkItemResult {
+  id: string;
+  error: string | null;
+}
+
+/** Bulk operations change every prompt or none; `applied` says which */
+export interface BulkReport {
+  applied: boolean;
+  items: BulkItemResult[];
+}
+
+export interface PromptExport extends BulkReport {
+  exportedAt: string;
+  prompts: Prompt[];
+}
+
+/** Prompts with the same or nearly the same content, most used first */
+export interface DuplicateGroup {
+  exact: boolean;
+  /** 0–1; 1 for exact duplicates */
+  similarity: number;
+  prompts: PromptMetadata[];
+}
+
+export interface RenderedPrompt {
+  id: string;
+  content: string;
+  missingVariables: string[];
+}
+
+export interface FolderNode {
+  name: string;
+  path: string;
+  promptCount: number;
+  totalPromptCount: number;
+  meta: FolderMeta | null;
+  children: FolderNode[];
+}
+
+export type FolderDeleteMode = 'moveToRoot' | 'moveToParent' | 'deleteContents';
+
+export interface TemplateError {
+  message: string;
+  line: number;
+  column: number;
+}
+
+export interface PromptDependencies {
+  id: string;
+  includes: PromptMetadata[];
+  unresolved: string[];
+  includedBy: PromptMetadata[];
+}
+
+export type DeliveryMode = 'paste' | 'copy';
+
+export type DeepLink =
+  | { action: 'open'; id: string }
+  | { action: 'paste'; id: string; variables: Record<string, string> }
+  | { action: 'import'; path: string };
+
+export interface PendingDeepLink {
+  link: DeepLink;
+  summary: string;
+}
+
+export interface UsageBucket {
+  period: string;
+  count: number;
+}
+
+export interface PromptUsageStat {
+  id: string;
+  name: string;
+  folder: string;
+  count: number;
+  lastUsed: string | null;
+}
+
 export interface FolderMeta {
   name: string;
-  icon?: string;
-  color?: string;
+  icon?: string | null;
+  color?: string | null;
+  description: string;
+  sortIndex?: number | null;
 }
 
 export interface PromptIndex {
+  version: number;
   prompts: PromptMetadata[];
   folders: string[];
   folderMeta?: Record<string, FolderMeta>;
+  savedSearches: SavedSearch[];
   seeded: boolean;
 }
 
@@ -34,6 +155,8 @@ export interface GeneralSettings {
   hotkey: string;
   editorAlwaysOnTop: boolean;
   welcomeScreenDismissed: boolean;
+  lastKnownGoodHotkey?: string | null;
+  quickAddFolder: string;
 }
 
 export interface AppearanceSettings {
@@ -41,7 +164,66 @@ export interface AppearanceSettings {
   accentColor: string;
 }
 
+export interface SearchSettings {
+  frecencyHalfLifeDays: number;
+  frecencyWeight: number;
+  contextWeight: number;
+  maxEventsPerPrompt: number;
+  semantic: boolean;
+  semanticWeight: number;
+  semanticMinSimilarity: number;
+}
+
+export interface PromptHotkey {
+  accelerator: string;
+  promptId: string;
+}
+
+export interface HotkeySettings {
+  quickAdd: string | null;
+  pasteLastUsed: string | null;
+  prompts: PromptHotkey[];
+}
+
+export interface ApiSettings {
+  enabled: boolean;
+  port: number;
+  token: string;
+}
+
 export interface AppSettings {
+  version: number;
   general: GeneralSettings;
   appearance: AppearanceSettings;
+  search: SearchSettings;
+  hotkeys: HotkeySettings;
+  api: ApiSettings;
+}
+
+export type HotkeyAction =
+  | { type: 'toggleLauncher' }
+  | { type: 'quickAdd' }
+  | { type: 'pasteLastUsed' }
+  | { type: 'pastePrompt'; promptId: string };
+
+export interface HotkeyBinding {
+  accelerator: string;
+  action: HotkeyAction;
+}
+
+export interface HotkeyFailure {
+  binding: HotkeyBinding;
+  error: string;
+}
+
+export interface HotkeyFallback {
+  requested: string;
+  active: string | null;
+  error: string;
+}
+
+export interface HotkeyReport {
+  registered: HotkeyBinding[];
+  failures: HotkeyFailure[];
+  rolledBack: boolean;
 }
\ No newline at end of file
@@ -0,0 +1,83 @@
+import { useCallback, useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { api } from '../../services/ipc';
+import { useSettingsStore } from '../../stores/settingsStore';
+import type { PendingDeepLink } from '../../types';
+import '../../styles/editor.css';
+
+export function ConfirmApp() {
+  const { settings, load } = useSettingsStore();
+  const [pending, setPending] = useState<PendingDeepLink | null>(null);
+  const [error, setError] = useState<string | null>(null);
+  const [busy, setBusy] = useState(false);
+
+  useEffect(() => {
+    void load();
+    void api.getPendingDeepLink().then(setPending);
+
+    const unlisten = listen<PendingDeepLink>('deep-link-pending', (event) => {
+      setPending(event.payload);
+      setError(null);
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, [load]);
+
+  useEffect(() => {
+    if (settings) {
+      document.documentElement.setAttribute('data-theme', settings.appearance.theme);
+    }
+  }, [settings]);
+
+  const cancel = useCallback(async () => {
+    await api.cancelDeepLink();
+  }, []);
+
+  const confirm = useCallback(async () => {
+    setBusy(true);
+    try {
+      await api.confirmDeepLink();
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setBusy(false);
+    }
+  }, []);
+
+  useEffect(() => {
+    const handler = (event: KeyboardEvent) => {
+      if (event.key === 'Escape') {
+        event.preventDefault();
+        void cancel();
+      }
+    };
+    window.addEventListener('keydown', handler);
+    return () => window.removeEventListener('keydown', handler);
+  }, [cancel]);
+
+  const action = pending?.link.action === 'import' ? 'Import' : 'Paste';
+
+  return (
+    <div className="confirm-app">
+      <div className="confirm-summary">{pending?.summary ?? 'Nothing to confirm'}</div>
+      <div className="confirm-note">This was requested by a link. Only continue if you clicked it.</div>
+
+      {error && <div className="settings-warning">{error}</div>}
+
+      <div className="quick-add-actions">
+        <button className="btn-sm btn-ghost" onClick={() => void cancel()}>
+          Cancel
+        </button>
+        <button
+          className="btn-sm btn-primary"
+          onClick={() => void confirm()}
+          disabled={busy || !pending}
+          autoFocus
+        >
+          {action}
+        </button>
+      </div>
+    </div>
+  );
+}
@@ -1,4 +1,6 @@
 import { useEffect, useCallback, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { api } from '../../services/ipc';
 import { useEditorStore } from '../../stores/editorStore';
 import { useSettingsStore } from '../../stores/settingsStore';
 import { Sidebar } from './Sidebar';
@@ -8,7 +10,7 @@ import { Settings } from 'lucide-react';
 import '../../styles/editor.css';
 
 export function EditorApp() {
-  const { activePrompt, loadInitial, saveActive, createPrompt } = useEditorStore();
+  const { activePrompt, loadInitial, saveActive, createPrompt, selectPrompt } = useEditorStore();
   const { settings, load } = useSettingsStore();
   const [showSettings, setShowSettings] = useState(false);
 
@@ -16,6 +18,24 @@ export function EditorApp() {
     loadInitial();
   }, [loadInitial]);
 
+  // Prompts opened from an openprompts:// link
+  useEffect(() => {
+    void api
+      .takePendingOpenPrompt()
+      .then((id) => {
+        if (id) void selectPrompt(id);
+      })
+      .catch((e) => console.error('Failed to read pending prompt:', e));
+    const unlisten = listen<string>('open-prompt', (event) => {
+      setShowSettings(false);
+      // The prompt may have just been imported
+      void loadInitial().then(() => selectPrompt(event.payload));
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, [loadInitial, selectPrompt]);
+
   useEffect(() => {
     if (!settings) {
       void load();
@@ -1,10 +1,36 @@
+import { useEffect, useState } from 'react';
+import { api } from '../../services/ipc';
 import { useEditorStore } from '../../stores/editorStore';
+import type { PromptDependencies } from '../../types';
 import { MarkdownEditor } from './MarkdownEditor';
-import { Trash2 } from 'lucide-react';
+import { Copy, Pin, PinOff, Trash2 } from 'lucide-react';
 
 export function PromptForm() {
-  const { activePrompt, folders, dirty, saveStatus, updateActive, saveActive, deleteActive } =
-    useEditorStore();
+  const {
+    activePrompt,
+    folders,
+    dirty,
+    saveStatus,
+    templateErrors,
+    updateActive,
+    saveActive,
+    deleteActive,
+    duplicateActive,
+    togglePinned,
+  } = useEditorStore();
+  const [dependencies, setDependencies] = useState<PromptDependencies | null>(null);
+
+  const activeId = activePrompt?.id;
+  useEffect(() => {
+    if (!activeId || saveStatus === 'saving') {
+      setDependencies(null);
+      return;
+    }
+    api
+      .getPromptDependencies(activeId)
+      .then(setDependencies)
+      .catch(() => setDependencies(null));
+  }, [activeId, saveStatus]);
 
   if (!activePrompt) return null;
 
@@ -31,6 +57,20 @@ export function PromptForm() {
           <button className="toolbar-btn" onClick={saveActive}>
             Save
           </button>
+          {activePrompt.id && (
+            <button className="toolbar-btn" onClick={duplicateActive} title="Duplicate prompt">
+              <Copy size={14} />
+            </button>
+          )}
+          {activePrompt.id && (
+            <button
+              className="toolbar-btn"
+              onClick={() => togglePinned(activePrompt.id)}
+              title={activePrompt.pinned ? 'Unpin prompt' : 'Pin prompt'}
+            >
+              {activePrompt.pinned ? <PinOff size={14} /> : <Pin size={14} />}
+            </button>
+          )}
           <button className="toolbar-btn danger" onClick={deleteActive} title="Delete prompt">
             <Trash2 size={14} />
           </button>
@@ -54,6 +94,21 @@ export function PromptForm() {
             placeholder="Short description"
           />
         </div>
+        <div className="meta-field" style={{ flex: 1 }}>
+          <label>Tags</label>
+          <input
+            key={activePrompt.id || 'new'}
+            defaultValue={activePrompt.tags.join(', ')}
+            onBlur={(e) => {
+              const tags = e.target.value
+                .split(',')
+                .map((t) => t.trim())
+                .filter(Boolean);
+              if (tags.join(',') !== activePrompt.tags.join(',')) updateActive({ tags });
+            }}
+            placeholder="review, draft"
+          />
+        </div>
         <div className="meta-field" style={{ flex: 1 }}>
           <label>Folder</label>
           <select value={activePrompt.folder} onChange={(e) => updateActive({ folder: e.target.value })}>
@@ -67,6 +122,27 @@ export function PromptForm() {
         </div>
       </div>
 
+      {dependencies && (dependencies.includedBy.length > 0 || dependencies.unresolved.length > 0) && (
+        <div className="prompt-dependencies">
+          {dependencies.includedBy.length > 0 && (
+            <span>Included by {dependencies.includedBy.map((p) => p.name).join(', ')}</span>
+          )}
+          {dependencies.unresolved.length > 0 && (
+            <span className="unresolved">Missing includes: {dependencies.unresolved.join(', ')}</span>
+          )}
+        </div>
+      )}
+
+      {templateErrors.length > 0 && (
+        <div className="template-errors">
+          {templateErrors.map((e) => (
+            <div key={`${e.line}:${e.column}:${e.message}`}>
+              Line {e.line}, column {e.column}: {e.message}
+            </div>
+          ))}
+        </div>
+      )}
+
       <div className="editor-content">
         <MarkdownEditor key={activePrompt.id || 'new'} content={activePrompt.content} onChange={(content) => updateActive({ content })} />
       </div>
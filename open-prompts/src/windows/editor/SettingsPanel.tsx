@@ -1,8 +1,9 @@
 import { useCallback, useEffect, useState } from 'react';
-import { getCurrentWindow } from '@tauri-apps/api/window';
-import { Keyboard, Palette, Pin, Play, Power, Settings } from 'lucide-react';
+import { listen } from '@tauri-apps/api/event';
+import { Globe, Keyboard, Palette, Pin, Play, Power, Settings } from 'lucide-react';
 import { api } from '../../services/ipc';
 import { useSettingsStore } from '../../stores/settingsStore';
+import type { HotkeyFallback } from '../../types';
 
 function formatAccelerator(event: KeyboardEvent): string {
   const modifiers: string[] = [];
@@ -23,11 +24,25 @@ function formatAccelerator(event: KeyboardEvent): string {
 }
 
 export function SettingsPanel() {
-  const { settings, save } = useSettingsStore();
+  const { settings, save, load } = useSettingsStore();
   const [isRecording, setIsRecording] = useState(false);
   const [recordedHotkey, setRecordedHotkey] = useState('');
+  const [hotkeyError, setHotkeyError] = useState<string | null>(null);
+  const [hotkeyFallback, setHotkeyFallback] = useState<HotkeyFallback | null>(null);
   const [isQuitting, setIsQuitting] = useState(false);
 
+  useEffect(() => {
+    void api.getHotkeyFallback().then(setHotkeyFallback).catch((e) => {
+      console.error('Failed to load hotkey fallback state:', e);
+    });
+    const unlisten = listen<HotkeyFallback>('hotkey-fallback', (event) => {
+      setHotkeyFallback(event.payload);
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, []);
+
   const startRecording = useCallback(async () => {
     try {
       await api.pauseHotkey();
@@ -52,26 +67,23 @@ export function SettingsPanel() {
     if (!settings || !recordedHotkey) return;
 
     try {
+      // set_hotkey registers and persists the hotkey itself
       await api.setHotkey(recordedHotkey);
-      await save({
-        ...settings,
-        general: {
-          ...settings.general,
-          hotkey: recordedHotkey,
-        },
-      });
-      await api.resumeHotkey();
+      await load();
       setIsRecording(false);
       setRecordedHotkey('');
+      setHotkeyError(null);
+      setHotkeyFallback(null);
     } catch (e) {
       console.error('Failed to save hotkey:', e);
+      setHotkeyError(String(e));
       try {
         await api.resumeHotkey();
       } catch (resumeError) {
         console.error('Failed to resume hotkey after save failure:', resumeError);
       }
     }
-  }, [recordedHotkey, save, settings]);
+  }, [load, recordedHotkey, settings]);
 
   useEffect(() => {
     if (!isRecording) return;
@@ -141,7 +153,6 @@ export function SettingsPanel() {
     async (value: boolean) => {
       if (!settings) return;
       try {
-        await getCurrentWindow().setAlwaysOnTop(value);
         await save({
           ...settings,
           general: {
@@ -160,7 +171,6 @@ export function SettingsPanel() {
     async (value: boolean) => {
       if (!settings) return;
       try {
-        await api.setAutoLaunchEnabled(value);
         await save({
           ...settings,
           general: {
@@ -175,6 +185,29 @@ export function SettingsPanel() {
     [save, settings],
   );
 
+  const handleApiEnabled = useCallback(
+    async (value: boolean) => {
+      if (!settings) return;
+      await save({
+        ...settings,
+        api: {
+          ...settings.api,
+          enabled: value,
+        },
+      });
+    },
+    [save, settings],
+  );
+
+  const handleRegenerateToken = useCallback(async () => {
+    try {
+      const next = await api.regenerateApiToken();
+      useSettingsStore.setState({ settings: next });
+    } catch (e) {
+      console.error('Failed to regenerate API token:', e);
+    }
+  }, []);
+
   const handleQuitApp = useCallback(async () => {
     try {
       setIsQuitting(true);
@@ -219,6 +252,14 @@ export function SettingsPanel() {
             </button>
           </div>
         )}
+        {hotkeyError && <div className="settings-warning">{hotkeyError}</div>}
+        {hotkeyFallback && (
+          <div className="settings-warning">
+            {hotkeyFallback.active
+              ? `${hotkeyFallback.requested} could not be registered; using ${hotkeyFallback.active} instead.`
+              : `${hotkeyFallback.requested} could not be registered; no launcher hotkey is active.`}
+          </div>
+        )}
       </div>
 
       <div className="settings-section">
@@ -269,6 +310,29 @@ export function SettingsPanel() {
         </label>
       </div>
 
+      <div className="settings-section">
+        <label className="settings-label">
+          <Globe size={14} />
+          Local API
+        </label>
+        <label className="settings-toggle">
+          <input
+            type="checkbox"
+            checked={settings.api.enabled}
+            onChange={(event) => void handleApiEnabled(event.target.checked)}
+          />
+          <span>Serve prompts to scripts on http://127.0.0.1:{settings.api.port}</span>
+        </label>
+        {settings.api.enabled && settings.api.token && (
+          <div className="hotkey-recorder">
+            <div className="hotkey-display">{settings.api.token}</div>
+            <button className="btn-sm btn-ghost" onClick={() => void handleRegenerateToken()}>
+              Regenerate
+            </button>
+          </div>
+        )}
+      </div>
+
       <div className="settings-section">
         <label className="settings-label">
           <Power size={14} />
@@ -1,22 +1,42 @@
 import { useState } from 'react';
 import { useEditorStore } from '../../stores/editorStore';
-import { FolderOpen, Plus, FileText } from 'lucide-react';
+import type { FolderNode } from '../../types';
+import { FolderOpen, Plus, FileText, Pin, Search } from 'lucide-react';
+
+/** Folders in display order, each with its depth in the tree. */
+function flattenTree(node: FolderNode | undefined, depth = 0): { node: FolderNode; depth: number }[] {
+  if (!node) return [];
+  return node.children.flatMap((child) => [{ node: child, depth }, ...flattenTree(child, depth + 1)]);
+}
 
 export function Sidebar() {
   const {
     prompts,
-    folders,
+    folderTree,
     activePromptId,
     folderFilter,
+    savedSearchFilter,
+    savedSearchResults,
     selectPrompt,
     createPrompt,
     addFolder,
     setFolderFilter,
+    savedSearches,
+    runSavedSearch,
   } = useEditorStore();
   const [newFolderName, setNewFolderName] = useState('');
   const [showNewFolder, setShowNewFolder] = useState(false);
 
-  const filteredPrompts = folderFilter ? prompts.filter((p) => p.folder === folderFilter) : prompts;
+  // A saved search keeps its own order; a folder shows its own prompts and those of its subfolders
+  const filteredPrompts = savedSearchFilter ? savedSearchResults : (
+    folderFilter
+      ? prompts.filter((p) => p.folder === folderFilter || p.folder.startsWith(`${folderFilter}/`))
+      : [...prompts]
+  ).sort(
+    (a, b) =>
+      Number(b.pinned) - Number(a.pinned) ||
+      (a.sortIndex ?? Number.MAX_SAFE_INTEGER) - (b.sortIndex ?? Number.MAX_SAFE_INTEGER),
+  );
 
   const handleAddFolder = async () => {
     if (newFolderName.trim()) {
@@ -46,20 +66,33 @@ export function Sidebar() {
 
       <div className="sidebar-folders">
         <button
-          className={`folder-item ${!folderFilter ? 'active' : ''}`}
+          className={`folder-item ${!folderFilter && !savedSearchFilter ? 'active' : ''}`}
           onClick={() => setFolderFilter(undefined)}
         >
           <FileText size={14} />
           All Prompts ({prompts.length})
         </button>
-        {folders.map((f) => (
+        {flattenTree(folderTree).map(({ node, depth }) => (
+          <button
+            key={node.path}
+            className={`folder-item ${folderFilter === node.path ? 'active' : ''}`}
+            style={{ paddingLeft: 8 + depth * 14 }}
+            onClick={() => setFolderFilter(node.path)}
+            title={node.meta?.description || node.path}
+          >
+            <FolderOpen size={14} color={node.meta?.color ?? undefined} />
+            {node.name} ({node.totalPromptCount})
+          </button>
+        ))}
+        {savedSearches.map((search) => (
           <button
-            key={f}
-            className={`folder-item ${folderFilter === f ? 'active' : ''}`}
-            onClick={() => setFolderFilter(f)}
+            key={search.name}
+            className={`folder-item ${savedSearchFilter === search.name ? 'active' : ''}`}
+            onClick={() => runSavedSearch(search.name)}
+            title={search.query || search.name}
           >
-            <FolderOpen size={14} />
-            {f} ({prompts.filter((p) => p.folder === f).length})
+            <Search size={14} />
+            {search.name}
           </button>
         ))}
         {showNewFolder && (
@@ -97,13 +130,16 @@ export function Sidebar() {
             className={`prompt-item ${activePromptId === p.id ? 'active' : ''}`}
             onClick={() => selectPrompt(p.id)}
           >
-            <span className="prompt-item-name">{p.name}</span>
+            <span className="prompt-item-name">
+              {p.pinned && <Pin size={11} className="prompt-item-pin" />}
+              {p.name}
+            </span>
             {p.description && <span className="prompt-item-desc">{p.description}</span>}
           </button>
         ))}
         {filteredPrompts.length === 0 && (
           <div style={{ padding: 16, textAlign: 'center', color: 'var(--text-muted)', fontSize: 13 }}>
-            No prompts{folderFilter ? ` in "${folderFilter}"` : ''}
+            No prompts{folderFilter ? ` in "${folderFilter}"` : savedSearchFilter ? ` for "${savedSearchFilter}"` : ''}
           </div>
         )}
       </div>
@@ -1,4 +1,5 @@
 import { useEffect, useRef } from 'react';
+import { api } from '../../services/ipc';
 import { useLauncherStore } from '../../stores/launcherStore';
 import { SearchInput } from './SearchInput';
 import { ResultList } from './ResultList';
@@ -20,8 +21,12 @@ export function LauncherApp() {
   // Also refresh when the window becomes visible again (re-triggered by Tauri show)
   useEffect(() => {
     const handleFocus = () => {
-      // Reset query and refresh on each launcher activation
-      setQuery('');
+      // Reset query and refresh on each launcher activation, unless a
+      // `--search` launch left a query behind
+      void api
+        .takePendingSearch()
+        .catch(() => null)
+        .then((pending) => setQuery(pending ?? ''));
     };
     window.addEventListener('focus', handleFocus);
     return () => window.removeEventListener('focus', handleFocus);
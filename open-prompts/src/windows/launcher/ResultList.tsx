@@ -1,8 +1,27 @@
 import { useEffect, useRef, useState } from 'react';
 import { FileText } from 'lucide-react';
 import { useLauncherStore } from '../../stores/launcherStore';
+import type { SearchField, SearchHit } from '../../types';
 import { ContextMenu } from './ContextMenu';
 
+/** `text` with the characters the search matched in `field` in bold. */
+function Highlighted({ hit, field, text }: { hit: SearchHit; field: SearchField; text: string }) {
+  const ranges = hit.matches.find((m) => m.field === field)?.ranges ?? [];
+  if (ranges.length === 0) return <>{text}</>;
+
+  // Ranges count code points, not UTF-16 units
+  const chars = Array.from(text);
+  const parts = [];
+  let pos = 0;
+  for (const { start, end } of ranges) {
+    if (start > pos) parts.push(chars.slice(pos, start).join(''));
+    parts.push(<mark key={start}>{chars.slice(start, end).join('')}</mark>);
+    pos = end;
+  }
+  if (pos < chars.length) parts.push(chars.slice(pos).join(''));
+  return <>{parts}</>;
+}
+
 export function ResultList() {
   const { results, selectedIndex, pasteSelected } = useLauncherStore();
   const listRef = useRef<HTMLDivElement>(null);
@@ -17,9 +36,9 @@ export function ResultList() {
 
   return (
     <div className="launcher-results" ref={listRef}>
-      {results.map((prompt, index) => (
+      {results.map((hit, index) => (
         <button
-          key={prompt.id}
+          key={hit.meta.id}
           className={`result-item ${index === selectedIndex ? 'selected' : ''}`}
           onClick={() => {
             useLauncherStore.setState({ selectedIndex: index });
@@ -38,11 +57,26 @@ export function ResultList() {
             <FileText size={16} />
           </div>
           <div className="result-text">
-            <div className="result-name">{prompt.name}</div>
-            {prompt.description && <div className="result-desc">{prompt.description}</div>}
+            <div className="result-name">
+              <Highlighted hit={hit} field="name" text={hit.meta.name} />
+            </div>
+            {hit.meta.description && (
+              <div className="result-desc">
+                <Highlighted hit={hit} field="description" text={hit.meta.description} />
+              </div>
+            )}
+            {hit.snippet && hit.matches.some((m) => m.field === 'body') && (
+              <div className="result-snippet">
+                <Highlighted hit={hit} field="body" text={hit.snippet} />
+              </div>
+            )}
           </div>
           <div className="result-meta">
-            {prompt.folder && <span className="result-folder-badge">{prompt.folder}</span>}
+            {hit.meta.folder && (
+              <span className="result-folder-badge">
+                <Highlighted hit={hit} field="folder" text={hit.meta.folder} />
+              </span>
+            )}
           </div>
         </button>
       ))}
@@ -41,6 +41,7 @@ export function SearchInput() {
         onChange={e => handleChange(e.target.value)}
         autoComplete="off"
         spellCheck={false}
+        title={'Filters: folder:Writing  tag:review  used:>5  updated:<30d  "exact phrase"  -exclude  in:body'}
       />
     </div>
   );
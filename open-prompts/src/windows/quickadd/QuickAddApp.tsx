@@ -0,0 +1,129 @@
+import { useCallback, useEffect, useState } from 'react';
+import { listen } from '@tauri-apps/api/event';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { api } from '../../services/ipc';
+import { useSettingsStore } from '../../stores/settingsStore';
+import '../../styles/editor.css';
+
+function firstLine(content: string): string {
+  const line = content.split('\n').find((l) => l.trim() !== '') ?? '';
+  return line.replace(/^[#>*\-\s]+/, '').replace(/\*+$/, '').trim().slice(0, 60);
+}
+
+export function QuickAddApp() {
+  const { settings, load } = useSettingsStore();
+  const [content, setContent] = useState('');
+  const [name, setName] = useState('');
+  const [folder, setFolder] = useState<string | null>(null);
+  const [folders, setFolders] = useState<string[]>([]);
+  const [error, setError] = useState<string | null>(null);
+  const [saving, setSaving] = useState(false);
+
+  useEffect(() => {
+    void load();
+    void api.getFolders().then(setFolders).catch((e) => console.error('Failed to load folders:', e));
+    void api.getQuickAddCapture().then((text) => {
+      if (text) setContent(text);
+    });
+
+    const unlisten = listen<string>('quick-add-captured', (event) => {
+      setContent(event.payload);
+      setName('');
+      setError(null);
+    });
+    return () => {
+      void unlisten.then((fn) => fn());
+    };
+  }, [load]);
+
+  useEffect(() => {
+    if (settings) {
+      document.documentElement.setAttribute('data-theme', settings.appearance.theme);
+    }
+  }, [settings]);
+
+  const close = useCallback(async () => {
+    setContent('');
+    setName('');
+    setError(null);
+    await getCurrentWindow().close();
+  }, []);
+
+  const save = useCallback(async () => {
+    setSaving(true);
+    try {
+      await api.quickAddPrompt(content, name || undefined, folder ?? undefined);
+      await close();
+    } catch (e) {
+      setError(String(e));
+    } finally {
+      setSaving(false);
+    }
+  }, [close, content, folder, name]);
+
+  useEffect(() => {
+    const handler = (event: KeyboardEvent) => {
+      if (event.key === 'Escape') {
+        event.preventDefault();
+        void close();
+      }
+      if (event.ctrlKey && event.key === 'Enter') {
+        event.preventDefault();
+        void save();
+      }
+    };
+    window.addEventListener('keydown', handler);
+    return () => window.removeEventListener('keydown', handler);
+  }, [close, save]);
+
+  const selectedFolder = folder ?? settings?.general.quickAddFolder ?? '';
+
+  return (
+    <div className="quick-add-app">
+      <div className="editor-meta">
+        <div className="meta-field" style={{ flex: 2 }}>
+          <label>Name</label>
+          <input
+            value={name}
+            onChange={(e) => setName(e.target.value)}
+            placeholder={firstLine(content) || 'Prompt name'}
+          />
+        </div>
+        <div className="meta-field" style={{ flex: 1 }}>
+          <label>Folder</label>
+          <select value={selectedFolder} onChange={(e) => setFolder(e.target.value)}>
+            <option value="">Uncategorized</option>
+            {folders.map((f) => (
+              <option key={f} value={f}>
+                {f}
+              </option>
+            ))}
+          </select>
+        </div>
+      </div>
+
+      <textarea
+        className="quick-add-content"
+        value={content}
+        onChange={(e) => setContent(e.target.value)}
+        placeholder="Nothing was selected — type or paste the prompt here"
+        autoFocus
+      />
+
+      {error && <div className="settings-warning">{error}</div>}
+
+      <div className="quick-add-actions">
+        <button className="btn-sm btn-ghost" onClick={() => void close()}>
+          Cancel
+        </button>
+        <button
+          className="btn-sm btn-primary"
+          onClick={() => void save()}
+          disabled={saving || content.trim() === ''}
+        >
+          Save
+        </button>
+      </div>
+    </div>
+  );
+}